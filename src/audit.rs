@@ -0,0 +1,122 @@
+//! Contains helper functions that write the audit log of the injections.
+//!
+//! The audit log is a file of JSON lines, one per DLL loaded or unloaded, that is only ever
+//! appended to.
+
+#![warn(missing_docs)]
+
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use serde_json::{Map, Value};
+use windows::Win32::Foundation::{ERROR_LOCK_VIOLATION, ERROR_SHARING_VIOLATION};
+
+use crate::winapi;
+
+
+/// Maximum number of attempts at opening the audit log while other processes hold it.
+const MAX_OPEN_ATTEMPTS: u32 = 20;
+
+/// Delay between two attempts at opening the audit log.
+const OPEN_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Method of the actions that unload a DLL with `FreeLibrary` in a remote thread.
+pub const FREE_LIBRARY_METHOD: &str = "CreateRemoteThread+FreeLibrary";
+
+/// Method of the actions that load a DLL with `LoadLibraryW` in an APC queued to the main thread
+/// of a process spawned in a suspended state.
+pub const EARLY_BIRD_METHOD: &str = "EarlyBird+QueueUserAPC+LoadLibraryW";
+
+
+/// Action on a target process recorded in the audit log.
+#[derive(Clone, Debug)]
+pub struct AuditEntry {
+    /// Command that performed the action (e.g. `load`).
+    pub command: &'static str,
+    /// Technique used to perform the action (e.g. the name of the injector).
+    pub method: &'static str,
+    /// PID of the target process, if it is known.
+    pub pid: Option<u32>,
+    /// Path of the DLL, or its name if it was unloaded by name.
+    pub module_path: String,
+    /// Outcome of the action (e.g. `loaded` or `failed`).
+    pub outcome: &'static str,
+    /// Reason why the action failed.
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    /// Returns the entry as a JSON object, along with the time of the action, the user that
+    /// performed it, the image path of the target process and the SHA-256 digest of the DLL.
+    ///
+    /// The fields that cannot be queried are null.
+    fn to_json(&self) -> Value {
+        let module_path: &Path = Path::new(&self.module_path);
+
+        let mut object: Map<String, Value> = Map::new();
+        object.insert("timestamp".to_owned(), humantime::format_rfc3339_millis(SystemTime::now()).to_string().into());
+        object.insert("user".to_owned(), winapi::get_process_user(std::process::id()).ok().into());
+        object.insert("command".to_owned(), self.command.into());
+        object.insert("method".to_owned(), self.method.into());
+        object.insert("pid".to_owned(), self.pid.into());
+        object.insert("image_path".to_owned(), self.pid.and_then(|pid| winapi::get_process_image_path(pid).ok()).into());
+        object.insert("module_path".to_owned(), self.module_path.clone().into());
+        object.insert("module_sha256".to_owned(), module_path.is_file().then(|| winapi::get_file_sha256(module_path).ok()).flatten().into());
+        object.insert("outcome".to_owned(), self.outcome.into());
+        object.insert("error".to_owned(), self.error.clone().into());
+
+        Value::Object(object)
+    }
+}
+
+
+/// Audit log that the actions on target processes are appended to.
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    /// Path of the audit log.
+    pub path: PathBuf,
+}
+
+impl AuditLog {
+    /// Appends an entry to the audit log.
+    ///
+    /// The entry is written as a single line with a single write, so that the entries of
+    /// concurrent invocations do not interleave.
+    pub fn append(&self, entry: &AuditEntry) -> io::Result<()> {
+        let line: String = format!("{}\n", entry.to_json());
+
+        let mut file: File = self.open()?;
+        file.write_all(line.as_bytes())?;
+        file.flush()
+    }
+
+    /// Opens the audit log for appending, creating it if missing.
+    ///
+    /// Retries for a short while if the file is locked by another process.
+    fn open(&self) -> io::Result<File> {
+        let mut attempts: u32 = 0;
+
+        loop {
+            match OpenOptions::new().create(true).append(true).open(&self.path) {
+                Ok(file) => return Ok(file),
+                Err(e) if is_sharing_violation(&e) && attempts + 1 < MAX_OPEN_ATTEMPTS => {
+                    attempts += 1;
+                    thread::sleep(OPEN_RETRY_DELAY);
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+
+/// Returns whether an I/O error is caused by another process holding the file.
+fn is_sharing_violation(e: &io::Error) -> bool {
+    e.raw_os_error().is_some_and(|code| {
+        code == ERROR_SHARING_VIOLATION.0 as i32 || code == ERROR_LOCK_VIOLATION.0 as i32
+    })
+}
@@ -0,0 +1,361 @@
+//! Contains helper functions that load the configuration file.
+//!
+//! The file uses a subset of TOML: one `key = value` pair per line, where values are strings,
+//! integers, booleans or single-line arrays of strings, and `#` starts a comment.
+
+#![warn(missing_docs)]
+
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+
+/// Name of the configuration file.
+pub const CONFIG_FILE_NAME: &str = "dll-spider.toml";
+
+
+/// Default options read from the configuration file.
+///
+/// The options passed on the command line always override the ones of the file.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// Number of times the verbose flag is passed.
+    pub verbose: Option<u8>,
+    /// Number of times the quiet flag is passed.
+    pub quiet: Option<u8>,
+    /// Time to wait for each DLL to load, in milliseconds.
+    pub timeout_ms: Option<u64>,
+    /// Output format of the summary of the commands that load DLLs, e.g. `json`.
+    pub output: Option<String>,
+    /// Processes excluded from the targets, by PID or name.
+    pub exclude: Vec<String>,
+    /// Whether the processes are matched by name strictly, as with `--exact`.
+    pub case_sensitive: Option<bool>,
+    /// Directories searched for the DLLs that are not found relative to the current directory.
+    pub module_path: Vec<PathBuf>,
+    /// File that the injections are recorded in.
+    pub audit_log: Option<PathBuf>,
+}
+
+impl Config {
+    /// Resolves the path of a DLL, searching the directories of the module search path in
+    /// order if the path does not exist.
+    ///
+    /// Returns `None` if the DLL is not found.
+    pub fn resolve_module(&self, path: &Path) -> Option<PathBuf> {
+        if path.exists() {
+            return Some(path.to_path_buf());
+        }
+        if path.is_absolute() {
+            return None;
+        }
+
+        self.module_path
+            .iter()
+            .map(|directory| directory.join(path))
+            .find(|candidate| candidate.is_file())
+            .map(|candidate| dunce::canonicalize(&candidate).unwrap_or(candidate))
+    }
+
+    /// Sets the option named by a key of the configuration file.
+    fn set(&mut self, key: &str, value: Value) -> Result<(), String> {
+        match key {
+            "verbose" => self.verbose = Some(value.into_integer()?),
+            "quiet" => self.quiet = Some(value.into_integer()?),
+            "timeout_ms" => self.timeout_ms = Some(value.into_integer()?),
+            "output" => self.output = Some(value.into_string()?),
+            "exclude" => self.exclude = value.into_string_array()?,
+            "case_sensitive" => self.case_sensitive = Some(value.into_boolean()?),
+            "module_path" => self.module_path = value.into_string_array()?.into_iter().map(PathBuf::from).collect(),
+            "audit_log" => self.audit_log = Some(PathBuf::from(value.into_string()?)),
+            _ => return Err("unknown key".to_owned()),
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Value of a key of the configuration file.
+#[derive(Clone, Debug)]
+enum Value {
+    /// Basic (`"..."`) or literal (`'...'`) string.
+    String(String),
+    /// Decimal integer.
+    Integer(i64),
+    /// `true` or `false`.
+    Boolean(bool),
+    /// Array of values.
+    Array(Vec<Value>),
+}
+
+impl Value {
+    /// Returns the name of the type of the value.
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Boolean(_) => "a boolean",
+            Value::Array(_) => "an array",
+        }
+    }
+
+    /// Converts the value to an integer, failing if it is not an integer in the range of `T`.
+    fn into_integer<T: TryFrom<i64>>(self) -> Result<T, String> {
+        match self {
+            Value::Integer(value) => T::try_from(value).map_err(|_| format!("{} is out of range", value)),
+            value => Err(format!("expected an integer, found {}", value.type_name())),
+        }
+    }
+
+    /// Converts the value to a boolean, failing if it is not one.
+    fn into_boolean(self) -> Result<bool, String> {
+        match self {
+            Value::Boolean(value) => Ok(value),
+            value => Err(format!("expected a boolean, found {}", value.type_name())),
+        }
+    }
+
+    /// Converts the value to a string, failing if it is not one.
+    fn into_string(self) -> Result<String, String> {
+        match self {
+            Value::String(value) => Ok(value),
+            value => Err(format!("expected a string, found {}", value.type_name())),
+        }
+    }
+
+    /// Converts the value to an array of strings, failing if it is not one.
+    fn into_string_array(self) -> Result<Vec<String>, String> {
+        let Value::Array(values) = self else {
+            return Err(format!("expected an array of strings, found {}", self.type_name()));
+        };
+
+        values
+            .into_iter()
+            .map(|value| value.into_string().map_err(|e| format!("{} in the array", e)))
+            .collect()
+    }
+}
+
+
+/// Returns the path of the configuration file, if one exists.
+///
+/// The file is looked up next to the executable, then in `%APPDATA%\dll-spider\`.
+pub fn find() -> Option<PathBuf> {
+    let executable_directory: Option<PathBuf> = env::current_exe()
+        .ok()
+        .and_then(|path| path.parent().map(Path::to_path_buf));
+    let appdata_directory: Option<PathBuf> = env::var_os("APPDATA").map(|path| PathBuf::from(path).join("dll-spider"));
+
+    [executable_directory, appdata_directory]
+        .into_iter()
+        .flatten()
+        .map(|directory| directory.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+
+/// Reads and parses a configuration file.
+///
+/// Errors name the file, and the line and the key at fault.
+pub fn load(path: &Path) -> Result<Config, String> {
+    let content: String = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read the configuration file {}: {}", path.display(), e))?;
+
+    parse(&content).map_err(|e| format!("{}:{}", path.display(), e))
+}
+
+
+/// Parses the content of a configuration file.
+fn parse(content: &str) -> Result<Config, String> {
+    let mut config: Config = Config::default();
+
+    for (i, line) in content.lines().enumerate() {
+        let line: &str = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("{}: expected `key = value`, found {:?}", i + 1, line));
+        };
+        let key: &str = key.trim();
+
+        parse_value(value)
+            .and_then(|value| config.set(key, value))
+            .map_err(|e| format!("{}: key {:?}: {}", i + 1, key, e))?;
+    }
+
+    Ok(config)
+}
+
+
+/// Parses a value, optionally followed by a comment.
+fn parse_value(input: &str) -> Result<Value, String> {
+    let (value, rest) = parse_next_value(input)?;
+
+    let rest: &str = rest.trim_start();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(format!("unexpected {:?} after the value", rest));
+    }
+
+    Ok(value)
+}
+
+
+/// Parses the value at the start of the input, and returns it with the rest of the input.
+fn parse_next_value(input: &str) -> Result<(Value, &str), String> {
+    let input: &str = input.trim_start();
+
+    if let Some(rest) = input.strip_prefix('"') {
+        let mut value: String = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::String(value), &rest[i + 1..])),
+                '\\' => match chars.next() {
+                    Some((_, '\\')) => value.push('\\'),
+                    Some((_, '"')) => value.push('"'),
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, c)) => return Err(format!("unsupported escape sequence \\{}", c)),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+
+        return Err("unterminated string".to_owned());
+    }
+
+    // literal strings have no escape sequences, which suits Windows paths.
+    if let Some(rest) = input.strip_prefix('\'') {
+        let (value, rest) = rest.split_once('\'').ok_or("unterminated string")?;
+        return Ok((Value::String(value.to_owned()), rest));
+    }
+
+    if let Some(mut rest) = input.strip_prefix('[') {
+        let mut values: Vec<Value> = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(rest) = rest.strip_prefix(']') {
+                return Ok((Value::Array(values), rest));
+            }
+
+            let (value, next) = parse_next_value(rest)?;
+            values.push(value);
+
+            rest = next.trim_start();
+            if let Some(next) = rest.strip_prefix(',') {
+                rest = next;
+            } else if !rest.starts_with(']') {
+                return Err("expected `,` or `]` in the array".to_owned());
+            }
+        }
+    }
+
+    let end: usize = input
+        .find(|c: char| c.is_whitespace() || c == ',' || c == ']' || c == '#')
+        .unwrap_or(input.len());
+    let (token, rest) = input.split_at(end);
+    if token.is_empty() {
+        return Err("missing value".to_owned());
+    }
+
+    match token {
+        "true" => return Ok((Value::Boolean(true), rest)),
+        "false" => return Ok((Value::Boolean(false), rest)),
+        _ => {},
+    }
+
+    token
+        .replace('_', "")
+        .parse::<i64>()
+        .map(|value| (Value::Integer(value), rest))
+        .map_err(|_| format!("invalid value {:?}", token))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_strings_with_escapes() {
+        let config: Config = parse(concat!(
+            r#"audit_log = "C:\\logs\\\"audit\"\t.jsonl""#, "\n",
+            r#"exclude = ['C:\app\a.exe', "b\n.exe"]"#, "\n",
+        )).unwrap();
+
+        assert_eq!(config.audit_log, Some(PathBuf::from("C:\\logs\\\"audit\"\t.jsonl")));
+        assert_eq!(config.exclude, ["C:\\app\\a.exe", "b\n.exe"]);
+        assert!(parse(r#"audit_log = "C:\logs""#).unwrap_err().contains("unsupported escape sequence \\l"));
+    }
+
+    #[test]
+    fn parses_integers() {
+        let config: Config = parse("verbose = 2\nquiet = 0\ntimeout_ms = 10_000\n").unwrap();
+
+        assert_eq!(config.verbose, Some(2));
+        assert_eq!(config.quiet, Some(0));
+        assert_eq!(config.timeout_ms, Some(10_000));
+        assert!(parse("timeout_ms = -1").unwrap_err().contains("-1 is out of range"));
+        assert!(parse("verbose = 256").unwrap_err().contains("256 is out of range"));
+    }
+
+    #[test]
+    fn parses_booleans() {
+        assert_eq!(parse("case_sensitive = true").unwrap().case_sensitive, Some(true));
+        assert_eq!(parse("case_sensitive = false").unwrap().case_sensitive, Some(false));
+        assert_eq!(parse("").unwrap().case_sensitive, None);
+        assert!(parse("case_sensitive = 1").unwrap_err().contains("expected a boolean, found an integer"));
+        assert!(parse("case_sensitive = True").unwrap_err().contains("invalid value \"True\""));
+    }
+
+    #[test]
+    fn parses_arrays() {
+        let config: Config = parse("exclude = [ 'a.exe' ,\"4\",]\nmodule_path = []").unwrap();
+
+        assert_eq!(config.exclude, ["a.exe", "4"]);
+        assert!(config.module_path.is_empty());
+        assert!(parse("exclude = ['a.exe', 4]").unwrap_err().contains("expected a string, found an integer in the array"));
+        assert!(parse("exclude = 'a.exe'").unwrap_err().contains("expected an array of strings, found a string"));
+    }
+
+    #[test]
+    fn ignores_comments() {
+        let config: Config = parse(concat!(
+            "# defaults\n",
+            "\n",
+            "  # indented comment\n",
+            "timeout_ms = 500 # trailing comment\n",
+            "exclude = ['#not-a-comment.exe'] # trailing comment\n",
+        )).unwrap();
+
+        assert_eq!(config.timeout_ms, Some(500));
+        assert_eq!(config.exclude, ["#not-a-comment.exe"]);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert_eq!(parse("verbose = 1\ncolor = 'never'").unwrap_err(), "2: key \"color\": unknown key");
+    }
+
+    #[test]
+    fn rejects_bad_syntax() {
+        let cases: [(&str, &str); 7] = [
+            ("verbose", "1: expected `key = value`, found \"verbose\""),
+            ("verbose =", "1: key \"verbose\": missing value"),
+            ("verbose = 1 2", "1: key \"verbose\": unexpected \"2\" after the value"),
+            ("audit_log = \"a.jsonl", "1: key \"audit_log\": unterminated string"),
+            ("audit_log = 'a.jsonl", "1: key \"audit_log\": unterminated string"),
+            ("exclude = ['a.exe' 'b.exe']", "1: key \"exclude\": expected `,` or `]` in the array"),
+            ("exclude = ['a.exe',", "1: key \"exclude\": missing value"),
+        ];
+
+        for (content, expected) in cases {
+            assert_eq!(parse(content).unwrap_err(), expected, "{:?}", content);
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Contains helper functions that generate the reference documentation of the CLI.
+//!
+//! The documentation is generated from the clap definition of the CLI, so that it covers every
+//! subcommand and option of the real CLI.
+
+#![warn(missing_docs)]
+
+
+use clap::{Arg, ArgAction, ArgGroup, Command};
+
+
+/// Exit code of the CLI and its meaning.
+pub type ExitCode = (i32, &'static str);
+
+
+/// Returns whether an argument is documented.
+/// 
+/// Hidden arguments and the arguments added by clap to print the help and the version are not.
+fn is_documented(arg: &Arg) -> bool {
+    !arg.is_hide_set() && !matches!(arg.get_action(), ArgAction::Help | ArgAction::HelpShort | ArgAction::HelpLong | ArgAction::Version)
+}
+
+
+/// Returns the arguments of a command that are documented, without the global arguments
+/// propagated from its parent.
+fn documented_args(command: &Command, is_root: bool) -> Vec<&Arg> {
+    command
+        .get_arguments()
+        .filter(|arg| is_documented(arg) && (is_root || !arg.is_global_set()))
+        .collect()
+}
+
+
+/// Returns the subcommands of a command that are documented.
+/// 
+/// Hidden subcommands and the `help` subcommand added by clap are not.
+fn documented_subcommands(command: &Command) -> Vec<&Command> {
+    command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set() && subcommand.get_name() != "help")
+        .collect()
+}
+
+
+/// Returns how an argument is passed on the command line (e.g. `-n, --by-name <NAME>`).
+fn arg_synopsis(arg: &Arg) -> String {
+    let value_names: Vec<String> = match arg.get_value_names() {
+        Some(names) => names.iter().map(|name| format!("<{}>", name)).collect(),
+        None => vec![format!("<{}>", arg.get_id().as_str().to_uppercase())],
+    };
+    let takes_value: bool = !matches!(arg.get_action(), ArgAction::SetTrue | ArgAction::SetFalse | ArgAction::Count);
+    let is_repeated: bool = matches!(arg.get_action(), ArgAction::Append | ArgAction::Count);
+
+    let mut names: Vec<String> = Vec::new();
+    if let Some(short) = arg.get_short() {
+        names.push(format!("-{}", short));
+    }
+    if let Some(long) = arg.get_long() {
+        names.push(format!("--{}", long));
+    }
+
+    let mut synopsis: String = if arg.is_positional() {
+        value_names.join(" ")
+    } else if takes_value {
+        format!("{} {}", names.join(", "), value_names.join(" "))
+    } else {
+        names.join(", ")
+    };
+    if is_repeated {
+        synopsis.push_str("...");
+    }
+
+    synopsis
+}
+
+
+/// Returns the description of an argument, followed by its default value, its possible values
+/// and the environment variable it is read from.
+fn arg_description(arg: &Arg) -> String {
+    let mut description: String = arg.get_help().map(ToString::to_string).unwrap_or_default();
+    let mut append = |text: String| {
+        if !description.is_empty() {
+            description.push(' ');
+        }
+        description.push_str(&text);
+    };
+
+    let default_values: Vec<String> = arg.get_default_values().iter().map(|value| value.to_string_lossy().into_owned()).collect();
+    if !default_values.is_empty() && !matches!(arg.get_action(), ArgAction::SetTrue | ArgAction::SetFalse | ArgAction::Count) {
+        append(format!("[default: {}]", default_values.join(", ")));
+    }
+
+    let possible_values: Vec<String> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_owned())
+        .collect();
+    if !possible_values.is_empty() && arg.get_action().takes_values() {
+        append(format!("[possible values: {}]", possible_values.join(", ")));
+    }
+
+    if let Some(env) = arg.get_env() {
+        append(format!("[env: {}]", env.to_string_lossy()));
+    }
+
+    description
+}
+
+
+/// Describes the constraint of a group of arguments (e.g. exactly one of `--by-name` and
+/// `--by-pid` is required), if it has one.
+fn group_description(command: &Command, group: &ArgGroup) -> Option<String> {
+    let names: Vec<String> = group
+        .get_args()
+        .filter_map(|id| command.get_arguments().find(|arg| arg.get_id() == id))
+        .filter(|arg| is_documented(arg))
+        .map(|arg| arg.get_long().map_or_else(|| arg_synopsis(arg), |long| format!("--{}", long)))
+        .collect();
+    if names.len() < 2 {
+        return None;
+    }
+
+    let names: String = names.iter().map(|name| format!("`{}`", name)).collect::<Vec<String>>().join(", ");
+    match (group.is_required_set(), group.clone().is_multiple()) {
+        (true, false) => Some(format!("Exactly one of {} is required.", names)),
+        (true, true) => Some(format!("At least one of {} is required.", names)),
+        (false, false) => Some(format!("At most one of {} can be passed.", names)),
+        (false, true) => None,
+    }
+}
+
+
+/// Returns the usage line of a command, without the `Usage:` prefix.
+fn usage(command: &Command) -> String {
+    let usage: String = command.clone().render_usage().to_string();
+
+    usage.trim_start_matches("Usage:").trim().to_owned()
+}
+
+
+/// Returns the long description of a command, falling back to its short description.
+fn about(command: &Command) -> String {
+    command
+        .get_long_about()
+        .or_else(|| command.get_about())
+        .map(ToString::to_string)
+        .unwrap_or_default()
+}
+
+
+/// Escapes text for roff.
+fn roff_escape(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let line: String = line.replace('\\', "\\e").replace('-', "\\-");
+            // lines starting with a dot or a quote would be parsed as requests.
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{}", line)
+            } else {
+                line
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+
+/// Appends the options of a command to a man page.
+fn man_options(page: &mut String, command: &Command, is_root: bool) {
+    for arg in documented_args(command, is_root) {
+        page.push_str(&format!(".TP\n\\fB{}\\fR\n{}\n", roff_escape(&arg_synopsis(arg)), roff_escape(&arg_description(arg))));
+    }
+    for group in command.get_groups() {
+        if let Some(description) = group_description(command, group) {
+            page.push_str(&format!(".PP\n{}\n", roff_escape(&description.replace('`', ""))));
+        }
+    }
+}
+
+
+/// Generates the man page of a command, in roff format.
+/// 
+/// The command must be built, so that the usage of its subcommands is complete.
+pub fn man_page(command: &Command, exit_codes: &[ExitCode]) -> String {
+    let name: &str = command.get_name();
+    let mut page: String = String::new();
+
+    page.push_str(&format!(
+        ".TH {} 1 \"\" \"{} {}\"\n",
+        roff_escape(&name.to_uppercase()),
+        roff_escape(name),
+        roff_escape(command.get_version().unwrap_or_default()),
+    ));
+    page.push_str(&format!(".SH NAME\n{} \\- {}\n", roff_escape(name), roff_escape(&about(command))));
+    page.push_str(&format!(".SH SYNOPSIS\n{}\n", roff_escape(&usage(command))));
+
+    page.push_str(".SH OPTIONS\n");
+    man_options(&mut page, command, true);
+
+    page.push_str(".SH COMMANDS\n");
+    for subcommand in documented_subcommands(command) {
+        page.push_str(&format!(".SS {}\n{}\n", roff_escape(subcommand.get_name()), roff_escape(&about(subcommand))));
+        let aliases: Vec<&str> = subcommand.get_visible_aliases().collect();
+        if !aliases.is_empty() {
+            page.push_str(&format!(".PP\nAliases: {}\n", roff_escape(&aliases.join(", "))));
+        }
+        page.push_str(&format!(".PP\n\\fBUsage:\\fR {}\n", roff_escape(&usage(subcommand))));
+        man_options(&mut page, subcommand, false);
+    }
+
+    page.push_str(".SH \"EXIT STATUS\"\n");
+    for (code, meaning) in exit_codes {
+        page.push_str(&format!(".TP\n\\fB{}\\fR\n{}\n", code, roff_escape(meaning)));
+    }
+
+    page
+}
+
+
+/// Escapes text for a cell of a Markdown table.
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+
+/// Appends the options of a command to a Markdown reference.
+fn markdown_options(reference: &mut String, command: &Command, is_root: bool) {
+    let args: Vec<&Arg> = documented_args(command, is_root);
+    if !args.is_empty() {
+        reference.push_str("| Option | Description |\n|--------|-------------|\n");
+        for arg in args {
+            reference.push_str(&format!("| `{}` | {} |\n", markdown_cell(&arg_synopsis(arg)), markdown_cell(&arg_description(arg))));
+        }
+        reference.push('\n');
+    }
+
+    for group in command.get_groups() {
+        if let Some(description) = group_description(command, group) {
+            reference.push_str(&format!("{}\n\n", description));
+        }
+    }
+}
+
+
+/// Generates the reference of a command, in Markdown format.
+/// 
+/// The command must be built, so that the usage of its subcommands is complete.
+pub fn markdown(command: &Command, exit_codes: &[ExitCode]) -> String {
+    let name: &str = command.get_name();
+    let mut reference: String = String::new();
+
+    reference.push_str(&format!("# {}\n\n{}\n\n", name, about(command)));
+    reference.push_str(&format!("```\n{}\n```\n\n", usage(command)));
+
+    reference.push_str("## Global options\n\n");
+    markdown_options(&mut reference, command, true);
+
+    reference.push_str("## Commands\n\n");
+    for subcommand in documented_subcommands(command) {
+        reference.push_str(&format!("### {} {}\n\n{}\n\n", name, subcommand.get_name(), about(subcommand)));
+        let aliases: Vec<&str> = subcommand.get_visible_aliases().collect();
+        if !aliases.is_empty() {
+            reference.push_str(&format!("Aliases: {}\n\n", aliases.join(", ")));
+        }
+        reference.push_str(&format!("```\n{}\n```\n\n", usage(subcommand)));
+        markdown_options(&mut reference, subcommand, false);
+    }
+
+    reference.push_str("## Exit codes\n\n| Code | Meaning |\n|------|---------|\n");
+    for (code, meaning) in exit_codes {
+        reference.push_str(&format!("| {} | {} |\n", code, markdown_cell(meaning)));
+    }
+
+    reference
+}
@@ -6,10 +6,72 @@
 
 
 use std::env;
-use std::io::Write;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
 
-use env_logger::{Builder, Env};
-use log::Level;
+use env_logger::{Builder, Env, Logger};
+use log::{warn, Level, LevelFilter, Log, Metadata, Record};
+
+
+/// Configuration of the global logger.
+#[derive(Debug, Default)]
+pub struct LogConfig {
+    /// Minimum logging level of the console. If None, defaults to the value contained in the
+    /// RUST_LOG environment variable.
+    pub level: Option<LevelFilter>,
+    /// File that the records are appended to, in addition to the console.
+    pub file: Option<PathBuf>,
+    /// Whether the records logged to the console are prefixed with a timestamp.
+    pub timestamps: bool,
+}
+
+
+/// Minimum logging level of the log file, regardless of the one of the console.
+const FILE_LEVEL: Level = Level::Debug;
+
+
+/// Logger that writes the records both to the console and to a log file.
+struct TeeLogger {
+    /// Logger of the console.
+    console: Logger,
+    /// Log file, if one could be opened.
+    file: Option<Mutex<File>>,
+}
+
+impl Log for TeeLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.console.enabled(metadata) || (self.file.is_some() && metadata.level() <= FILE_LEVEL)
+    }
+
+    fn log(&self, record: &Record) {
+        self.console.log(record);
+
+        if let Some(file) = &self.file {
+            if record.level() <= FILE_LEVEL {
+                // a poisoned lock only means that another thread panicked while logging.
+                let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+                let _ = writeln!(
+                    file,
+                    "{} [{}] {}: {}.",
+                    humantime::format_rfc3339_millis(SystemTime::now()),
+                    record.level(),
+                    record.target(),
+                    record.args(),
+                );
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap_or_else(|e| e.into_inner()).flush();
+        }
+    }
+}
 
 
 /// Initializes the global logger.
@@ -18,9 +80,16 @@ use log::Level;
 /// to the value contained in the RUST_LOG environment variable. If RUST_LOG is not set,
 /// the logger will default to log `info` and above.
 /// 
+/// Records are prefixed with an RFC 3339 timestamp, unless `timestamps` is unset, and with
+/// their level and target. The level is colored only if stderr is a terminal.
+/// 
+/// If a log file is passed, every record at the `debug` level and above is also appended to
+/// it, regardless of the level of the console. If the file cannot be opened, the error is
+/// logged and only the console is used.
+/// 
 /// # Example
 /// ```
-/// logging::init(Level::Warn);
+/// logging::init(LogConfig { level: Some(LevelFilter::Warn), file: None, timestamps: true });
 /// 
 /// trace!("helloworld!"); // no output.
 /// debug!("helloworld!"); // no output.
@@ -28,43 +97,119 @@ use log::Level;
 /// warn!("helloworld!");
 /// error!("helloworld!");
 /// ```
-pub fn init(level: Option<Level>) {
-    if let Some(l) = level { 
+pub fn init(config: LogConfig) {
+    if let Some(l) = config.level { 
         set_logging_level_before_builder_init(l);
     }
 
-    Builder::from_env(Env::default().default_filter_or("info"))
-        .format(|buf, record| {
+    let timestamps: bool = config.timestamps;
+    let console: Logger = Builder::from_env(Env::default().default_filter_or("info"))
+        .format(move |buf, record| {
+            if timestamps {
+                write!(buf, "{} ", buf.timestamp_millis())?;
+            }
             writeln!(
                 buf,
-                "[{}] {}.",
-                record.level(),
+                "[{}] {}: {}.",
+                buf.default_styled_level(record.level()),
+                record.target(),
                 record.args()
             )
         })
-        .init();
+        .build();
+
+    let file: io::Result<Option<File>> = config.file
+        .as_ref()
+        .map(|path| OpenOptions::new().create(true).append(true).open(path))
+        .transpose();
+    let (file, file_error) = match file {
+        Ok(file) => (file, None),
+        Err(e) => (None, Some(e)),
+    };
+
+    let max_level: LevelFilter = if file.is_some() {
+        console.filter().max(FILE_LEVEL.to_level_filter())
+    } else {
+        console.filter()
+    };
+    log::set_max_level(max_level);
+    log::set_boxed_logger(Box::new(TeeLogger { console, file: file.map(Mutex::new) }))
+        .expect("the logger is initialized once");
+
+    if let Some(e) = file_error {
+        warn!("{}", format!(
+            "failed to open the log file {}: {}, logging to the console only",
+            config.file.unwrap_or_default().display(),
+            e,
+        ));
+    }
 }
 
 
 /// Sets the RUST_LOG environment variable.
 /// 
 /// To see a change, this function must be called before the `env_logger::Builder` is initialized.
-fn set_logging_level_before_builder_init(level: Level) {
+fn set_logging_level_before_builder_init(level: LevelFilter) {
     unsafe {
         env::set_var("RUST_LOG", level.as_str());
     }
 }
 
 
-/// Converts a u8 integer to a logging level.
+/// Computes the logging level from the number of times the verbose and quiet flags were passed.
 /// 
-/// Defaults to `trace` if the conversion fails.
-pub fn u8_to_level(value: u8) -> Level {
-    return match value {
-        1 => log::Level::Debug,
-        2 => log::Level::Info,
-        3 => log::Level::Warn,
-        4 => log::Level::Error,
-        _ => log::Level::Trace,
+/// Starting from `info`, each verbose flag increases the verbosity (`debug`, then `trace`),
+/// and each quiet flag decreases it (`warn`, `error`, then off). Returns None if neither flag
+/// was passed, so that the RUST_LOG environment variable applies.
+pub fn level_from_flags(verbose: u8, quiet: u8) -> Option<LevelFilter> {
+    if verbose == 0 && quiet == 0 {
+        return None;
+    }
+
+    let levels: [LevelFilter; 6] = [
+        LevelFilter::Off,
+        LevelFilter::Error,
+        LevelFilter::Warn,
+        LevelFilter::Info,
+        LevelFilter::Debug,
+        LevelFilter::Trace,
+    ];
+    let index: i16 = 3 + verbose as i16 - quiet as i16;
+
+    Some(levels[index.clamp(0, levels.len() as i16 - 1) as usize])
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defers_to_rust_log_without_flags() {
+        assert_eq!(level_from_flags(0, 0), None);
+    }
+
+    #[test]
+    fn increases_verbosity_up_to_trace() {
+        assert_eq!(level_from_flags(1, 0), Some(LevelFilter::Debug));
+        assert_eq!(level_from_flags(2, 0), Some(LevelFilter::Trace));
+        assert_eq!(level_from_flags(3, 0), Some(LevelFilter::Trace));
+        assert_eq!(level_from_flags(u8::MAX, 0), Some(LevelFilter::Trace));
+    }
+
+    #[test]
+    fn decreases_verbosity_down_to_off() {
+        assert_eq!(level_from_flags(0, 1), Some(LevelFilter::Warn));
+        assert_eq!(level_from_flags(0, 2), Some(LevelFilter::Error));
+        assert_eq!(level_from_flags(0, 3), Some(LevelFilter::Off));
+        assert_eq!(level_from_flags(0, u8::MAX), Some(LevelFilter::Off));
+    }
+
+    #[test]
+    fn cancels_out_verbose_and_quiet_flags() {
+        assert_eq!(level_from_flags(1, 1), Some(LevelFilter::Info));
+        assert_eq!(level_from_flags(3, 3), Some(LevelFilter::Info));
+        assert_eq!(level_from_flags(2, 1), Some(LevelFilter::Debug));
+        assert_eq!(level_from_flags(1, 2), Some(LevelFilter::Warn));
     }
 }
@@ -3,15 +3,52 @@
 #![warn(missing_docs)]
 
 
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::num::ParseIntError;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
-use clap::{Args, Parser, Subcommand};
-use log::debug;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap::builder::BoolishValueParser;
+use clap::parser::ValueSource;
+use clap_complete::Shell;
+use log::{debug, error, info, warn};
+use regex::{Regex, RegexBuilder};
+use serde_json::{Map, Value};
+use tabled::Table;
 use tabled::builder::Builder;
-use tabled::settings::{Alignment, Modify, Style, object::Segment};
+use tabled::settings::{Alignment, Color, Modify, Style, object::{Rows, Segment}};
+use windows::Win32::Foundation::{
+    ERROR_ACCESS_DENIED,
+    ERROR_BAD_EXE_FORMAT,
+    ERROR_CANCELLED,
+    ERROR_EXE_MACHINE_TYPE_MISMATCH,
+    ERROR_FILE_NOT_FOUND,
+    ERROR_INVALID_IMAGE_HASH,
+    ERROR_INVALID_PARAMETER,
+    ERROR_NOT_FOUND,
+    ERROR_OPERATION_ABORTED,
+    ERROR_TIMEOUT,
+};
 
 
+mod audit;
+mod config;
+mod docs;
 mod logging;
+mod pe;
+mod retry;
+mod staging;
 mod winapi;
 
 
@@ -20,11 +57,51 @@ mod winapi;
 #[command(version, about, long_about = None)]
 #[command(propagate_version = true)]
 struct Cli {
-    /// Set the verbosity level. option is additive, and can be used up to 5 times (trace/debug/info/warn/error). (default: info)
-    #[arg(short, long, action = clap::ArgAction::Count, value_parser = clap::value_parser!(u8).range(0..=5))]
+    /// Increase the verbosity level. option is additive (debug/trace). (default: info)
+    #[arg(short, long, action = clap::ArgAction::Count)]
     #[clap(global = true)]
     verbose: u8,
 
+    /// Decrease the verbosity level. option is additive (warn/error/off). (default: info)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    #[clap(global = true)]
+    quiet: u8,
+
+    /// Append the logs to a file, at the debug level regardless of the verbosity level.
+    #[arg(long, value_name = "PATH", env = "DLL_SPIDER_LOG_FILE")]
+    #[clap(global = true)]
+    log_file: Option<PathBuf>,
+
+    /// Do not prefix the logs printed on stderr with a timestamp.
+    #[arg(long)]
+    #[clap(global = true)]
+    log_no_time: bool,
+
+    /// Record the DLLs loaded and unloaded by the load, reload, spawn and eject commands in this file.
+    #[arg(long, value_name = "PATH")]
+    #[clap(global = true)]
+    audit_log: Option<PathBuf>,
+
+    /// Read the default options from this configuration file.
+    #[arg(long, value_name = "PATH", conflicts_with = "no_config")]
+    #[clap(global = true)]
+    config: Option<PathBuf>,
+
+    /// Ignore the configuration file.
+    #[arg(long)]
+    #[clap(global = true)]
+    no_config: bool,
+
+    /// Do not enable SeDebugPrivilege, which the load, reload, watch, eject, check and enum commands enable to open the processes of other users.
+    #[arg(long)]
+    #[clap(global = true)]
+    no_debug_privilege: bool,
+
+    /// When to color the tables. `auto` colors them if stdout is a terminal and `NO_COLOR` is not set.
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    #[clap(global = true)]
+    color: ColorChoice,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -32,74 +109,3460 @@ struct Cli {
 #[derive(Debug)]
 #[derive(Args)]
 #[group(required = true, multiple = false)]
-struct Process {
+struct ProcessSelector {
     /// Name of the target process.
     #[arg(short, long = "by-name")]
     name: Option<String>,
 
-    /// PID of the target process.
+    /// PID of the target process. Can be repeated by the commands that accept multiple targets.
     #[arg(short, long = "by-pid")]
-    pid: Option<u32>,
+    pid: Vec<u32>,
+
+    /// Full path of the executable of the target process (case-insensitive).
+    #[arg(long = "by-path")]
+    path: Option<PathBuf>,
+
+    /// Substring of the title of a top-level window of the target process (case-insensitive).
+    #[arg(short, long = "by-window")]
+    window: Option<String>,
+
+    /// File listing the target processes, one PID or name per line (`-` for stdin). Only
+    /// accepted by the commands that accept multiple targets.
+    #[arg(long)]
+    targets_file: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+#[derive(Args)]
+struct Process {
+    #[command(flatten)]
+    selector: ProcessSelector,
+
+    /// Also match invisible and tool windows with `--by-window`.
+    #[arg(long, requires = "window")]
+    include_hidden: bool,
+
+    /// Interpret `--by-name` as a regex matched against the whole name (case-insensitive
+    /// unless the pattern starts with `(?-i)`).
+    #[arg(long, requires = "name")]
+    name_regex: bool,
+
+    /// Compare `--by-name` strictly with the names of the processes, including case and the
+    /// `.exe` extension.
+    #[arg(long, requires = "name", conflicts_with = "name_regex")]
+    exact: bool,
+
+    /// If the name matches multiple processes, select the one started last.
+    #[arg(long, requires = "name", conflicts_with = "oldest")]
+    newest: bool,
+
+    /// If the name matches multiple processes, select the one started first.
+    #[arg(long, requires = "name")]
+    oldest: bool,
+
+    /// Skip a process matching a name (with the same rules as `--by-name`) or a PID. Can be repeated.
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Whether `--by-name` and `--exclude` compare the names case-sensitively, as set by the
+    /// configuration file.
+    #[arg(skip)]
+    case_sensitive: bool,
+}
+
+impl Process {
+    /// Returns the pattern that selects the target processes by name, if one was passed.
+    /// 
+    /// Exits with a usage error if the name is not a valid regex in regex mode.
+    fn name_pattern(&self) -> Option<winapi::NamePattern> {
+        let name: &str = self.selector.name.as_deref()?;
+        if self.exact {
+            return Some(winapi::NamePattern::Exact(name.to_owned()));
+        }
+        if !self.name_regex {
+            return Some(winapi::NamePattern::new(name, self.case_sensitive));
+        }
+
+        match winapi::NamePattern::regex(name) {
+            Ok(pattern) => Some(pattern),
+            Err(e) => usage_error(e.message()),
+        }
+    }
+
+    /// Returns the PID of the target process.
+    /// 
+    /// If a name matches multiple processes, selects the newest or oldest one if requested,
+    /// or asks the user to pick one when stdin is a terminal. Exits with a usage error if
+    /// multiple PIDs were passed.
+    fn pid(&self) -> windows::core::Result<u32> {
+        if let Some(pattern) = self.name_pattern() {
+            let pids: Vec<u32> = self.exclude(winapi::find_processes_by_name(&pattern)?)?;
+            return match pids.as_slice() {
+                [pid] => Ok(*pid),
+                _ if self.newest || self.oldest => Ok(select_by_start_time(&Candidate::from_pids(&pids), self.newest)),
+                _ if io::stdin().is_terminal() => pick_process(&Candidate::from_pids(&pids)),
+                // fails listing the candidates that are not excluded.
+                _ => Err(winapi::ambiguous_process_error(&pattern, &pids)),
+            };
+        }
+        if let Some(path) = &self.selector.path {
+            return winapi::find_process_by_path(path);
+        }
+        if let Some(title) = &self.selector.window {
+            return winapi::find_process_by_window(title, self.include_hidden);
+        }
+
+        match self.selector.pid.as_slice() {
+            [pid] if self.selector.targets_file.is_none() => Ok(*pid),
+            _ => usage_error("this command accepts a single target process"),
+        }
+    }
+
+    /// Returns the PIDs of the target processes.
+    /// 
+    /// If `all_matching` is set, a name or a path selects every process with that name or
+    /// running that executable.
+    fn pids(&self, all_matching: bool) -> windows::core::Result<Vec<u32>> {
+        if all_matching {
+            if let Some(pattern) = self.name_pattern() {
+                return self.exclude(winapi::find_processes_by_name(&pattern)?);
+            }
+            if let Some(path) = &self.selector.path {
+                return self.exclude(winapi::find_processes_by_path(path)?);
+            }
+        }
+
+        match self.selector.pid.as_slice() {
+            [] => Ok(vec![self.pid()?]),
+            pids => self.exclude(pids.to_vec()),
+        }
+    }
+
+    /// Returns the PIDs of the running target processes.
+    /// 
+    /// Unlike `pids`, does not fail if no target process is running.
+    fn poll(&self) -> Vec<u32> {
+        let pids: Vec<u32> = if let Some(pattern) = self.name_pattern() {
+            poll_processes(&pattern)
+        } else if let Some(path) = &self.selector.path {
+            winapi::find_processes_by_path(path).unwrap_or_default()
+        } else if let Some(title) = &self.selector.window {
+            winapi::find_process_by_window(title, self.include_hidden).map(|pid| vec![pid]).unwrap_or_default()
+        } else {
+            winapi::get_process_list()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|entry| entry.pid)
+                .filter(|pid| self.selector.pid.contains(pid))
+                .collect()
+        };
+
+        pids.into_iter().filter(|&pid| !self.is_excluded(pid)).collect()
+    }
+
+    /// Returns whether a process was excluded with `--exclude`.
+    fn is_excluded(&self, pid: u32) -> bool {
+        if self.exclude.is_empty() {
+            return false;
+        }
+
+        let process_name: Option<String> = winapi::get_process_name(pid).ok();
+        let excluded: bool = self.exclude.iter().any(|exclude| match exclude.parse::<u32>() {
+            Ok(excluded_pid) => excluded_pid == pid,
+            Err(_) => process_name.as_ref().is_some_and(|name| winapi::NamePattern::new(exclude, self.case_sensitive).matches(name)),
+        });
+        if excluded {
+            debug!("{}", format!("excluding process ({}) {}", pid, process_name.unwrap_or_default()));
+        }
+
+        excluded
+    }
+
+    /// Removes the processes excluded with `--exclude` from a list of PIDs.
+    /// 
+    /// Fails with `ERROR_NOT_FOUND` if every process was excluded.
+    fn exclude(&self, pids: Vec<u32>) -> windows::core::Result<Vec<u32>> {
+        let pids: Vec<u32> = pids.into_iter().filter(|&pid| !self.is_excluded(pid)).collect();
+        if pids.is_empty() {
+            return Err(no_targets_left_error());
+        }
+
+        Ok(pids)
+    }
+
+    /// Returns the target processes, read from the targets file if one was passed.
+    /// 
+    /// Unlike `pids`, a target that cannot be resolved does not exit, but is returned as an
+    /// error holding its line of the targets file, so that the remaining targets can still
+    /// be attempted.
+    fn targets(&self, all_matching: bool) -> windows::core::Result<Vec<Target>> {
+        let Some(path) = &self.selector.targets_file else {
+            return Ok(self.pids(all_matching)?.into_iter().map(Ok).collect());
+        };
+
+        let content: io::Result<String> = if path.as_os_str() == "-" {
+            io::read_to_string(io::stdin())
+        } else {
+            fs::read_to_string(path)
+        };
+        let content: String = content.map_err(|e| windows::core::Error::new(
+            e.raw_os_error().map_or(windows::core::HRESULT(-1), |code| windows::core::HRESULT::from_win32(code as u32)),
+            format!("failed to read the targets file {}: {}", path.display(), e),
+        ))?;
+
+        let mut targets: Vec<Target> = Vec::new();
+        for line in content.lines() {
+            let target: &str = line.split('#').next().unwrap_or_default().trim();
+            if target.is_empty() {
+                continue;
+            }
+
+            if let Ok(pid) = target.parse::<u32>() {
+                targets.push(Ok(pid));
+                continue;
+            }
+
+            let pattern = winapi::NamePattern::new(target, self.case_sensitive);
+            // the excluded processes do not make a name ambiguous.
+            let pids = winapi::find_processes_by_name(&pattern)
+                .map(|pids| pids.into_iter().filter(|&pid| !self.is_excluded(pid)).collect::<Vec<u32>>())
+                .and_then(|pids| match pids.as_slice() {
+                    [_, _, ..] if !all_matching => Err(winapi::ambiguous_process_error(&pattern, &pids)),
+                    _ => Ok(pids),
+                });
+            match pids {
+                Ok(pids) => targets.extend(pids.into_iter().map(Ok)),
+                Err(e) => {
+                    error!("{}", format!("failed to resolve target {:?}: {}", target, e.message()));
+                    targets.push(Err(target.to_owned()));
+                },
+            }
+        }
+
+        if targets.is_empty() {
+            return Err(windows::core::Error::new(ERROR_NOT_FOUND.to_hresult(), format!("no targets in {}", path.display())));
+        }
+
+        targets.retain(|target| !matches!(target, Ok(pid) if self.is_excluded(*pid)));
+        if targets.is_empty() {
+            return Err(no_targets_left_error());
+        }
+
+        Ok(targets)
+    }
+}
+
+
+/// Converts a path passed on the command line to a string, failing with
+/// `ERROR_INVALID_PARAMETER` if it is not valid UTF-8.
+fn path_to_str(path: &Path) -> windows::core::Result<&str> {
+    path.to_str().ok_or_else(|| windows::core::Error::new(
+        ERROR_INVALID_PARAMETER.to_hresult(),
+        format!("path {} is not valid UTF-8", path.display()),
+    ))
+}
+
+
+/// Returns the error of the commands whose target processes were all excluded with `--exclude`.
+fn no_targets_left_error() -> windows::core::Error {
+    windows::core::Error::new(ERROR_NOT_FOUND.to_hresult(), "no targets remain after applying --exclude")
+}
+
+
+/// Exits with a usage error formatted by clap, and its exit code 2, for the invalid arguments
+/// that clap cannot check by itself.
+fn usage_error(message: impl std::fmt::Display) -> ! {
+    Cli::command().error(clap::error::ErrorKind::ValueValidation, message).exit()
+}
+
+
+#[derive(Debug)]
+#[derive(Args)]
+struct InitExport {
+    /// Function exported by the DLLs that is called once each of them is loaded (x64 only).
+    #[arg(long, value_name = "EXPORT", conflicts_with = "method")]
+    init_export: Option<String>,
+
+    /// String whose address is passed to the function of --init-export.
+    #[arg(long, value_name = "ARG", requires = "init_export")]
+    init_arg: Option<String>,
+
+    /// File shared with the DLLs through a named file mapping, whose name is passed to the function of --init-export.
+    #[arg(long, value_name = "FILE", requires = "init_export", conflicts_with = "init_arg")]
+    config_file: Option<PathBuf>,
+
+    /// Create a named pipe, pass its name to the function of --init-export, and bridge it to stdin and stdout once the DLL connects.
+    #[arg(long, requires = "init_export", conflicts_with_all = ["init_arg", "config_file", "all_matching"])]
+    pipe: bool,
+
+    /// Time to wait for the DLL to connect to the pipe of --pipe, in milliseconds (the value of --timeout by default).
+    #[arg(long, value_name = "MS", requires = "pipe")]
+    pipe_timeout: Option<u64>,
+}
+
+#[derive(Debug)]
+#[derive(Args)]
+struct Staging {
+    /// Load copies of the DLLs staged in %TEMP%\dll-spider, so that the originals are not locked by the targets.
+    #[arg(long)]
+    copy: bool,
+
+    /// Load copies of the DLLs staged in this directory instead of %TEMP%\dll-spider.
+    #[arg(long, value_name = "DIR")]
+    copy_to: Option<PathBuf>,
+
+    /// Path the targets load each DLL from, once per DLL in order, when it differs from the local path (e.g. in a container).
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["copy", "copy_to"])]
+    remote_path: Vec<String>,
+}
+
+#[derive(Debug)]
+#[derive(Args)]
+struct Retry {
+    /// Retry this many times when the target denies access or its modules cannot be read yet, e.g. right after it started.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retry: u32,
+
+    /// Delay before the first retry, in milliseconds, doubled before each of the next ones.
+    #[arg(long, value_name = "MS", default_value_t = retry::DEFAULT_RETRY_DELAY_MS)]
+    retry_delay: u64,
 }
 
+impl Retry {
+    /// Returns the retry policy set by the options.
+    fn policy(&self) -> retry::RetryPolicy {
+        retry::RetryPolicy { retries: self.retry, delay: Duration::from_millis(self.retry_delay) }
+    }
+}
+
+
+/// PID of a target process, or the name of a target that could not be resolved.
+type Target = std::result::Result<u32, String>;
+
 #[derive(Debug)]
 #[derive(Subcommand)]
 enum Commands {
-    /// Load a DLL inside a target process.
+    /// Load one or more DLLs inside a target process.
     Load {
         #[command(flatten)]
         process: Process,
 
-        /// Path to the DLL to load.
+        /// Paths to the DLLs to load, in load order.
+        #[arg(num_args = 1.., required = true, value_parser = parse_module_path)]
+        modules: Vec<PathBuf>,
+
+        /// Keep loading the remaining DLLs after one fails.
+        #[arg(long)]
+        keep_going: bool,
+
+        /// Stop loading the remaining DLLs as soon as one fails (the default).
+        #[arg(long, hide = true, conflicts_with = "keep_going")]
+        stop_on_error: bool,
+
+        /// Skip the check on the architecture of the DLLs and of the target process, and allow
+        /// injecting into critical system processes.
+        #[arg(long)]
+        force: bool,
+
+        /// Try to load the DLLs even if the mitigation policies of the target process (Microsoft-signed images only, arbitrary code guard) reject them.
+        #[arg(long)]
+        ignore_mitigations: bool,
+
+        /// Time to wait for each DLL to load, in milliseconds.
+        #[arg(long, env = "DLL_SPIDER_TIMEOUT", default_value_t = winapi::DEFAULT_LOAD_TIMEOUT_MS)]
+        timeout: u64,
+
+        /// Terminate the thread loading a DLL if it is still running after the timeout.
+        #[arg(long)]
+        kill_on_timeout: bool,
+
+        /// Size of the stack of the threads loading the DLLs, in bytes, rounded up to a whole page (the stack size of the target executable by default).
+        #[arg(long, value_name = "BYTES", value_parser = parse_stack_size)]
+        stack_size: Option<usize>,
+
+        /// Creation flags of the threads loading the DLLs, e.g. STACK_SIZE_PARAM_IS_A_RESERVATION.
+        #[arg(long, value_name = "FLAG", value_delimiter = ',', value_parser = parse_thread_flag)]
+        thread_flags: Vec<u32>,
+
+        /// Create the threads loading the DLLs suspended, print their ID, and resume them once Enter is pressed.
+        #[arg(long)]
+        start_suspended: bool,
+
+        /// Resume the suspended threads after this many seconds without waiting for Enter.
+        #[arg(long, value_name = "SECS", requires = "start_suspended")]
+        resume_after: Option<u64>,
+
+        /// Keep the memory holding the DLL paths in the targets, and print its address, instead of zeroing and freeing it.
+        #[arg(long, conflicts_with = "fire_and_forget")]
+        no_free: bool,
+
+        /// Free the memory holding the DLL paths in the targets without zeroing it first.
+        #[arg(long, conflicts_with_all = ["no_free", "fire_and_forget"])]
+        no_zero: bool,
+
+        /// Start the threads loading the DLLs without waiting for them, leaking the memory holding the DLL paths.
+        #[arg(long, conflicts_with_all = ["start_suspended", "kill_on_timeout", "init_export", "load_flags"])]
+        fire_and_forget: bool,
+
+        /// Do not ask for confirmation before injecting into processes of another user or session.
+        #[arg(short, long, env = "DLL_SPIDER_YES", value_parser = BoolishValueParser::new())]
+        yes: bool,
+
+        /// Output format of the summary.
+        #[arg(short, long, value_enum, env = "DLL_SPIDER_OUTPUT", default_value_t = ReportFormat::Table)]
+        output: ReportFormat,
+
+        /// Only check the targets and the DLLs, and print what would be loaded where.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Load the DLLs into every process with the given name or path, or with a name listed in the targets file.
+        #[arg(long, conflicts_with_all = ["newest", "oldest"])]
+        all_matching: bool,
+
+        /// Command run through `cmd /C` after each DLL is loaded, with SPIDER_PID, SPIDER_PROCESS,
+        /// SPIDER_MODULE and SPIDER_REMOTE_BASE set.
+        #[arg(long, value_name = "COMMAND")]
+        on_success: Option<String>,
+
+        /// Command run through `cmd /C` after each DLL fails to load, with SPIDER_ERROR also set.
+        #[arg(long, value_name = "COMMAND")]
+        on_failure: Option<String>,
+
+        /// Consider a target failed if a hook fails.
+        #[arg(long)]
+        hook_strict: bool,
+
+        /// Technique used to load the DLLs.
+        #[arg(long, value_enum, default_value_t = InjectionMethod::CreateRemoteThread)]
+        method: InjectionMethod,
+
+        /// Name of the hook procedure exported by the DLLs, for the hook method.
+        #[arg(long, value_name = "HOOK_PROC", required_if_eq("method", "hook"))]
+        export: Option<String>,
+
+        #[command(flatten)]
+        init: Box<InitExport>,
+
+        #[command(flatten)]
+        staging: Box<Staging>,
+
+        #[command(flatten)]
+        retry: Retry,
+
+        /// Flags passed to LoadLibraryExW instead of calling LoadLibraryW, e.g. LOAD_WITH_ALTERED_SEARCH_PATH (x64 only).
+        #[arg(long, value_name = "FLAG", value_delimiter = ',', value_parser = parse_load_flag, conflicts_with_all = ["method", "init_export"])]
+        load_flags: Vec<u32>,
+
+        /// Do not check that the DLLs are in the module list of the targets once loaded.
+        #[arg(long)]
+        no_verify: bool,
+
+        /// Unload the DLLs already loaded by the targets before loading them again, instead of skipping them.
+        #[arg(long, conflicts_with = "allow_duplicate")]
+        force_reload: bool,
+
+        /// Load the DLLs even if the targets already loaded them, incrementing their reference count.
+        #[arg(long)]
+        allow_duplicate: bool,
+
+        /// Named event that the DLLs set once they are ready; a target succeeds only once it is set.
+        #[arg(long, value_name = "NAME")]
+        wait_event: Option<String>,
+
+        /// Time to wait for the DLLs to set the event of --wait-event, in milliseconds (the value of --timeout by default).
+        #[arg(long, value_name = "MS", requires = "wait_event")]
+        wait_event_timeout: Option<u64>,
+    },
+
+    /// Start a process in a suspended state and load a DLL inside it before it runs.
+    Spawn {
+        /// Path to the executable to start.
         #[arg(value_parser = |path: &str| dunce::canonicalize(path))]
+        executable: PathBuf,
+
+        /// Path to the DLL to load.
+        #[arg(short, long, value_parser = parse_module_path)]
+        module: PathBuf,
+
+        /// Skip the check on the architecture of the DLL and of the new process.
+        #[arg(long)]
+        force: bool,
+
+        /// Technique used to load the DLL.
+        #[arg(long, value_enum, default_value_t = SpawnMethod::CreateRemoteThread)]
+        method: SpawnMethod,
+
+        /// Time to wait for the DLL to load with the early-bird method, in milliseconds.
+        #[arg(long, env = "DLL_SPIDER_TIMEOUT", default_value_t = winapi::DEFAULT_LOAD_TIMEOUT_MS)]
+        timeout: u64,
+
+        /// Arguments passed to the new process.
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+
+    /// Wait for target processes to start and load a DLL inside them.
+    Watch {
+        /// Name of the target processes.
+        #[arg(short, long = "by-name")]
+        name: String,
+
+        /// Path to the DLL to load.
+        #[arg(short, long, value_parser = parse_module_path)]
         module: PathBuf,
+
+        /// Interval between checks for new processes, in milliseconds.
+        #[arg(short, long, default_value_t = 250)]
+        interval: u64,
+
+        /// Exit after the first successful load.
+        #[arg(long)]
+        once: bool,
+
+        /// Skip the check on the architecture of the DLL and of the target processes, and allow
+        /// injecting into critical system processes.
+        #[arg(long)]
+        force: bool,
     },
 
-    /// Enumearate target processes.
-    Enum {},
-}
+    /// Wait for a target process to start, or to exit with `--for-exit`.
+    Wait {
+        #[command(flatten)]
+        process: Process,
 
+        /// Give up after this many seconds, exiting with code 7.
+        #[arg(short, long)]
+        timeout: Option<u64>,
 
-/// Main function.
-fn main() {
-    let args: Cli = Cli::parse();
-    
-    logging::init(
-        if args.verbose != 0 { Some(logging::u8_to_level(args.verbose - 1)) } else { None }
-    );
+        /// Interval between checks for the process, in milliseconds.
+        #[arg(short, long, default_value_t = 250)]
+        interval: u64,
 
-    match &args.command {
-        Commands::Load { process , module} => {
-            debug!("{}", format!("action=load, process={:#?}, module={:#?}", process, module));
+        /// Wait for the target process to exit instead, and print its exit code.
+        #[arg(long)]
+        for_exit: bool,
+    },
 
-            let dll_path: &str = module.to_str().unwrap();
+    /// Unload DLLs from a target process and load them again.
+    Reload {
+        #[command(flatten)]
+        process: Process,
 
-            let pid: u32 = if let Some(process_name) = &process.name {
-                winapi::find_process_by_name(&process_name, Some(true)).unwrap()
-            } else {
-                process.pid.expect("must provide either the PID or the name of the target process")
-            };
+        /// Paths to the new builds of the DLLs to reload, in load order.
+        #[arg(num_args = 1.., required = true, value_parser = parse_module_path)]
+        modules: Vec<PathBuf>,
 
-            let _ = winapi::load_dll(pid, dll_path);
-        },
-        Commands::Enum {  } => {
-            debug!("action=enum");
+        /// Keep reloading the remaining DLLs after one fails.
+        #[arg(long)]
+        keep_going: bool,
 
-            let mut builder = Builder::default();
+        /// Stop reloading the remaining DLLs as soon as one fails (the default).
+        #[arg(long, hide = true, conflicts_with = "keep_going")]
+        stop_on_error: bool,
 
-            for (key, value) in &winapi::get_process_name_pid_mapping().unwrap() {
-                builder.push_record([&value.to_string(), key]);
-            }
+        /// Skip the check on the architecture of the DLLs and of the target process, and allow
+        /// injecting into critical system processes.
+        #[arg(long)]
+        force: bool,
 
-            let mut table = builder.build();
-            table
-                .with(
-                    Modify::new(Segment::all())
-                        .with(Alignment::left())
-                        .with(Alignment::top()))
-                .with(Style::blank());
-            
-            println!("{}", table);
-        }
+        /// Time to wait for each DLL to load, in milliseconds.
+        #[arg(long, env = "DLL_SPIDER_TIMEOUT", default_value_t = winapi::DEFAULT_LOAD_TIMEOUT_MS)]
+        timeout: u64,
+
+        /// Terminate the thread loading a DLL if it is still running after the timeout.
+        #[arg(long)]
+        kill_on_timeout: bool,
+
+        /// Do not ask for confirmation before injecting into processes of another user or session.
+        #[arg(short, long, env = "DLL_SPIDER_YES", value_parser = BoolishValueParser::new())]
+        yes: bool,
+    },
+
+    /// Unload a DLL from a target process.
+    #[command(visible_alias = "unload")]
+    Eject {
+        #[command(flatten)]
+        process: Process,
+
+        /// Base name or full path of the DLL to unload.
+        #[arg(short, long, visible_alias = "module-name")]
+        module: String,
+
+        #[command(flatten)]
+        retry: Retry,
+    },
+
+    /// Check whether a DLL can be injected into a target process, without injecting it.
+    Check {
+        #[command(flatten)]
+        process: Process,
+
+        /// Path to the DLL to check.
+        #[arg(short, long, value_parser = parse_module_path)]
+        module: PathBuf,
+    },
+
+    /// Call a function exported by a module loaded by a target process.
+    Call {
+        #[command(flatten)]
+        process: Process,
+
+        /// Base name or full path of the module that exports the function.
+        #[arg(short, long)]
+        module: String,
+
+        /// Name of the exported function.
+        #[arg(short, long)]
+        export: String,
+
+        /// String passed to the function as a pointer to a UTF-16 string.
+        #[arg(short, long)]
+        string_arg: Option<String>,
+
+        #[command(flatten)]
+        retry: Retry,
+    },
+
+    /// List the modules loaded by a target process.
+    Modules {
+        #[command(flatten)]
+        process: Process,
+    },
+
+    /// Show detailed information about a target process.
+    Info {
+        #[command(flatten)]
+        process: Process,
+    },
+
+    /// List the threads of a target process.
+    Threads {
+        #[command(flatten)]
+        process: Process,
+    },
+
+    /// Suspend all the threads of a target process.
+    Suspend {
+        #[command(flatten)]
+        process: Process,
+    },
+
+    /// Resume all the threads of a target process.
+    Resume {
+        #[command(flatten)]
+        process: Process,
+    },
+
+    /// List the regions of the virtual address space of a target process.
+    Regions {
+        #[command(flatten)]
+        process: Process,
+
+        /// Hide free and reserved regions.
+        #[arg(long)]
+        committed_only: bool,
+    },
+
+    /// Dump a range of the memory of a target process.
+    Dump {
+        #[command(flatten)]
+        process: Process,
+
+        /// Start address of the range to dump, in hexadecimal (0x prefix) or decimal.
+        #[arg(short, long, value_parser = parse_number)]
+        address: usize,
+
+        /// Size of the range to dump, in hexadecimal (0x prefix) or decimal.
+        #[arg(short, long, value_parser = parse_number)]
+        size: usize,
+
+        /// Path to the output file, or `-` for stdout.
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+
+    /// Reconstruct a module loaded by a target process and save it to a file.
+    DumpModule {
+        #[command(flatten)]
+        process: Process,
+
+        /// Base name or full path of the module to dump.
+        #[arg(short, long)]
+        module: String,
+
+        /// Path to the output file.
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+
+    /// List the processes that have loaded a DLL.
+    FindModule {
+        /// Base name of the DLL to look for.
+        module: String,
+
+        /// Match the full path of the DLL instead of its base name.
+        #[arg(long)]
+        path: bool,
+    },
+
+    /// Enumearate target processes.
+    Enum {
+        /// Output format.
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        output: OutputFormat,
+
+        /// Column to sort the processes by.
+        #[arg(short, long, value_enum, default_value_t = SortKey::Pid)]
+        sort: SortKey,
+
+        /// Sort in descending order.
+        #[arg(long)]
+        desc: bool,
+
+        /// Only list the processes whose name matches a regex (case-insensitive).
+        #[arg(short, long)]
+        filter: Option<String>,
+
+        /// Refresh the list until interrupted, marking the processes that started (+) or exited (-).
+        #[arg(short, long, conflicts_with = "output")]
+        watch: bool,
+
+        /// Refresh interval of the watch mode, in seconds.
+        #[arg(short, long, default_value_t = 2, requires = "watch")]
+        interval: u64,
+
+        /// Arrange the processes in a tree, with children indented under their parents.
+        #[arg(short, long, conflicts_with_all = ["output", "watch", "filter", "columns"])]
+        tree: bool,
+
+        /// Comma-separated list of the columns to show, in order. Use `help` to list the available columns.
+        #[arg(short, long, value_delimiter = ',', default_value = "pid,name")]
+        columns: Vec<String>,
+
+        /// Show the full path of the executables, in addition to the selected columns.
+        #[arg(long, conflicts_with = "tree")]
+        path: bool,
+
+        /// Only list the processes owned by an account, given as `user` or `DOMAIN\user` (case-insensitive).
+        #[arg(short, long, conflicts_with = "tree")]
+        user: Option<String>,
+
+        /// Only list the processes of a session, given as an ID or as `current`.
+        #[arg(long, value_parser = parse_session_id, conflicts_with = "tree")]
+        session: Option<u32>,
+
+        /// Only list the processes that can be opened with full access, i.e. viable injection targets.
+        #[arg(short, long, conflicts_with = "tree")]
+        accessible_only: bool,
+
+        /// Do not print the header row and the summary footer of the table.
+        #[arg(long)]
+        no_header: bool,
+
+        /// Only print the PIDs of the processes, one per line (e.g. for `load --targets-file -`).
+        #[arg(long, conflicts_with_all = ["output", "watch", "tree", "columns"])]
+        pids_only: bool,
+    },
+
+    /// Generate a shell completion script and print it to stdout.
+    Completions {
+        /// Shell to generate the completion script for.
+        shell: Shell,
+    },
+
+    /// Generate the reference documentation of the CLI and print it to stdout.
+    #[command(hide = true)]
+    GenDocs {
+        /// Format of the documentation.
+        #[arg(short, long, value_enum, default_value_t = DocsFormat::Markdown)]
+        format: DocsFormat,
+    },
+}
+
+impl Commands {
+    /// Returns the target process options of the command, if it has a target process.
+    fn process_mut(&mut self) -> Option<&mut Process> {
+        match self {
+            Commands::Load { process, .. }
+            | Commands::Wait { process, .. }
+            | Commands::Reload { process, .. }
+            | Commands::Eject { process, .. }
+            | Commands::Check { process, .. }
+            | Commands::Call { process, .. }
+            | Commands::Modules { process }
+            | Commands::Info { process }
+            | Commands::Threads { process }
+            | Commands::Suspend { process }
+            | Commands::Resume { process }
+            | Commands::Regions { process, .. }
+            | Commands::Dump { process, .. }
+            | Commands::DumpModule { process, .. } => Some(process),
+            _ => None,
+        }
+    }
+
+    /// Returns the paths of the DLLs the command loads or checks.
+    fn module_paths_mut(&mut self) -> Vec<&mut PathBuf> {
+        match self {
+            Commands::Load { modules, .. } | Commands::Reload { modules, .. } => modules.iter_mut().collect(),
+            Commands::Spawn { module, .. } | Commands::Watch { module, .. } | Commands::Check { module, .. } => vec![module],
+            _ => Vec::new(),
+        }
+    }
+}
+
+
+/// Output format of the commands that list data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable table.
+    Table,
+    /// JSON array of objects.
+    Json,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+
+/// When to color the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ColorChoice {
+    /// Color if stdout is a terminal and the `NO_COLOR` environment variable is not set.
+    Auto,
+    /// Always color.
+    Always,
+    /// Never color.
+    Never,
+}
+
+impl ColorChoice {
+    /// Returns whether the output should be colored.
+    fn is_enabled(self) -> bool {
+        match self {
+            // see https://no-color.org/.
+            ColorChoice::Auto => env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && io::stdout().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+
+/// Format of the reference documentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum DocsFormat {
+    /// Man page, in roff format.
+    Man,
+    /// Markdown.
+    Markdown,
+}
+
+
+/// Technique used by the commands that load DLLs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum InjectionMethod {
+    /// Call `LoadLibraryW` in a new thread created with `CreateRemoteThread`.
+    CreateRemoteThread,
+    /// Call `LoadLibraryW` in a new thread created with the native `NtCreateThreadEx` (x64 only).
+    #[value(name = "ntcreatethreadex")]
+    NtCreateThreadEx,
+    /// Redirect an existing thread to a stub that calls `LoadLibraryW` (x64 targets only).
+    Hijack,
+    /// Install a `WH_GETMESSAGE` hook exported by the DLL on the thread of the main window.
+    Hook,
+    /// Map the DLL without the Windows loader, so that it is not listed in the modules (x64 only).
+    ManualMap,
+    /// Start a thread at the `ReflectiveLoader` export of the DLL, written as is to the target.
+    Reflective,
+    /// Call `LoadLibraryW` with a path shared through a section mapped into the target, instead
+    /// of one written with `WriteProcessMemory`.
+    Section,
+}
+
+impl InjectionMethod {
+    /// Returns the injector implementing the technique, given the hook procedure exported by
+    /// the DLLs for the hook method.
+    fn injector(self, export: Option<&str>) -> Box<dyn winapi::Injector> {
+        match self {
+            InjectionMethod::CreateRemoteThread => Box::new(winapi::CreateRemoteThreadInjector),
+            InjectionMethod::NtCreateThreadEx => Box::new(winapi::NtCreateThreadExInjector),
+            InjectionMethod::Hijack => Box::new(winapi::ThreadHijackInjector),
+            InjectionMethod::Hook => Box::new(winapi::SetWindowsHookInjector { export: export.unwrap_or_default().to_owned() }),
+            InjectionMethod::ManualMap => Box::new(winapi::ManualMapInjector),
+            InjectionMethod::Reflective => Box::new(winapi::ReflectiveInjector),
+            InjectionMethod::Section => Box::new(winapi::SectionInjector),
+        }
+    }
+}
+
+
+/// Technique used by `spawn` to load the DLL into the new process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SpawnMethod {
+    /// Call `LoadLibraryW` in a new thread created with `CreateRemoteThread`, then resume the process.
+    CreateRemoteThread,
+    /// Queue an APC calling `LoadLibraryW` to the main thread, which runs it before the entry point.
+    EarlyBird,
+}
+
+impl SpawnMethod {
+    /// Returns the name of the technique, as recorded in the audit log.
+    fn name(self) -> &'static str {
+        match self {
+            SpawnMethod::CreateRemoteThread => InjectionMethod::CreateRemoteThread.injector(None).name(),
+            SpawnMethod::EarlyBird => audit::EARLY_BIRD_METHOD,
+        }
+    }
+}
+
+
+/// Output format of the summary of the commands that load DLLs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ReportFormat {
+    /// Human-readable table.
+    Table,
+    /// JSON object, or array of objects if there are multiple DLLs or targets.
+    Json,
+}
+
+
+/// Column to sort the processes listed by the enum command by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SortKey {
+    /// Process name, case-insensitive.
+    Name,
+    /// PID.
+    Pid,
+    /// Working set size. Processes whose working set cannot be queried come first.
+    Memory,
+}
+
+
+/// Options of the process list of the enum command.
+#[derive(Debug)]
+struct EnumOptions {
+    /// Regex that the names of the listed processes must match.
+    filter: Option<Regex>,
+    /// Account that must own the listed processes.
+    user: Option<String>,
+    /// Session the listed processes must belong to.
+    session: Option<u32>,
+    /// Whether to only list the processes that can be opened with full access.
+    accessible_only: bool,
+    /// Column to sort the processes by.
+    sort: SortKey,
+    /// Whether to sort in descending order.
+    desc: bool,
+    /// Columns to show, in order.
+    columns: Vec<Column>,
+    /// Whether to print a header row and a summary footer around tables.
+    header: bool,
+    /// Whether to color tables.
+    color: bool,
+}
+
+
+/// Column of the process list of the enum command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Column {
+    /// PID.
+    Pid,
+    /// Process name.
+    Name,
+    /// Full path of the executable.
+    Path,
+    /// User that owns the process.
+    User,
+    /// Processor architecture.
+    Arch,
+    /// Working set size.
+    Memory,
+    /// Remote Desktop Services session ID.
+    Session,
+    /// Local start time.
+    Started,
+    /// Mandatory integrity level.
+    Integrity,
+    /// Protection level (PP or PPL, and signer).
+    Protection,
+}
+
+impl Column {
+    /// Returns the name of a column, as accepted by `--columns`.
+    fn name(&self) -> &'static str {
+        match self {
+            Column::Pid => "pid",
+            Column::Name => "name",
+            Column::Path => "path",
+            Column::User => "user",
+            Column::Arch => "arch",
+            Column::Memory => "memory",
+            Column::Session => "session",
+            Column::Started => "started",
+            Column::Integrity => "integrity",
+            Column::Protection => "protection",
+        }
+    }
+
+    /// Returns the header of a column in tables.
+    fn header(&self) -> String {
+        self.name().to_uppercase()
+    }
+}
+
+
+/// Process listed by the enum command.
+/// 
+/// The optional fields are only queried when their column is selected, and are `None` if
+/// they could not be queried, e.g. because access to the process was denied.
+#[derive(Clone, Debug, Default)]
+struct ProcessRecord {
+    /// PID of the process.
+    pid: u32,
+    /// Name of the process.
+    name: String,
+    /// Whether the process can be opened with full access.
+    is_accessible: bool,
+    /// Full path of the executable of the process.
+    path: Option<String>,
+    /// User that owns the process.
+    user: Option<String>,
+    /// Processor architecture of the process.
+    arch: Option<winapi::Arch>,
+    /// Working set size of the process, in bytes.
+    memory: Option<usize>,
+    /// ID of the session of the process.
+    session: Option<u32>,
+    /// Start time of the process.
+    started: Option<SystemTime>,
+    /// Mandatory integrity level of the process.
+    integrity: Option<winapi::IntegrityLevel>,
+    /// Protection level of the process.
+    protection: Option<winapi::ProtectionLevel>,
+}
+
+impl ProcessRecord {
+    /// Returns the record of a process, querying the details needed by the selected columns.
+    fn new(entry: winapi::ProcessListEntry, columns: &[Column]) -> Self {
+        let has_column = |column: Column| columns.contains(&column);
+        let pid: u32 = entry.pid;
+
+        ProcessRecord {
+            pid,
+            name: entry.name,
+            is_accessible: entry.is_accessible,
+            path: has_column(Column::Path).then(|| winapi::get_process_image_path(pid).ok()).flatten(),
+            user: has_column(Column::User).then(|| winapi::get_process_user(pid).ok()).flatten(),
+            arch: has_column(Column::Arch).then(|| winapi::get_process_architecture(pid).ok()).flatten(),
+            memory: has_column(Column::Memory).then(|| winapi::get_process_working_set_size(pid).ok()).flatten(),
+            session: has_column(Column::Session).then(|| winapi::get_process_session_id(pid).ok()).flatten(),
+            started: has_column(Column::Started).then(|| winapi::get_process_start_time(pid).ok()).flatten(),
+            integrity: has_column(Column::Integrity).then(|| winapi::get_process_integrity_level(pid).ok()).flatten(),
+            protection: has_column(Column::Protection).then(|| winapi::get_process_protection_level(pid).ok()).flatten(),
+        }
+    }
+
+    /// Returns the value of a column of a record, formatted for display.
+    fn value(&self, column: Column) -> String {
+        match column {
+            Column::Pid => self.pid.to_string(),
+            Column::Name => self.name.clone(),
+            Column::Path => self.path.clone().unwrap_or_default(),
+            // the token of the process could not be opened.
+            Column::User => self.user.clone().unwrap_or_else(|| "?".to_owned()),
+            Column::Arch => self.arch.map(|arch| arch.to_string()).unwrap_or_default(),
+            Column::Memory => self.memory.map(format_size).unwrap_or_default(),
+            Column::Session => self.session.map(format_session_id).unwrap_or_default(),
+            Column::Started => self.started.and_then(format_start_time).unwrap_or_default(),
+            Column::Integrity => self.integrity.map(|integrity| integrity.to_string()).unwrap_or_default(),
+            Column::Protection => self.protection.map(|protection| protection.to_string()).unwrap_or_default(),
+        }
+    }
+
+    /// Returns the color of the row of a record in tables, if it stands out.
+    /// 
+    /// The current process is highlighted, inaccessible processes are dimmed and elevated
+    /// processes are yellow.
+    fn color(&self) -> Option<Color> {
+        if self.pid == std::process::id() {
+            Some(Color::BOLD | Color::FG_CYAN)
+        } else if !self.is_accessible {
+            Some(Color::new("\x1b[2m", "\x1b[22m"))
+        } else if self.integrity.is_some_and(|integrity| integrity >= winapi::IntegrityLevel::High) {
+            Some(Color::FG_YELLOW)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the values of the selected columns of a record, formatted for display.
+    fn row(&self, columns: &[Column]) -> Vec<String> {
+        columns.iter().map(|&column| self.value(column)).collect()
+    }
+
+    /// Returns the selected columns of a record as a JSON object.
+    fn to_json(&self, columns: &[Column]) -> Value {
+        let mut object: Map<String, Value> = Map::new();
+
+        for &column in columns {
+            let value: Value = match column {
+                Column::Pid => self.pid.into(),
+                Column::Name => self.name.clone().into(),
+                Column::Path => self.path.clone().into(),
+                Column::User => self.user.clone().into(),
+                Column::Arch => self.arch.map(|arch| arch.to_string()).into(),
+                Column::Memory => self.memory.into(),
+                Column::Session => self.session.into(),
+                Column::Started => self.started.and_then(|started| winapi::format_local_time(started).ok()).into(),
+                Column::Integrity => self.integrity.map(|integrity| integrity.to_string()).into(),
+                Column::Protection => self.protection.map(|protection| protection.to_string()).into(),
+            };
+            object.insert(column.name().to_owned(), value);
+        }
+
+        Value::Object(object)
+    }
+}
+
+
+/// Formats a session ID, marking the session of the services.
+fn format_session_id(session_id: u32) -> String {
+    if session_id == winapi::SERVICES_SESSION_ID {
+        format!("{} (services)", session_id)
+    } else {
+        session_id.to_string()
+    }
+}
+
+
+/// Formats a start time as a local time (`HH:MM:SS`), preceded by the date if it is not today.
+fn format_start_time(time: SystemTime) -> Option<String> {
+    let local_time: String = winapi::format_local_time(time).ok()?;
+    let now: String = winapi::format_local_time(SystemTime::now()).ok()?;
+
+    // both times are formatted as `YYYY-MM-DD HH:MM:SS`.
+    if local_time[..10] == now[..10] {
+        Some(local_time[11..].to_owned())
+    } else {
+        Some(local_time)
+    }
+}
+
+
+/// Parses a session ID, resolving `current` to the session of the current process.
+fn parse_session_id(value: &str) -> Result<u32, String> {
+    if value.eq_ignore_ascii_case("current") {
+        winapi::get_current_session_id().map_err(|e| e.message())
+    } else {
+        value.parse().map_err(|e: ParseIntError| e.to_string())
+    }
+}
+
+
+/// Parses the name of a flag of `LoadLibraryExW`, failing with the list of the supported flags.
+fn parse_load_flag(name: &str) -> Result<u32, String> {
+    winapi::LOAD_LIBRARY_FLAG_NAMES
+        .iter()
+        .find(|(flag_name, _)| flag_name.eq_ignore_ascii_case(name.trim()))
+        .map(|(_, flag)| flag.0)
+        .ok_or_else(|| {
+            let names: Vec<&str> = winapi::LOAD_LIBRARY_FLAG_NAMES.iter().map(|(flag_name, _)| *flag_name).collect();
+            format!("unknown flag, supported flags: {}", names.join(", "))
+        })
+}
+
+
+/// Parses the name of a flag of `CreateRemoteThread`, failing with the list of the supported flags.
+fn parse_thread_flag(name: &str) -> Result<u32, String> {
+    winapi::THREAD_CREATION_FLAG_NAMES
+        .iter()
+        .find(|(flag_name, _)| flag_name.eq_ignore_ascii_case(name.trim()))
+        .map(|(_, flag)| flag.0)
+        .ok_or_else(|| {
+            let names: Vec<&str> = winapi::THREAD_CREATION_FLAG_NAMES.iter().map(|(flag_name, _)| *flag_name).collect();
+            format!("unknown flag, supported flags: {}", names.join(", "))
+        })
+}
+
+
+/// Parses the size of the stack of a remote thread, in bytes, rounding it up to a whole page
+/// since Windows only allocates stacks by pages.
+fn parse_stack_size(value: &str) -> Result<usize, String> {
+    const PAGE_SIZE: usize = 0x1000;
+
+    let size: usize = value.parse().map_err(|e: ParseIntError| e.to_string())?;
+    if size == 0 {
+        return Err("the stack size must be greater than 0".to_owned());
+    }
+
+    size.checked_next_multiple_of(PAGE_SIZE).ok_or_else(|| format!("{} is too large", size))
+}
+
+
+/// Parses the path of a DLL, canonicalizing it if it exists.
+/// 
+/// Paths that do not exist are kept as is, to be resolved against the module search path of
+/// the configuration file.
+fn parse_module_path(path: &str) -> Result<PathBuf, String> {
+    Ok(dunce::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path)))
+}
+
+
+/// Process matching the name of a target, among multiple ones.
+#[derive(Clone, Debug)]
+struct Candidate {
+    /// PID of the process.
+    pid: u32,
+    /// Creation time of the process, if it can be queried.
+    start_time: Option<SystemTime>,
+}
+
+impl Candidate {
+    /// Creates the candidates for a list of PIDs, querying their creation times.
+    fn from_pids(pids: &[u32]) -> Vec<Candidate> {
+        pids.iter()
+            .map(|&pid| Candidate { pid, start_time: winapi::get_process_start_time(pid).ok() })
+            .collect()
+    }
+}
+
+
+/// Returns the PID of the candidate started last if `newest` is set, or first otherwise.
+/// 
+/// Candidates whose creation time cannot be queried are only selected if no creation time
+/// is known. Ties are broken by selecting the highest PID.
+fn select_by_start_time(candidates: &[Candidate], newest: bool) -> u32 {
+    let times = candidates.iter().filter_map(|candidate| candidate.start_time);
+    let selected_time: Option<SystemTime> = if newest { times.max() } else { times.min() };
+
+    let tied: Vec<u32> = candidates
+        .iter()
+        .filter(|candidate| candidate.start_time == selected_time)
+        .map(|candidate| candidate.pid)
+        .collect();
+    let pid: u32 = tied.iter().copied().max().unwrap_or_default();
+    if tied.len() > 1 {
+        debug!("{}", format!("processes {:?} have the same start time, selecting the highest PID ({})", tied, pid));
+    }
+
+    pid
+}
+
+
+/// Asks the user to pick one of multiple candidate processes and returns its PID.
+/// 
+/// The candidates are listed on stderr with their start time, window title and memory usage,
+/// and the user is prompted until a valid number is entered. Fails with `ERROR_CANCELLED` if
+/// stdin is closed.
+fn pick_process(candidates: &[Candidate]) -> windows::core::Result<u32> {
+    let window_titles: HashMap<u32, String> = winapi::get_windows(false)
+        .unwrap_or_default()
+        .into_iter()
+        // keep the title of the first window of each process, usually its main window.
+        .rev()
+        .map(|window| (window.pid, window.title))
+        .collect();
+
+    let mut builder = Builder::default();
+    builder.push_record(["#", "PID", "STARTED", "WINDOW", "MEMORY"]);
+    for (i, candidate) in candidates.iter().enumerate() {
+        builder.push_record([
+            (i + 1).to_string(),
+            candidate.pid.to_string(),
+            candidate.start_time.and_then(format_start_time).unwrap_or_default(),
+            window_titles.get(&candidate.pid).cloned().unwrap_or_default(),
+            winapi::get_process_working_set_size(candidate.pid).map(format_size).unwrap_or_default(),
+        ]);
+    }
+    eprintln!("{}", build_table(builder));
+
+    loop {
+        let Some(answer) = prompt(&format!("select a process [1-{}]: ", candidates.len())) else {
+            return Err(windows::core::Error::new(ERROR_CANCELLED.to_hresult(), "no process selected"));
+        };
+
+        match answer.parse::<usize>() {
+            Ok(i) if (1..=candidates.len()).contains(&i) => return Ok(candidates[i - 1].pid),
+            _ => eprintln!("invalid selection {:?}", answer),
+        }
+    }
+}
+
+
+/// Prints a question on stderr and returns the trimmed line entered by the user on stdin.
+/// 
+/// Returns `None` if stdin is closed.
+fn prompt(question: &str) -> Option<String> {
+    eprint!("{}", question);
+    io::stderr().flush().unwrap();
+
+    let mut answer: String = String::new();
+    match io::stdin().read_line(&mut answer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(answer.trim().to_owned()),
+    }
+}
+
+
+/// Time after which the threads created suspended by `--start-suspended` are resumed, if set.
+static RESUME_AFTER: OnceLock<Duration> = OnceLock::new();
+
+/// Whether a thread created suspended by `--start-suspended` is waiting to be resumed.
+static IS_THREAD_SUSPENDED: AtomicBool = AtomicBool::new(false);
+
+/// Whether Ctrl+C was pressed while a thread was waiting to be resumed.
+static IS_RESUME_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+
+/// Returns the lines entered on stdin, read by a background thread so that waiting on them
+/// can be interrupted.
+fn stdin_lines() -> &'static Mutex<Receiver<String>> {
+    static STDIN_LINES: OnceLock<Mutex<Receiver<String>>> = OnceLock::new();
+
+    STDIN_LINES.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut line: String = String::new();
+            while io::stdin().read_line(&mut line).is_ok_and(|size| size > 0) && sender.send(std::mem::take(&mut line)).is_ok() {}
+        });
+
+        Mutex::new(receiver)
+    })
+}
+
+
+/// Installs the Ctrl+C handler of `--start-suspended`: Ctrl+C resumes the thread waiting to be
+/// resumed, which is then waited on as usual, and exits otherwise.
+fn handle_ctrl_c_while_suspended() {
+    ctrlc::set_handler(|| {
+        if IS_THREAD_SUSPENDED.load(Ordering::SeqCst) {
+            IS_RESUME_REQUESTED.store(true, Ordering::SeqCst);
+        } else {
+            std::process::exit(EXIT_FAILURE);
+        }
+    }).unwrap();
+}
+
+
+/// Prints the ID of a remote thread created suspended by `--start-suspended`, and returns once
+/// it can be resumed: when Enter is pressed, when `--resume-after` elapses, or when Ctrl+C is
+/// pressed.
+/// 
+/// If stdin is closed and `--resume-after` is not set, returns right away.
+fn wait_before_resume(pid: u32, tid: u32) {
+    let resume_after: Option<Duration> = RESUME_AFTER.get().copied();
+    match resume_after {
+        Some(resume_after) => eprintln!(
+            "thread {} of process ({}) is suspended, press Enter to resume it or wait {} s",
+            tid, pid, resume_after.as_secs(),
+        ),
+        None => eprintln!("thread {} of process ({}) is suspended, press Enter to resume it", tid, pid),
+    }
+
+    let lines = stdin_lines().lock().unwrap();
+    // the lines entered before the thread was created do not resume it.
+    while lines.try_recv().is_ok() {}
+
+    IS_RESUME_REQUESTED.store(false, Ordering::SeqCst);
+    IS_THREAD_SUSPENDED.store(true, Ordering::SeqCst);
+    let start: Instant = Instant::now();
+    loop {
+        if IS_RESUME_REQUESTED.load(Ordering::SeqCst) {
+            warn!("{}", format!("interrupted, resuming thread {} of process ({}) and waiting for it", tid, pid));
+            break;
+        }
+        if resume_after.is_some_and(|resume_after| start.elapsed() >= resume_after) {
+            break;
+        }
+
+        match lines.recv_timeout(Duration::from_millis(100)) {
+            Ok(_) => break,
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) if resume_after.is_none() => {
+                warn!("{}", format!("stdin is closed, resuming thread {} of process ({})", tid, pid));
+                break;
+            },
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+    IS_THREAD_SUSPENDED.store(false, Ordering::SeqCst);
+}
+
+
+/// Asks the user to confirm injecting into the target processes that belong to another user
+/// account or session than the current process, and fails with `ERROR_CANCELLED` if they do
+/// not.
+/// 
+/// Processes whose owner cannot be queried are considered to belong to another user. The
+/// confirmation is skipped if `yes` is set, and fails if stdin is not a terminal.
+fn confirm_foreign_targets(targets: &[Target], yes: bool) -> windows::core::Result<()> {
+    if yes {
+        return Ok(());
+    }
+
+    let current_user: Option<String> = winapi::get_process_user(std::process::id()).ok();
+    let current_session: Option<u32> = winapi::get_current_session_id().ok();
+
+    let mut builder = Builder::default();
+    builder.push_record(["PID", "NAME", "USER", "SESSION", "PATH"]);
+    let mut foreign_pids: Vec<u32> = Vec::new();
+    for &pid in targets.iter().flatten() {
+        let user: Option<String> = winapi::get_process_user(pid).ok();
+        let session: Option<u32> = winapi::get_process_session_id(pid).ok();
+        if user.is_some() && user == current_user && session == current_session {
+            continue;
+        }
+
+        foreign_pids.push(pid);
+        builder.push_record([
+            pid.to_string(),
+            winapi::get_process_name(pid).unwrap_or_default(),
+            user.unwrap_or_else(|| "?".to_owned()),
+            session.map(format_session_id).unwrap_or_default(),
+            winapi::get_process_image_path(pid).unwrap_or_default(),
+        ]);
+    }
+
+    if foreign_pids.is_empty() {
+        return Ok(());
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err(windows::core::Error::new(ERROR_CANCELLED.to_hresult(), format!(
+            "target processes {:?} belong to another user or session, pass --yes to inject into them",
+            foreign_pids,
+        )));
+    }
+
+    eprintln!("the following processes belong to another user or session:");
+    eprintln!("{}", build_table(builder));
+    let confirmed: bool = prompt("inject into them? [y/N] ")
+        .is_some_and(|answer| answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"));
+    if !confirmed {
+        return Err(windows::core::Error::new(ERROR_CANCELLED.to_hresult(), "aborted"));
+    }
+
+    Ok(())
+}
+
+
+/// Parses the columns passed to `--columns`.
+/// 
+/// Prints the available columns and exits if `help` is passed, and exits with a usage error
+/// if a column is unknown.
+fn parse_columns(names: &[String]) -> Vec<Column> {
+    if names.iter().any(|name| name == "help") {
+        for column in Column::value_variants() {
+            let help: String = column.to_possible_value().and_then(|value| value.get_help().map(ToString::to_string)).unwrap_or_default();
+            println!("{:<8} {}", column.name(), help);
+        }
+        std::process::exit(0);
+    }
+
+    names
+        .iter()
+        .map(|name| {
+            Column::from_str(name.trim(), true).unwrap_or_else(|_| {
+                usage_error(format!("unknown column {:?}, use --columns help to list the available columns", name))
+            })
+        })
+        .collect()
+}
+
+
+/// Function that loads DLLs in order into a target process, like `winapi::load_dlls`.
+type DllLoader = fn(u32, &[&str], &winapi::InjectionOptions, &dyn winapi::Injector) -> windows::core::Result<Vec<winapi::DllLoad>>;
+
+
+/// Options of the commands that load DLLs.
+#[derive(Clone, Debug)]
+struct LoadOptions {
+    /// Options of the loads into each target, e.g. whether to keep loading the remaining DLLs
+    /// into a target after one fails.
+    injection: winapi::InjectionOptions<'static>,
+    /// Technique used to load the DLLs.
+    method: InjectionMethod,
+    /// Hook procedure exported by the DLLs, for the hook method.
+    export: Option<String>,
+    /// Function exported by the DLLs that is called once each of them is loaded, and its argument.
+    init_export: Option<(String, Option<String>)>,
+    /// Flags passed to `LoadLibraryExW`, if the DLLs are loaded with it.
+    load_flags: Option<u32>,
+    /// Output format of the summary.
+    format: ReportFormat,
+    /// Commands run after each DLL is loaded or fails to load.
+    hooks: Hooks,
+    /// Name of the command, recorded in the audit log.
+    command: &'static str,
+    /// Audit log that the loads are recorded in, if enabled.
+    audit_log: Option<audit::AuditLog>,
+    /// Event that the DLLs set once they are ready, and the time to wait for it after loading
+    /// the DLLs into each target.
+    ready_event: Option<(Rc<winapi::ReadyEvent>, Duration)>,
+    /// Copies of the DLLs that are loaded instead of them, in the same order, if they are
+    /// staged with `--copy`.
+    staged_modules: Vec<PathBuf>,
+    /// Paths the targets load the DLLs from, in the same order, if they are passed with
+    /// `--remote-path`.
+    remote_paths: Vec<String>,
+    /// Policy for retrying the loads into each target that fail while it initializes.
+    retry: retry::RetryPolicy,
+}
+
+
+/// Outcome of loading a DLL into a target process, reported by the commands that load DLLs.
+#[derive(Clone, Debug)]
+struct InjectionReport {
+    /// PID of the target process, if the target was resolved.
+    pid: Option<u32>,
+    /// Name of the target process, or the target itself if it was not resolved.
+    process_name: String,
+    /// Path of the DLL.
+    module_path: String,
+    /// Base address of the DLL in the target process, if it was loaded.
+    remote_base: Option<usize>,
+    /// Address of the path of the DLL in the target process, if it is kept with `--no-free`.
+    remote_dll_path: Option<usize>,
+    /// Path of the copy of the DLL that was loaded instead of it, if it is staged with `--copy`.
+    loaded_path: Option<String>,
+    /// Time taken to load the DLL, if it was attempted.
+    duration: Option<Duration>,
+    /// Whether the DLL was `loaded`, `failed` to load, was `skipped`, was `already loaded` by the
+    /// target, or is loaded by a thread that was `started` without waiting for it.
+    status: &'static str,
+    /// Reason why the DLL failed to load.
+    error: Option<String>,
+}
+
+impl InjectionReport {
+    /// Returns the report as a row of the summary table.
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.pid.map_or_else(|| "-".to_owned(), |pid| pid.to_string()),
+            self.process_name.clone(),
+            self.module_path.clone(),
+            self.status.to_owned(),
+            self.remote_base.map_or_else(|| "-".to_owned(), format_base_address),
+            self.duration.map_or_else(|| "-".to_owned(), |duration| format!("{} ms", duration.as_millis())),
+        ]
+    }
+
+    /// Returns the line printed once the DLL is loaded, e.g. ``loaded hook.dll at 0x7ffe`12340000 in PID 4242``,
+    /// followed by the path of its staged copy and the address of its path if they are kept.
+    /// 
+    /// The base address is reported as unknown if it was not verified with `--no-verify`.
+    fn loaded_message(&self) -> Option<String> {
+        let module_name = Path::new(&self.module_path).file_name().map_or(self.module_path.clone(), |name| name.to_string_lossy().into_owned());
+        if self.status == "already loaded" {
+            return Some(format!(
+                "{} already loaded at {} in PID {} (use --force-reload)",
+                module_name, format_base_address(self.remote_base?), self.pid?,
+            ));
+        }
+        if self.status != "loaded" {
+            return None;
+        }
+        let base_address: String = self.remote_base.map_or_else(|| "an unknown address".to_owned(), format_base_address);
+        let mut message: String = format!("loaded {} at {} in PID {}", module_name, base_address, self.pid?);
+        if let Some(loaded_path) = &self.loaded_path {
+            message.push_str(&format!(" from {}", loaded_path));
+        }
+        if let Some(remote_dll_path) = self.remote_dll_path {
+            message.push_str(&format!(", its path is kept at {}", format_base_address(remote_dll_path)));
+        }
+
+        Some(message)
+    }
+
+    /// Returns the report as a JSON object.
+    fn to_json(&self) -> Value {
+        let mut object: Map<String, Value> = Map::new();
+        object.insert("pid".to_owned(), self.pid.into());
+        object.insert("process_name".to_owned(), self.process_name.clone().into());
+        object.insert("module_path".to_owned(), self.module_path.clone().into());
+        object.insert("remote_base".to_owned(), self.remote_base.map(|remote_base| format!("{:#x}", remote_base)).into());
+        object.insert("remote_dll_path".to_owned(), self.remote_dll_path.map(|remote_dll_path| format!("{:#x}", remote_dll_path)).into());
+        object.insert("loaded_path".to_owned(), self.loaded_path.clone().into());
+        object.insert("duration_ms".to_owned(), self.duration.map(|duration| duration.as_millis() as u64).into());
+        object.insert("status".to_owned(), self.status.into());
+        object.insert("error".to_owned(), self.error.clone().into());
+
+        Value::Object(object)
+    }
+}
+
+
+/// Commands run after each DLL is loaded into a target process, or fails to load.
+#[derive(Clone, Debug, Default)]
+struct Hooks {
+    /// Command run after a DLL is loaded.
+    on_success: Option<String>,
+    /// Command run after a DLL fails to load.
+    on_failure: Option<String>,
+    /// Whether a failed hook makes the target fail.
+    strict: bool,
+}
+
+impl Hooks {
+    /// Runs the hook matching the outcome of each report, and returns whether every hook
+    /// that ran succeeded.
+    /// 
+    /// The hooks run through `cmd /C`, with the fields of the report in `SPIDER_*` environment
+    /// variables. Their stdout is redirected to stderr, so that the summary stays parseable.
+    fn run(&self, reports: &[InjectionReport]) -> bool {
+        let mut succeeded: bool = true;
+
+        for report in reports {
+            let command: &str = match report.status {
+                "loaded" => self.on_success.as_deref(),
+                "failed" => self.on_failure.as_deref(),
+                _ => None,
+            }
+            .unwrap_or_default();
+            if command.is_empty() {
+                continue;
+            }
+
+            debug!("{}", format!("running hook {:?} for {} in {}", command, report.module_path, report.process_name));
+            // the command is passed verbatim, since cmd does not follow the usual quoting rules.
+            let status: io::Result<ExitStatus> = Command::new("cmd")
+                .arg("/C")
+                .raw_arg(command)
+                .env("SPIDER_PID", report.pid.map(|pid| pid.to_string()).unwrap_or_default())
+                .env("SPIDER_PROCESS", &report.process_name)
+                .env("SPIDER_MODULE", &report.module_path)
+                .env("SPIDER_REMOTE_BASE", report.remote_base.map(|remote_base| format!("{:#x}", remote_base)).unwrap_or_default())
+                .env("SPIDER_STATUS", report.status)
+                .env("SPIDER_ERROR", report.error.clone().unwrap_or_default())
+                .stdout(Stdio::from(io::stderr()))
+                .status();
+
+            match status {
+                Ok(status) if status.success() => info!("{}", format!("hook {:?} exited with {}", command, status)),
+                Ok(status) => {
+                    warn!("{}", format!("hook {:?} exited with {}", command, status));
+                    succeeded = false;
+                },
+                Err(e) => {
+                    warn!("{}", format!("failed to run hook {:?}: {}", command, e));
+                    succeeded = false;
+                },
+            }
+        }
+
+        succeeded
+    }
+}
+
+
+/// Loads DLLs in order into target processes and prints a summary of the outcome.
+/// 
+/// Within a target, the DLLs following a failed one are skipped unless `options.injection.keep_going`
+/// is set.
+/// A failure in one target does not prevent the DLLs from being loaded into the next ones.
+/// The hooks run after each target. Returns the exit code of each target: 0 if every DLL was
+/// loaded successfully into it, the code of its first failure, or `EXIT_FAILURE` if a hook
+/// failed and the hooks are strict, along with the report of each DLL. Fails before loading
+/// anything if a path is not valid UTF-8.
+fn load_modules(targets: &[Target], modules: &[PathBuf], loader: DllLoader, options: &LoadOptions) -> windows::core::Result<(Vec<i32>, Vec<InjectionReport>)> {
+    let mut reports: Vec<InjectionReport> = Vec::new();
+    let mut exit_codes: Vec<i32> = Vec::new();
+
+    let module_paths: Vec<&str> = modules.iter().map(|module| path_to_str(module)).collect::<windows::core::Result<_>>()?;
+    // the staged copies of the DLLs are loaded instead of them, if any.
+    let dll_paths: Vec<&str> = if options.staged_modules.is_empty() {
+        module_paths.clone()
+    } else {
+        options.staged_modules.iter().map(|module| path_to_str(module)).collect::<windows::core::Result<_>>()?
+    };
+    let loaded_path = |i: usize| options
+        .staged_modules
+        .get(i)
+        .map(|module| module.display().to_string())
+        .or_else(|| options.remote_paths.get(i).cloned());
+    let remote_paths: Vec<&str> = options.remote_paths.iter().map(String::as_str).collect();
+    let injection = winapi::InjectionOptions {
+        remote_paths: (!remote_paths.is_empty()).then_some(remote_paths.as_slice()),
+        ..options.injection
+    };
+    let injector: Box<dyn winapi::Injector> = match (&options.init_export, options.load_flags) {
+        (Some((export, argument)), _) => Box::new(winapi::InitExportInjector { export: export.clone(), argument: argument.clone() }),
+        (None, Some(flags)) => Box::new(winapi::LoadLibraryExInjector { flags }),
+        (None, None) => options.method.injector(options.export.as_deref()),
+    };
+
+    for target in targets {
+        let first_report: usize = reports.len();
+        let pid: u32 = match target {
+            Ok(pid) => *pid,
+            Err(name) => {
+                reports.extend(module_paths.iter().enumerate().map(|(i, module_path)| InjectionReport {
+                    pid: None,
+                    process_name: name.clone(),
+                    module_path: module_path.to_string(),
+                    remote_base: None,
+                    remote_dll_path: None,
+                    loaded_path: loaded_path(i),
+                    duration: None,
+                    status: "failed",
+                    error: Some("target not found".to_owned()),
+                }));
+                options.hooks.run(&reports[first_report..]);
+                exit_codes.push(EXIT_NOT_FOUND);
+                continue;
+            },
+        };
+        let process_name: String = winapi::get_process_name(pid).unwrap_or_default();
+
+        if let Some((ready_event, _)) = &options.ready_event {
+            if let Err(e) = ready_event.reset() {
+                warn!("{}", format!("failed to reset event {}: {}", ready_event.name, e.message()));
+            }
+        }
+
+        let (results, target_error) = match options.retry.run_with(
+            &format!("loading DLLs into process ({})", pid),
+            || loader(pid, &dll_paths, &injection, injector.as_ref()),
+            retryable_load_error,
+        ) {
+            Ok(results) => (results, None),
+            Err(e) => {
+                report_load_error(pid, &e, format!("failed to load DLLs into process ({})", pid));
+                (Vec::new(), Some(e))
+            },
+        };
+        let ready_error: Option<windows::core::Error> = match &options.ready_event {
+            Some((ready_event, timeout)) if target_error.is_none() => wait_for_ready_event(pid, ready_event, *timeout, &results, dll_paths.len()).err(),
+            _ => None,
+        };
+        let first_error: Option<&windows::core::Error> = target_error
+            .as_ref()
+            .or_else(|| results.iter().find_map(|dll_load| dll_load.result.as_ref().err()))
+            .or(ready_error.as_ref());
+        let target_exit_code: i32 = first_error.map_or(0, exit_code);
+
+        for (i, dll_path) in dll_paths.iter().enumerate() {
+            let mut report = InjectionReport {
+                pid: Some(pid),
+                process_name: process_name.clone(),
+                module_path: module_paths[i].to_string(),
+                remote_base: None,
+                remote_dll_path: None,
+                loaded_path: loaded_path(i),
+                duration: None,
+                status: if target_error.is_some() { "failed" } else { "skipped" },
+                error: target_error.as_ref().map(windows::core::Error::message),
+            };
+
+            if let Some(dll_load) = results.get(i) {
+                report.duration = Some(dll_load.duration);
+                report.remote_dll_path = dll_load.remote_dll_path;
+                match &dll_load.result {
+                    Ok(_) if dll_load.is_detached => report.status = "started",
+                    Ok(_) if dll_load.is_base_address_unknown => report.status = "loaded",
+                    Ok(base_address) if dll_load.is_already_loaded => {
+                        report.remote_base = Some(*base_address);
+                        report.status = "already loaded";
+                    },
+                    Ok(base_address) => {
+                        report.remote_base = Some(*base_address);
+                        report.status = "loaded";
+                    },
+                    Err(e) => {
+                        report_load_error(pid, e, format!(
+                            "failed to load {} (DLL {} of {}) into process ({})",
+                            dll_path, i + 1, dll_paths.len(), pid,
+                        ));
+                        report.status = "failed";
+                        report.error = Some(e.message());
+                    },
+                }
+            }
+            reports.push(report);
+        }
+        if let (Some(e), Some(report)) = (&ready_error, reports.last_mut()) {
+            report_load_error(pid, e, format!("{} is not ready in process ({})", report.module_path, pid));
+            report.status = "failed";
+            report.error = Some(e.message());
+        }
+
+        // nothing is done to the DLLs that are skipped or already loaded.
+        for report in reports[first_report..].iter().filter(|report| !matches!(report.status, "skipped" | "already loaded")) {
+            record_audit_entry(options.audit_log.as_ref(), audit::AuditEntry {
+                command: options.command,
+                method: injector.name(),
+                pid: report.pid,
+                module_path: report.module_path.clone(),
+                outcome: report.status,
+                error: report.error.clone(),
+            });
+        }
+
+        let hooks_succeeded: bool = options.hooks.run(&reports[first_report..]);
+        exit_codes.push(if target_exit_code == 0 && !hooks_succeeded && options.hooks.strict { EXIT_FAILURE } else { target_exit_code });
+    }
+
+    match options.format {
+        ReportFormat::Table => {
+            for message in reports.iter().filter_map(InjectionReport::loaded_message) {
+                println!("{}", message);
+            }
+
+            let mut builder = Builder::default();
+            for report in &reports {
+                builder.push_record(report.row());
+            }
+            print_table(builder);
+        },
+        ReportFormat::Json => {
+            let value: Value = match reports.as_slice() {
+                [report] => report.to_json(),
+                reports => Value::Array(reports.iter().map(InjectionReport::to_json).collect()),
+            };
+            println!("{}", serde_json::to_string_pretty(&value).unwrap());
+        },
+    }
+
+    Ok((exit_codes, reports))
+}
+
+
+/// Returns the error that prevented loading any DLL into a target process, which is worth
+/// retrying if it is transient.
+/// 
+/// The loads are not retried once a DLL is injected, even if it failed afterwards (e.g. its
+/// verification could not list the modules of the target), so that it is not loaded twice.
+fn retryable_load_error(outcome: &windows::core::Result<Vec<winapi::DllLoad>>) -> Option<&windows::core::Error> {
+    match outcome {
+        Err(e) => Some(e),
+        Ok(results) if results.iter().all(|dll_load| dll_load.result.is_err() && !dll_load.is_injected) => {
+            results.first().and_then(|dll_load| dll_load.result.as_ref().err())
+        },
+        Ok(_) => None,
+    }
+}
+
+
+/// Appends an entry to the audit log if it is enabled, logging a warning if it cannot be written.
+fn record_audit_entry(audit_log: Option<&audit::AuditLog>, entry: audit::AuditEntry) {
+    let Some(audit_log) = audit_log else {
+        return;
+    };
+
+    if let Err(e) = audit_log.append(&entry) {
+        warn!("{}", format!("failed to write to the audit log {}: {}", audit_log.path.display(), e));
+    }
+}
+
+
+/// Waits for the DLLs loaded into a target process to set the event of `--wait-event`, once
+/// every DLL is loaded.
+/// 
+/// The event is not waited on if a DLL failed to load, nor if every DLL was already loaded,
+/// since it would not be set again. Fails with `ERROR_TIMEOUT` if the DLLs were loaded but did
+/// not set the event in time.
+fn wait_for_ready_event(pid: u32, ready_event: &winapi::ReadyEvent, timeout: Duration, results: &[winapi::DllLoad], dll_count: usize) -> windows::core::Result<()> {
+    if results.len() < dll_count || results.iter().any(|dll_load| dll_load.result.is_err()) {
+        debug!("{}", format!("not waiting for event {}, the DLLs failed to load into process ({})", ready_event.name, pid));
+        return Ok(());
+    }
+    if results.iter().all(|dll_load| dll_load.is_already_loaded) {
+        debug!("{}", format!("not waiting for event {}, the DLLs were already loaded into process ({})", ready_event.name, pid));
+        return Ok(());
+    }
+
+    info!("{}", format!("waiting up to {} ms for event {} to be set in process ({})", timeout.as_millis(), ready_event.name, pid));
+    if !ready_event.wait(timeout)? {
+        return Err(windows::core::Error::new(
+            ERROR_TIMEOUT.to_hresult(),
+            format!("the DLLs were loaded but did not set event {} within {} ms", ready_event.name, timeout.as_millis()),
+        ));
+    }
+
+    Ok(())
+}
+
+
+/// Outcome of enabling `SeDebugPrivilege` at the start of the commands that open processes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DebugPrivilege {
+    /// The privilege is enabled.
+    Enabled,
+    /// The privilege could not be enabled, e.g. because dll-spider is not elevated.
+    Failed,
+    /// The privilege was not enabled because of `--no-debug-privilege`.
+    Skipped,
+}
+
+/// Outcome of enabling `SeDebugPrivilege`, if the command tried to.
+static DEBUG_PRIVILEGE: OnceLock<DebugPrivilege> = OnceLock::new();
+
+
+/// Enables `SeDebugPrivilege` unless `skip` is set, and logs whether it succeeded.
+/// 
+/// The privilege is only needed to open the processes of other users, so failing to enable it
+/// is not an error.
+fn enable_debug_privilege(skip: bool) {
+    let outcome: DebugPrivilege = if skip {
+        debug!("not enabling SeDebugPrivilege (--no-debug-privilege)");
+        DebugPrivilege::Skipped
+    } else {
+        match winapi::enable_debug_privilege() {
+            Ok(()) => {
+                debug!("SeDebugPrivilege enabled");
+                DebugPrivilege::Enabled
+            },
+            Err(e) => {
+                info!("{}", format!("SeDebugPrivilege not enabled, the processes of other users cannot be opened: {}", e.message()));
+                DebugPrivilege::Failed
+            },
+        }
+    };
+    let _ = DEBUG_PRIVILEGE.set(outcome);
+}
+
+
+/// Returns a hint about an access denied error that tells whether `SeDebugPrivilege` was
+/// enabled, if the command tried to.
+fn debug_privilege_hint(e: &windows::core::Error) -> Option<&'static str> {
+    if e.code() != ERROR_ACCESS_DENIED.to_hresult() {
+        return None;
+    }
+
+    DEBUG_PRIVILEGE.get().map(|outcome| match outcome {
+        DebugPrivilege::Enabled => "SeDebugPrivilege was enabled, access was denied despite it",
+        DebugPrivilege::Failed => "SeDebugPrivilege could not be enabled, run dll-spider as administrator to open the processes of other users",
+        DebugPrivilege::Skipped => "SeDebugPrivilege was not enabled because of --no-debug-privilege",
+    })
+}
+
+
+/// Logs an error that occurred while loading DLLs into a target process, followed by hints
+/// if it can be solved by running as administrator.
+fn report_load_error(pid: u32, e: &windows::core::Error, message: String) {
+    error!("{}", format!("{}: {}", message, e.message()));
+    if let Some(hint) = winapi::elevation_hint(pid, e) {
+        warn!("{}", hint);
+    }
+    if let Some(hint) = debug_privilege_hint(e) {
+        warn!("{}", hint);
+    }
+}
+
+
+/// Checks the targets and the DLLs like `load_modules` would, and prints what would be loaded
+/// where, without loading anything.
+/// 
+/// Returns the exit code of each target, like `load_modules`.
+fn dry_run_load_modules(targets: &[Target], modules: &[PathBuf], keep_going: bool, force: bool) -> windows::core::Result<Vec<i32>> {
+    let dll_paths: Vec<&str> = modules.iter().map(|module| path_to_str(module)).collect::<windows::core::Result<_>>()?;
+    let mut exit_codes: Vec<i32> = Vec::new();
+
+    for target in targets {
+        let Ok(pid) = *target else {
+            exit_codes.push(EXIT_NOT_FOUND);
+            continue;
+        };
+
+        let results: Vec<windows::core::Result<()>> = match winapi::check_load_dlls(pid, &dll_paths, force, keep_going) {
+            Ok(results) => results,
+            Err(e) => {
+                report_load_error(pid, &e, format!("cannot load DLLs into process ({})", pid));
+                exit_codes.push(exit_code(&e));
+                continue;
+            },
+        };
+
+        let process_name: String = winapi::get_process_name(pid).unwrap_or_else(|_| "?".to_owned());
+        let process_arch: String = winapi::get_process_architecture(pid).map(|arch| arch.to_string()).unwrap_or_else(|_| "?".to_owned());
+        for (i, (dll_path, result)) in dll_paths.iter().zip(&results).enumerate() {
+            match result {
+                Ok(()) => {
+                    let module: &Path = Path::new(dll_path);
+                    let dll_arch: String = winapi::get_dll_architecture(module).map(|arch| arch.to_string()).unwrap_or_else(|_| "?".to_owned());
+                    let dll_size: String = fs::metadata(module).map(|metadata| format_size(metadata.len() as usize)).unwrap_or_else(|_| "?".to_owned());
+                    println!("would inject {} ({}, {}) into PID {} {} ({})", dll_path, dll_arch, dll_size, pid, process_name, process_arch);
+                },
+                Err(e) => report_load_error(pid, e, format!(
+                    "cannot load {} (DLL {} of {}) into process ({})",
+                    dll_path, i + 1, dll_paths.len(), pid,
+                )),
+            }
+        }
+
+        exit_codes.push(results.iter().find_map(|result| result.as_ref().err()).map_or(0, exit_code));
+    }
+
+    Ok(exit_codes)
+}
+
+
+/// Exits with a code reflecting into how many of the targets the DLLs were loaded, given the
+/// exit code of each target.
+/// 
+/// Returns if the DLLs were loaded into every target. Exits with `EXIT_PARTIAL_SUCCESS` if
+/// they were loaded only into some of them, and with the exit code of the first target if
+/// into none of them.
+fn exit_with_load_outcome(exit_codes: &[i32]) {
+    let succeeded: usize = exit_codes.iter().filter(|&&code| code == 0).count();
+    if succeeded == exit_codes.len() {
+        return;
+    }
+
+    error!("{}", format!("the DLLs were loaded into {} of {} target processes", succeeded, exit_codes.len()));
+    std::process::exit(if succeeded == 0 { exit_codes[0] } else { EXIT_PARTIAL_SUCCESS });
+}
+
+
+/// Waits for the DLLs loaded by `load_modules` to read the configuration block shared with
+/// `--config-file`, up to a timeout, then closes it.
+/// 
+/// The DLLs that were already loaded are not waited on, since their initialization export was
+/// not called.
+fn wait_for_config_consumers(shared_config: &winapi::SharedConfig, reports: &[InjectionReport], timeout: Duration) {
+    let expected: u32 = reports.iter().filter(|report| report.status == "loaded").count() as u32;
+    if !shared_config.wait_for_consumers(expected, timeout) {
+        warn!("{}", format!(
+            "{} of {} DLLs read the configuration after {} ms, closing {} anyway",
+            shared_config.consumers(), expected, timeout.as_millis(), shared_config.name,
+        ));
+    }
+}
+
+
+/// Bridges the pipe of `--pipe` to the standard streams once the DLL connects to it.
+/// 
+/// The data written by the DLL is printed to stdout, and the lines entered on stdin are
+/// written to the pipe, until the DLL closes the pipe or the target process exits. Ctrl+C
+/// exits as usual. Fails with `ERROR_TIMEOUT` if the DLL does not connect in time.
+fn bridge_pipe(pipe_server: winapi::PipeServer, pid: u32, timeout: Duration) -> windows::core::Result<()> {
+    info!("{}", format!("waiting up to {} ms for the DLL to connect to pipe {}", timeout.as_millis(), pipe_server.name));
+    let mut pipe: fs::File = pipe_server.accept(timeout).map_err(|e| windows::core::Error::new(
+        e.code(),
+        format!("the DLL was loaded into process ({}) but did not connect to the pipe: {}", pid, e.message()),
+    ))?;
+    info!("{}", format!("the DLL in process ({}) connected to the pipe, press Ctrl+C to exit", pid));
+
+    let mut reader: io::BufReader<fs::File> = io::BufReader::new(pipe.try_clone()?);
+    let (sender, is_closed) = mpsc::channel::<()>();
+    thread::spawn(move || {
+        let mut data: Vec<u8> = Vec::new();
+        while reader.read_until(b'\n', &mut data).is_ok_and(|size| size > 0) {
+            let mut stdout = io::stdout().lock();
+            let _ = stdout.write_all(&data).and_then(|_| stdout.flush());
+            data.clear();
+        }
+        let _ = sender.send(());
+    });
+
+    let lines = stdin_lines().lock().unwrap();
+    loop {
+        if is_closed.try_recv().is_ok() {
+            info!("the DLL closed the pipe");
+            return Ok(());
+        }
+        if winapi::wait_for_process_exit(pid, Some(Duration::ZERO)).is_ok_and(|exit_code| exit_code.is_some()) {
+            warn!("{}", format!("process ({}) exited, closing the pipe", pid));
+            return Ok(());
+        }
+
+        match lines.recv_timeout(Duration::from_millis(100)) {
+            Ok(line) => {
+                if let Err(e) = pipe.write_all(line.as_bytes()) {
+                    debug!("{}", format!("failed to write to the pipe: {}", e));
+                }
+            },
+            Err(RecvTimeoutError::Timeout) => {},
+            // the output of the DLL is still printed once stdin is closed.
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+}
+
+
+/// Formats the mitigation policies of a process that affect the loading of DLLs, e.g.
+/// `arbitrary code guard, Microsoft-signed images only`.
+fn format_mitigation_policies(protection: winapi::ProcessProtection) -> String {
+    let policies: Vec<&str> = [
+        (protection.prohibits_dynamic_code, "arbitrary code guard"),
+        (protection.microsoft_signed_only, "Microsoft-signed images only"),
+    ]
+        .into_iter()
+        .filter_map(|(enabled, policy)| enabled.then_some(policy))
+        .collect();
+
+    if policies.is_empty() { "none".to_owned() } else { policies.join(", ") }
+}
+
+
+/// Checks whether a DLL can be injected into a target process and prints a report.
+/// 
+/// Only reads the state of the process. `SeDebugPrivilege` is enabled first, as by the
+/// commands that load DLLs, unless `skip_debug_privilege` is set. Returns the reasons why the
+/// injection is expected to fail, if any, with the error codes that loading the DLL would fail
+/// with.
+fn check_injection(pid: u32, module: &Path, skip_debug_privilege: bool) -> Vec<windows::core::Error> {
+    let mut builder = Builder::default();
+    let mut failures: Vec<windows::core::Error> = Vec::new();
+
+    // a check either passes with a status and a detail, or fails with an error.
+    let mut report = |check: &str, outcome: std::result::Result<(&str, String), windows::core::Error>| match outcome {
+        Ok((status, detail)) => builder.push_record([check.to_owned(), status.to_owned(), detail]),
+        Err(e) => {
+            builder.push_record([check.to_owned(), "failed".to_owned(), e.message()]);
+            failures.push(windows::core::Error::new(e.code(), format!("{}: {}", check, e.message())));
+        },
+    };
+
+    match winapi::validate_pe(module) {
+        Ok(()) => report("dll", Ok(("ok", module.display().to_string()))),
+        Err(e) => report("dll", Err(e)),
+    }
+
+    let spider_arch: winapi::Arch = winapi::Arch::current();
+    match (winapi::get_dll_architecture(module), winapi::get_process_architecture(pid)) {
+        (Ok(dll_arch), Ok(process_arch)) => {
+            let is_supported: bool = spider_arch == process_arch || winapi::is_wow64_target(process_arch);
+            let detail: String = format!("{} DLL, {} process, {} dll-spider", dll_arch, process_arch, spider_arch);
+            if dll_arch == process_arch && is_supported {
+                report("architecture", Ok(("ok", detail)))
+            } else {
+                report("architecture", Err(windows::core::Error::new(ERROR_EXE_MACHINE_TYPE_MISMATCH.to_hresult(), detail)))
+            }
+        },
+        (Err(e), _) | (_, Err(e)) => report("architecture", Err(e)),
+    }
+
+    if skip_debug_privilege {
+        report("privilege", Ok(("skipped", "SeDebugPrivilege not enabled (--no-debug-privilege)".to_owned())));
+    } else {
+        match winapi::enable_debug_privilege() {
+            Ok(()) => report("privilege", Ok(("ok", "SeDebugPrivilege enabled".to_owned()))),
+            Err(e) => report("privilege", Ok(("warning", e.message()))),
+        }
+    }
+
+    match winapi::check_injection_access(pid) {
+        Ok(()) => report("access", Ok(("ok", "process opened with the required access rights".to_owned()))),
+        Err(e) => report("access", Err(e)),
+    }
+
+    match winapi::get_process_protection(pid) {
+        Ok(protection) => {
+            if protection.level.is_protected() {
+                report("protection", Err(windows::core::Error::new(
+                    ERROR_ACCESS_DENIED.to_hresult(),
+                    format!("protected process ({}), injection is not possible from user mode", protection.level),
+                )));
+            } else {
+                report("protection", Ok(("ok", "not a protected process".to_owned())));
+            }
+
+            // LoadLibraryW maps the DLL as an image, which ACG allows, but the DLL itself
+            // will not be able to generate code, and the methods that write code fail.
+            if protection.prohibits_dynamic_code {
+                report("dynamic code", Ok(("warning", "arbitrary code guard enabled (ProcessDynamicCodePolicy), only methods that map the DLL as an image work".to_owned())));
+            } else {
+                report("dynamic code", Ok(("ok", "allowed".to_owned())));
+            }
+
+            if protection.microsoft_signed_only {
+                report("signature", Err(windows::core::Error::new(
+                    ERROR_INVALID_IMAGE_HASH.to_hresult(),
+                    "only Microsoft-signed images can be loaded (ProcessSignaturePolicy)",
+                )));
+            } else {
+                report("signature", Ok(("ok", "unsigned images can be loaded".to_owned())));
+            }
+        },
+        Err(e) => report("protection", Err(e)),
+    }
+
+    let module_name: String = module.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    match winapi::is_module_loaded(pid, &module_name) {
+        Ok(true) => report("loaded", Ok(("warning", format!("{} is already loaded", module_name)))),
+        Ok(false) => report("loaded", Ok(("ok", format!("{} is not loaded", module_name)))),
+        Err(e) => report("loaded", Ok(("warning", e.message()))),
+    }
+
+    print_table(builder);
+
+    failures
+}
+
+
+/// Returns the PIDs of the running processes whose name matches a pattern.
+/// 
+/// Unlike `winapi::find_processes_by_name`, does not fail if no process matches. Failures to
+/// enumerate the processes are logged and treated as no match.
+fn poll_processes(pattern: &winapi::NamePattern) -> Vec<u32> {
+    let mapping: HashMap<String, Vec<u32>> = match winapi::get_process_name_pid_mapping() {
+        Ok(mapping) => mapping,
+        Err(e) => {
+            error!("{}", format!("failed to enumerate processes: {}", e.message()));
+            HashMap::new()
+        },
+    };
+
+    let mut pids: Vec<u32> = mapping
+        .iter()
+        .filter(|(key, _)| pattern.matches(key))
+        .flat_map(|(_, pids)| pids)
+        .copied()
+        .collect();
+    pids.sort_unstable();
+
+    pids
+}
+
+
+/// Polls the running processes until a target process is running and returns the PIDs of
+/// the running target processes.
+/// 
+/// Returns `None` if no target process started before the timeout elapsed.
+fn wait_for_processes(process: &Process, interval: Duration, timeout: Option<Duration>) -> Option<Vec<u32>> {
+    let start: Instant = Instant::now();
+
+    loop {
+        let pids: Vec<u32> = process.poll();
+        if !pids.is_empty() {
+            return Some(pids);
+        }
+
+        if timeout.is_some_and(|timeout| start.elapsed() >= timeout) {
+            return None;
+        }
+        thread::sleep(interval);
+    }
+}
+
+
+/// Polls the running processes and loads a DLL into every process with the given name.
+/// 
+/// Each process is handled only once, whether the DLL was loaded successfully or not.
+/// Runs until interrupted, or until the first successful load if `once` is set.
+fn watch(name: &str, dll_path: &str, interval: Duration, once: bool, force: bool) {
+    let mut handled_pids: HashSet<u32> = HashSet::new();
+    info!("{}", format!("waiting for {} to start", name));
+
+    let pattern: winapi::NamePattern = winapi::NamePattern::new(name, false);
+
+    loop {
+        let new_pids = poll_processes(&pattern)
+            .into_iter()
+            .filter(|pid| !handled_pids.contains(pid))
+            .collect::<Vec<u32>>();
+
+        for pid in new_pids {
+            handled_pids.insert(pid);
+
+            match winapi::load_dll(pid, dll_path, force) {
+                Ok(base_address) => {
+                    info!("{}", format!("loaded {} at {} into process ({})", dll_path, format_base_address(base_address), pid));
+                    if once {
+                        return;
+                    }
+                },
+                Err(e) => error!("{}", format!("failed to load {} into process ({}): {}", dll_path, pid, e.message())),
+            }
+        }
+
+        thread::sleep(interval);
+    }
+}
+
+
+/// Parses a number, either in hexadecimal (with a `0x` prefix) or in decimal.
+fn parse_number(value: &str) -> Result<usize, ParseIntError> {
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+}
+
+
+/// Formats an address as `module+offset` if it falls inside one of the given modules.
+fn format_address(modules: &[winapi::ModuleInfo], address: usize) -> String {
+    match modules.iter().find(|module| module.contains(address)) {
+        Some(module) => format!("{}+{:#x}", module.name, address - module.base_address),
+        None => format!("{:#x}", address),
+    }
+}
+
+
+/// Formats an address in hexadecimal, splitting the 64-bit addresses in two halves with a
+/// backtick like WinDbg does (e.g. ``0x7ffe`12340000``).
+fn format_base_address(address: usize) -> String {
+    let address: u64 = address as u64;
+    if address > u32::MAX as u64 {
+        format!("{:#x}`{:08x}", address >> 32, address & 0xffff_ffff)
+    } else {
+        format!("{:#x}", address)
+    }
+}
+
+
+/// Formats a size in bytes in a human-readable way (e.g. `4.2 MiB`).
+fn format_size(size: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+
+    let mut value: f64 = size as f64 / 1024.0;
+    let mut unit: usize = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+
+/// Builds the records of a table builder into a table with the default table style.
+fn build_table(builder: Builder) -> Table {
+    let mut table = builder.build();
+    table
+        .with(
+            Modify::new(Segment::all())
+                .with(Alignment::left())
+                .with(Alignment::top()))
+        .with(Style::blank());
+
+    table
+}
+
+
+/// Prints the records of a table builder using the default table style.
+fn print_table(builder: Builder) {
+    println!("{}", build_table(builder));
+}
+
+
+/// Builds a process list into a table, coloring its header row and the rows of the processes
+/// that stand out if colors are enabled.
+/// 
+/// The rows of the builder after the header row must match the records, in order.
+fn build_process_table(builder: Builder, records: &[&ProcessRecord], options: &EnumOptions) -> Table {
+    let mut table: Table = build_table(builder);
+    if !options.color {
+        return table;
+    }
+
+    if options.header {
+        table.modify(Rows::first(), Color::BOLD);
+    }
+    let first_row: usize = usize::from(options.header);
+    for (i, record) in records.iter().enumerate() {
+        if let Some(color) = record.color() {
+            table.modify(Rows::single(first_row + i), color);
+        }
+    }
+
+    table
+}
+
+
+/// Returns whether an account, formatted as `DOMAIN\user`, matches a `user` or `DOMAIN\user` name.
+fn account_matches(account: &str, name: &str) -> bool {
+    let user: &str = account.rsplit_once('\\').map_or(account, |(_, user)| user);
+
+    account.eq_ignore_ascii_case(name) || (!name.contains('\\') && user.eq_ignore_ascii_case(name))
+}
+
+
+/// Returns the summary footer of a process list (e.g. `173 processes (41 accessible)`).
+fn format_process_summary(records: &[ProcessRecord]) -> String {
+    let accessible_count: usize = records.iter().filter(|record| record.is_accessible).count();
+
+    format!("{} processes ({} accessible)", records.len(), accessible_count)
+}
+
+
+/// Lists the running processes that match the options of the enum command, in order.
+fn enumerate_processes(options: &EnumOptions) -> windows::core::Result<Vec<ProcessRecord>> {
+    let mut records: Vec<ProcessRecord> = winapi::get_process_list()?
+        .into_iter()
+        .filter(|entry| entry.is_accessible || !options.accessible_only)
+        .filter(|entry| options.filter.as_ref().is_none_or(|filter| filter.is_match(&entry.name)))
+        .filter(|entry| options.user.as_ref().is_none_or(|user| {
+            winapi::get_process_user(entry.pid).is_ok_and(|account| account_matches(&account, user))
+        }))
+        .filter(|entry| options.session.is_none_or(|session| {
+            winapi::get_process_session_id(entry.pid).is_ok_and(|session_id| session_id == session)
+        }))
+        .map(|entry| ProcessRecord::new(entry, &options.columns))
+        .collect();
+
+    match options.sort {
+        SortKey::Name => records.sort_by_cached_key(|record| (record.name.to_lowercase(), record.pid)),
+        SortKey::Pid => records.sort_by_key(|record| record.pid),
+        SortKey::Memory => {
+            for record in records.iter_mut().filter(|record| record.memory.is_none()) {
+                record.memory = winapi::get_process_working_set_size(record.pid).ok();
+            }
+            records.sort_by_key(|record| (record.memory, record.pid));
+        },
+    }
+    if options.desc {
+        records.reverse();
+    }
+
+    Ok(records)
+}
+
+
+/// Reprints the list of running processes at a fixed interval until interrupted.
+/// 
+/// Processes that started since the previous refresh are marked with `+`, and processes
+/// that exited are listed once more marked with `-`. Stops if the processes cannot be listed.
+fn watch_processes(options: &EnumOptions, interval: Duration) -> windows::core::Result<()> {
+    let running: Arc<AtomicBool> = Arc::new(AtomicBool::new(true));
+    let handler_running: Arc<AtomicBool> = Arc::clone(&running);
+    ctrlc::set_handler(move || handler_running.store(false, Ordering::SeqCst)).unwrap();
+
+    let mut stdout = io::stdout();
+    // hide the cursor while the screen is redrawn.
+    write!(stdout, "\x1b[?25l").unwrap();
+
+    let mut result: windows::core::Result<()> = Ok(());
+    let mut previous_records: Option<Vec<ProcessRecord>> = None;
+    while running.load(Ordering::SeqCst) {
+        let records: Vec<ProcessRecord> = match enumerate_processes(options) {
+            Ok(records) => records,
+            Err(e) => {
+                result = Err(e);
+                break;
+            },
+        };
+        let mut builder = Builder::default();
+
+        if options.header {
+            builder.push_record([String::new()].into_iter().chain(options.columns.iter().map(Column::header)));
+        }
+
+        let mut row_records: Vec<&ProcessRecord> = Vec::new();
+        match &previous_records {
+            Some(previous_records) => {
+                let previous_pids: HashSet<u32> = previous_records.iter().map(|record| record.pid).collect();
+                let pids: HashSet<u32> = records.iter().map(|record| record.pid).collect();
+
+                for record in &records {
+                    let marker: &str = if previous_pids.contains(&record.pid) { " " } else { "+" };
+                    builder.push_record([marker.to_owned()].into_iter().chain(record.row(&options.columns)));
+                    row_records.push(record);
+                }
+                for record in previous_records.iter().filter(|record| !pids.contains(&record.pid)) {
+                    builder.push_record(["-".to_owned()].into_iter().chain(record.row(&options.columns)));
+                    row_records.push(record);
+                }
+            },
+            None => {
+                for record in &records {
+                    builder.push_record([" ".to_owned()].into_iter().chain(record.row(&options.columns)));
+                    row_records.push(record);
+                }
+            },
+        }
+
+        // clear the screen and move the cursor to the top-left corner.
+        write!(stdout, "\x1b[2J\x1b[H").unwrap();
+        writeln!(stdout, "{}", build_process_table(builder, &row_records, options)).unwrap();
+        if options.header {
+            writeln!(stdout, "{}", format_process_summary(&records)).unwrap();
+        }
+        stdout.flush().unwrap();
+
+        previous_records = Some(records);
+
+        let started: Instant = Instant::now();
+        while running.load(Ordering::SeqCst) && started.elapsed() < interval {
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    // restore the cursor.
+    write!(stdout, "\x1b[?25h").unwrap();
+    stdout.flush().unwrap();
+
+    result
+}
+
+
+/// Prints the running processes as a tree, with children indented under their parents.
+/// 
+/// A process is linked to its parent only if the parent was created before it, since the
+/// PID of an exited parent can be reused by a younger process. Processes whose parent has
+/// exited are shown at the root and marked as orphans.
+fn print_process_tree(sort: SortKey, desc: bool) -> windows::core::Result<()> {
+    let mut entries: Vec<winapi::ProcessEntry> = winapi::get_process_entries_with_parents()?;
+    match sort {
+        SortKey::Name => entries.sort_by_cached_key(|entry| (entry.name.to_lowercase(), entry.pid)),
+        SortKey::Pid => entries.sort_by_key(|entry| entry.pid),
+        SortKey::Memory => entries.sort_by_cached_key(|entry| (winapi::get_process_working_set_size(entry.pid).ok(), entry.pid)),
+    }
+    if desc {
+        entries.reverse();
+    }
+
+    let indices: HashMap<u32, usize> = entries.iter().enumerate().map(|(i, entry)| (entry.pid, i)).collect();
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut roots: Vec<(usize, bool)> = Vec::new();
+
+    for (i, entry) in entries.iter().enumerate() {
+        let parent: Option<usize> = indices.get(&entry.parent_pid).copied().filter(|&parent| {
+            let parent_entry: &winapi::ProcessEntry = &entries[parent];
+
+            parent_entry.pid != entry.pid && match (parent_entry.start_time, entry.start_time) {
+                (Some(parent_start_time), Some(start_time)) => parent_start_time <= start_time,
+                _ => true,
+            }
+        });
+
+        match parent {
+            Some(parent) => children.entry(parent).or_default().push(i),
+            // the idle process is its own parent, and the system process is created at boot.
+            None => roots.push((i, entry.parent_pid != entry.pid && entry.parent_pid != 0)),
+        }
+    }
+
+    let mut visited: HashSet<usize> = HashSet::new();
+    let mut stdout = io::stdout().lock();
+
+    /// Prints the descendants of a process.
+    fn print_children(
+        stdout: &mut impl Write,
+        entries: &[winapi::ProcessEntry],
+        children: &HashMap<usize, Vec<usize>>,
+        visited: &mut HashSet<usize>,
+        node: usize,
+        prefix: &str,
+    ) {
+        let node_children: &[usize] = children.get(&node).map(Vec::as_slice).unwrap_or_default();
+        for (i, &child) in node_children.iter().enumerate() {
+            if !visited.insert(child) {
+                continue;
+            }
+
+            let is_last: bool = i + 1 == node_children.len();
+            writeln!(
+                stdout,
+                "{}{}{} ({})",
+                prefix,
+                if is_last { "└─ " } else { "├─ " },
+                entries[child].name,
+                entries[child].pid,
+            ).unwrap();
+
+            let child_prefix: String = format!("{}{}", prefix, if is_last { "   " } else { "│  " });
+            print_children(stdout, entries, children, visited, child, &child_prefix);
+        }
+    }
+
+    for (root, is_orphan) in roots {
+        visited.insert(root);
+        writeln!(
+            stdout,
+            "{} ({}){}",
+            entries[root].name,
+            entries[root].pid,
+            if is_orphan { " [orphan]" } else { "" },
+        ).unwrap();
+
+        print_children(&mut stdout, &entries, &children, &mut visited, root, "");
+    }
+
+    Ok(())
+}
+
+
+/// Quotes a CSV field if it contains separators, quotes or line breaks.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+
+/// Prints rows as CSV to stdout, preceded by a header row.
+fn print_csv(columns: &[&str], rows: impl Iterator<Item = Vec<String>>) {
+    let mut stdout = io::stdout().lock();
+
+    writeln!(stdout, "{}", columns.iter().map(|column| csv_field(column)).collect::<Vec<String>>().join(",")).unwrap();
+    for row in rows {
+        writeln!(stdout, "{}", row.iter().map(|value| csv_field(value)).collect::<Vec<String>>().join(",")).unwrap();
+    }
+}
+
+
+/// Exit code of the commands that fail for any other reason than the ones below.
+const EXIT_FAILURE: i32 = 1;
+
+/// Exit code of the commands whose target process or DLL file is not found.
+/// 
+/// 2 is skipped, since it is the exit code of clap for invalid arguments.
+const EXIT_NOT_FOUND: i32 = 3;
+
+/// Exit code of the commands that are denied access to the target process.
+const EXIT_ACCESS_DENIED: i32 = 4;
+
+/// Exit code of the commands whose DLL is not a valid PE image of a DLL.
+const EXIT_INVALID_DLL: i32 = 5;
+
+/// Exit code of the commands whose DLL does not match the architecture of the target process.
+const EXIT_ARCHITECTURE_MISMATCH: i32 = 6;
+
+/// Exit code of the commands that time out.
+const EXIT_TIMEOUT: i32 = 7;
+
+/// Exit code of the commands that succeed only for some of their target processes.
+const EXIT_PARTIAL_SUCCESS: i32 = 8;
+
+/// Exit codes of the commands and their meaning, listed in the reference documentation.
+const EXIT_CODES: &[docs::ExitCode] = &[
+    (0, "success"),
+    (EXIT_FAILURE, "any other error"),
+    (2, "invalid arguments"),
+    (EXIT_NOT_FOUND, "target process or DLL file not found"),
+    (EXIT_ACCESS_DENIED, "access denied"),
+    (EXIT_INVALID_DLL, "invalid DLL"),
+    (EXIT_ARCHITECTURE_MISMATCH, "architecture mismatch between the DLL, the target process and dll-spider"),
+    (EXIT_TIMEOUT, "timeout"),
+    (EXIT_PARTIAL_SUCCESS, "partial success, for the commands with multiple targets"),
+];
+
+
+/// Returns the exit code matching an error.
+fn exit_code(e: &windows::core::Error) -> i32 {
+    match e.code() {
+        code if code == ERROR_NOT_FOUND.to_hresult() || code == ERROR_FILE_NOT_FOUND.to_hresult() => EXIT_NOT_FOUND,
+        // E_ACCESSDENIED has the same value.
+        code if code == ERROR_ACCESS_DENIED.to_hresult() => EXIT_ACCESS_DENIED,
+        code if code == ERROR_BAD_EXE_FORMAT.to_hresult() => EXIT_INVALID_DLL,
+        code if code == ERROR_EXE_MACHINE_TYPE_MISMATCH.to_hresult() => EXIT_ARCHITECTURE_MISMATCH,
+        // remote threads are terminated with ERROR_OPERATION_ABORTED once they time out.
+        code if code == ERROR_TIMEOUT.to_hresult() || code == ERROR_OPERATION_ABORTED.to_hresult() => EXIT_TIMEOUT,
+        _ => EXIT_FAILURE,
+    }
+}
+
+
+/// Loads the configuration file passed with `--config`, or the one found in the default
+/// locations unless `--no-config` was passed.
+fn load_config(args: &Cli) -> std::result::Result<config::Config, String> {
+    if args.no_config {
+        return Ok(config::Config::default());
+    }
+
+    match args.config.clone().or_else(config::find) {
+        Some(path) => config::load(&path),
+        None => Ok(config::Config::default()),
+    }
+}
+
+
+/// Applies the defaults of the configuration file to the options that were not passed on the
+/// command line.
+/// 
+/// Exits with an error if the output format is unknown, or if a DLL is found neither at its
+/// path nor in the module search path.
+fn apply_config(args: &mut Cli, matches: &ArgMatches, config: &config::Config) {
+    // the environment variables take precedence over the configuration file.
+    let is_passed = |id: &str| matches
+        .subcommand()
+        .is_some_and(|(_, sub_matches)| matches!(
+            sub_matches.value_source(id),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable),
+        ));
+
+    if let Commands::Load { timeout, .. } | Commands::Reload { timeout, .. } | Commands::Spawn { timeout, .. } = &mut args.command {
+        if let Some(timeout_ms) = config.timeout_ms {
+            if !is_passed("timeout") {
+                *timeout = timeout_ms;
+            }
+        }
+    }
+
+    if let (Commands::Load { output, .. }, Some(format)) = (&mut args.command, &config.output) {
+        if !is_passed("output") {
+            *output = ReportFormat::from_str(format, true).unwrap_or_else(|_| {
+                error!("{}", format!("unknown output format {:?} in the configuration file, expected table or json", format));
+                std::process::exit(EXIT_FAILURE);
+            });
+        }
+    }
+
+    if args.audit_log.is_none() {
+        args.audit_log = config.audit_log.clone();
+    }
+
+    if let Some(process) = args.command.process_mut() {
+        if process.exclude.is_empty() {
+            process.exclude = config.exclude.clone();
+        }
+        if let Some(case_sensitive) = config.case_sensitive {
+            process.case_sensitive = case_sensitive;
+        }
+    }
+
+    for module in args.command.module_paths_mut() {
+        match config.resolve_module(module) {
+            Some(path) => *module = path,
+            None => {
+                error!("{}", format!("DLL {} not found", module.display()));
+                std::process::exit(EXIT_NOT_FOUND);
+            },
+        }
+    }
+}
+
+
+/// Main function.
+fn main() {
+    let matches: ArgMatches = Cli::command().get_matches();
+    let mut args: Cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    let config: std::result::Result<config::Config, String> = load_config(&args);
+
+    // the verbosity of the configuration file applies only if no verbosity flag was passed.
+    let (verbose, quiet) = match &config {
+        Ok(config) if args.verbose == 0 && args.quiet == 0 => (config.verbose.unwrap_or(0), config.quiet.unwrap_or(0)),
+        _ => (args.verbose, args.quiet),
+    };
+    logging::init(logging::LogConfig {
+        level: logging::level_from_flags(verbose, quiet),
+        file: args.log_file.clone(),
+        timestamps: !args.log_no_time,
+    });
+
+    let config: config::Config = config.unwrap_or_else(|e| {
+        error!("{}", e);
+        std::process::exit(EXIT_FAILURE);
+    });
+    debug!("{}", format!("config={:#?}", config));
+    apply_config(&mut args, &matches, &config);
+
+    if let Err(e) = run(&args) {
+        error!("{}", e.message());
+        if let Some(hint) = debug_privilege_hint(&e) {
+            warn!("{}", hint);
+        }
+        std::process::exit(exit_code(&e));
+    }
+}
+
+
+/// Runs the command passed on the command line.
+fn run(args: &Cli) -> windows::core::Result<()> {
+    let audit_log: Option<audit::AuditLog> = args.audit_log.clone().map(|path| audit::AuditLog { path });
+
+    match &args.command {
+        Commands::Load { process , modules, keep_going, stop_on_error: _, force, ignore_mitigations, timeout, kill_on_timeout, stack_size, thread_flags, start_suspended, resume_after, no_free, no_zero, fire_and_forget, yes, output, dry_run, all_matching, on_success, on_failure, hook_strict, method, export, init, staging, retry, load_flags, no_verify, force_reload, allow_duplicate, wait_event, wait_event_timeout } => {
+            debug!("{}", format!("action=load, process={:#?}, modules={:#?}, dry_run={:#?}, method={:#?}, export={:#?}, init_export={:#?}", process, modules, dry_run, method, export, init.init_export));
+
+            enable_debug_privilege(args.no_debug_privilege);
+
+            if *dry_run {
+                if on_success.is_some() || on_failure.is_some() {
+                    info!("hooks are not run in dry-run mode");
+                }
+                let targets: Vec<Target> = process.targets(*all_matching)?;
+                exit_with_load_outcome(&dry_run_load_modules(&targets, modules, *keep_going, *force)?);
+                return Ok(());
+            }
+
+            if !staging.remote_path.is_empty() && staging.remote_path.len() != modules.len() {
+                return Err(windows::core::Error::new(
+                    ERROR_INVALID_PARAMETER.to_hresult(),
+                    format!("--remote-path must be passed once per DLL ({} given for {} DLLs)", staging.remote_path.len(), modules.len()),
+                ));
+            }
+
+            let staged_modules: Vec<PathBuf> = match (staging.copy, &staging.copy_to) {
+                (false, None) => Vec::new(),
+                (_, copy_to) => {
+                    let directory: PathBuf = copy_to.clone().unwrap_or_else(staging::default_directory);
+                    staging::remove_stale_copies(&directory, staging::MAX_STAGED_COPY_AGE);
+                    modules.iter().map(|module| staging::stage(module, &directory)).collect::<windows::core::Result<Vec<PathBuf>>>()?
+                },
+            };
+
+            let shared_config: Option<winapi::SharedConfig> = init
+                .config_file
+                .as_ref()
+                .map(|config_file| {
+                    let content: Vec<u8> = fs::read(config_file)?;
+                    winapi::SharedConfig::create(&format!("Local\\dll-spider-config-{}", std::process::id()), &content)
+                })
+                .transpose()?;
+            let pipe_server: Option<winapi::PipeServer> = init.pipe.then(winapi::PipeServer::create).transpose()?;
+            let init_arg: Option<String> = shared_config
+                .as_ref()
+                .map(|shared_config| shared_config.name.clone())
+                .or_else(|| pipe_server.as_ref().map(|pipe_server| pipe_server.name.clone()))
+                .or_else(|| init.init_arg.clone());
+
+            let ready_event: Option<winapi::ReadyEvent> = wait_event.as_deref().map(winapi::ReadyEvent::create).transpose()?;
+
+            if *start_suspended {
+                if let Some(resume_after) = resume_after {
+                    RESUME_AFTER.set(Duration::from_secs(*resume_after)).unwrap();
+                }
+                handle_ctrl_c_while_suspended();
+            }
+
+            let options = LoadOptions {
+                injection: winapi::InjectionOptions {
+                    force: *force,
+                    ignore_mitigations: *ignore_mitigations,
+                    keep_going: *keep_going,
+                    verify: !*no_verify,
+                    thread: winapi::ThreadOptions {
+                        timeout: Some(winapi::ThreadTimeout { duration: Duration::from_millis(*timeout), kill: *kill_on_timeout }),
+                        stack_size: stack_size.unwrap_or_default(),
+                        flags: thread_flags.iter().fold(0, |flags, flag| flags | flag),
+                        before_resume: start_suspended.then_some(wait_before_resume as fn(u32, u32)),
+                        detach: *fire_and_forget,
+                    },
+                    cleanup: match (*no_free, *no_zero) {
+                        (true, _) => winapi::MemoryCleanup::Keep,
+                        (false, true) => winapi::MemoryCleanup::Free,
+                        (false, false) => winapi::MemoryCleanup::ZeroAndFree,
+                    },
+                    skip_loaded: !*allow_duplicate,
+                    remote_paths: None,
+                },
+                method: *method,
+                export: export.clone(),
+                init_export: init.init_export.clone().map(|init_export| (init_export, init_arg)),
+                load_flags: (!load_flags.is_empty()).then(|| load_flags.iter().fold(0, |flags, flag| flags | flag)),
+                format: *output,
+                hooks: Hooks { on_success: on_success.clone(), on_failure: on_failure.clone(), strict: *hook_strict },
+                command: "load",
+                audit_log: audit_log.clone(),
+                ready_event: ready_event.map(|ready_event| (Rc::new(ready_event), Duration::from_millis(wait_event_timeout.unwrap_or(*timeout)))),
+                staged_modules,
+                remote_paths: staging.remote_path.clone(),
+                retry: retry.policy(),
+            };
+            let targets: Vec<Target> = process.targets(*all_matching)?;
+            if pipe_server.is_some() && targets.len() > 1 {
+                return Err(windows::core::Error::new(ERROR_INVALID_PARAMETER.to_hresult(), "--pipe only supports a single target process"));
+            }
+            confirm_foreign_targets(&targets, *yes)?;
+            let loader: DllLoader = if *force_reload { winapi::reload_dlls } else { winapi::load_dlls };
+            let (exit_codes, reports) = load_modules(&targets, modules, loader, &options)?;
+            if let Some(shared_config) = shared_config {
+                wait_for_config_consumers(&shared_config, &reports, Duration::from_millis(*timeout));
+            }
+            if let (Some(pipe_server), [Ok(pid)], [0]) = (pipe_server, targets.as_slice(), exit_codes.as_slice()) {
+                bridge_pipe(pipe_server, *pid, Duration::from_millis(init.pipe_timeout.unwrap_or(*timeout)))?;
+            }
+            exit_with_load_outcome(&exit_codes);
+        },
+        Commands::Reload { process , modules, keep_going, stop_on_error: _, force, timeout, kill_on_timeout, yes } => {
+            debug!("{}", format!("action=reload, process={:#?}, modules={:#?}", process, modules));
+
+            enable_debug_privilege(args.no_debug_privilege);
+
+            let options = LoadOptions {
+                injection: winapi::InjectionOptions {
+                    force: *force,
+                    keep_going: *keep_going,
+                    thread: winapi::ThreadOptions {
+                        timeout: Some(winapi::ThreadTimeout { duration: Duration::from_millis(*timeout), kill: *kill_on_timeout }),
+                        ..winapi::ThreadOptions::default()
+                    },
+                    ..winapi::InjectionOptions::default()
+                },
+                method: InjectionMethod::CreateRemoteThread,
+                export: None,
+                init_export: None,
+                load_flags: None,
+                format: ReportFormat::Table,
+                hooks: Hooks::default(),
+                command: "reload",
+                audit_log: audit_log.clone(),
+                ready_event: None,
+                staged_modules: Vec::new(),
+                remote_paths: Vec::new(),
+                retry: retry::RetryPolicy::default(),
+            };
+            let targets: Vec<Target> = process.targets(false)?;
+            confirm_foreign_targets(&targets, *yes)?;
+            exit_with_load_outcome(&load_modules(&targets, modules, winapi::reload_dlls, &options)?.0);
+        },
+        Commands::Spawn { executable, module, force, method, timeout, args: process_args } => {
+            debug!("{}", format!("action=spawn, executable={:#?}, module={:#?}, method={:#?}, args={:#?}", executable, module, method, process_args));
+
+            winapi::validate_pe(module)?;
+            let (executable, module): (&str, &str) = (path_to_str(executable)?, path_to_str(module)?);
+
+            let result: windows::core::Result<u32> = match method {
+                SpawnMethod::CreateRemoteThread => winapi::spawn_and_load(
+                    executable,
+                    process_args,
+                    module,
+                    *force,
+                ),
+                SpawnMethod::EarlyBird => winapi::spawn_and_load_early_bird(
+                    executable,
+                    process_args,
+                    module,
+                    *force,
+                    Duration::from_millis(*timeout),
+                ),
+            };
+            record_audit_entry(audit_log.as_ref(), audit::AuditEntry {
+                command: "spawn",
+                method: method.name(),
+                pid: result.as_ref().ok().copied(),
+                module_path: module.to_owned(),
+                outcome: if result.is_ok() { "loaded" } else { "failed" },
+                error: result.as_ref().err().map(windows::core::Error::message),
+            });
+
+            println!("{}", result?);
+        },
+        Commands::Watch { name, module, interval, once, force } => {
+            debug!("{}", format!("action=watch, name={:#?}, module={:#?}, interval={:#?}", name, module, interval));
+
+            winapi::validate_pe(module)?;
+            enable_debug_privilege(args.no_debug_privilege);
+
+            watch(name, path_to_str(module)?, Duration::from_millis(*interval), *once, *force);
+        },
+        Commands::Wait { process, timeout, interval, for_exit } => {
+            debug!("{}", format!("action=wait, process={:#?}, timeout={:#?}, interval={:#?}, for_exit={:#?}", process, timeout, interval, for_exit));
+
+            let timeout: Option<Duration> = timeout.map(Duration::from_secs);
+
+            if *for_exit {
+                match winapi::wait_for_process_exit(process.pid()?, timeout)? {
+                    Some(exit_code) => println!("{}", exit_code),
+                    None => {
+                        error!("timed out waiting for the process to exit");
+                        std::process::exit(EXIT_TIMEOUT);
+                    },
+                }
+                return Ok(());
+            }
+
+            if process.selector.targets_file.is_some() {
+                usage_error("this command accepts a single target process");
+            }
+
+            match wait_for_processes(process, Duration::from_millis(*interval), timeout) {
+                Some(pids) => pids.iter().for_each(|pid| println!("{}", pid)),
+                None => {
+                    error!("timed out waiting for the process to start");
+                    std::process::exit(EXIT_TIMEOUT);
+                },
+            }
+        },
+        Commands::Eject { process, module, retry } => {
+            debug!("{}", format!("action=eject, process={:#?}, module={:#?}", process, module));
+
+            enable_debug_privilege(args.no_debug_privilege);
+
+            let pid: u32 = process.pid()?;
+            let result: windows::core::Result<()> = retry
+                .policy()
+                .run(&format!("unloading {} from process ({})", module, pid), || winapi::unload_dll(pid, module));
+            record_audit_entry(audit_log.as_ref(), audit::AuditEntry {
+                command: "eject",
+                method: audit::FREE_LIBRARY_METHOD,
+                pid: Some(pid),
+                module_path: module.clone(),
+                outcome: if result.is_ok() { "unloaded" } else { "failed" },
+                error: result.as_ref().err().map(windows::core::Error::message),
+            });
+            result?;
+        },
+        Commands::Check { process, module } => {
+            debug!("{}", format!("action=check, process={:#?}, module={:#?}", process, module));
+
+            let failures: Vec<windows::core::Error> = check_injection(process.pid()?, module, args.no_debug_privilege);
+            for failure in &failures {
+                error!("{}", failure.message());
+            }
+            // the exit code is the one of the first failure, as loading the DLL would fail with it.
+            if let Some(failure) = failures.first() {
+                return Err(windows::core::Error::new(failure.code(), "injection is expected to fail"));
+            }
+
+            info!("injection is expected to succeed");
+        },
+        Commands::Call { process, module, export, string_arg, retry } => {
+            debug!("{}", format!("action=call, process={:#?}, module={:#?}, export={:#?}, string_arg={:#?}", process, module, export, string_arg));
+
+            let pid: u32 = process.pid()?;
+            let exit_code: u32 = retry
+                .policy()
+                .run(&format!("calling {} in process ({})", export, pid), || winapi::call_export(pid, module, export, string_arg.as_deref()))?;
+
+            println!("{} returned {:#x}", export, exit_code);
+        },
+        Commands::Modules { process } => {
+            debug!("{}", format!("action=modules, process={:#?}", process));
+
+            let mut builder = Builder::default();
+
+            for module in &winapi::get_process_modules(process.pid()?)? {
+                builder.push_record([
+                    module.name.clone(),
+                    format!("{:#x}", module.base_address),
+                    format!("{:#x}", module.size),
+                    module.path.clone(),
+                ]);
+            }
+
+            print_table(builder);
+        },
+        Commands::Info { process } => {
+            debug!("{}", format!("action=info, process={:#?}", process));
+
+            let pid: u32 = process.pid()?;
+            let or_na = |value: windows::core::Result<String>| value.unwrap_or_else(|_| "n/a".to_owned());
+
+            let mut builder = Builder::default();
+            builder.push_record(["name", &or_na(winapi::get_process_name(pid))]);
+            builder.push_record(["pid", &pid.to_string()]);
+            builder.push_record(["path", &or_na(winapi::get_process_image_path(pid))]);
+            builder.push_record(["parent pid", &or_na(winapi::get_parent_process_id(pid).map(|ppid| ppid.to_string()))]);
+            builder.push_record(["session", &or_na(winapi::get_process_session_id(pid).map(format_session_id))]);
+            builder.push_record(["architecture", &or_na(winapi::get_process_architecture(pid).map(|arch| arch.to_string()))]);
+            builder.push_record(["start time", &or_na(winapi::get_process_start_time(pid).and_then(winapi::format_local_time))]);
+            builder.push_record(["user", &or_na(winapi::get_process_user(pid))]);
+            builder.push_record(["integrity", &or_na(winapi::get_process_integrity_level(pid).map(|integrity| integrity.to_string()))]);
+            builder.push_record(["protection", &or_na(winapi::get_process_protection_level(pid).map(|level| level.to_string()))]);
+            builder.push_record(["mitigations", &or_na(winapi::get_process_protection(pid).map(format_mitigation_policies))]);
+            builder.push_record(["threads", &or_na(winapi::get_process_thread_count(pid).map(|count| count.to_string()))]);
+            builder.push_record(["handles", &or_na(winapi::get_process_handle_count(pid).map(|count| count.to_string()))]);
+            builder.push_record(["working set", &or_na(winapi::get_process_working_set_size(pid).map(format_size))]);
+
+            print_table(builder);
+        },
+        Commands::Threads { process } => {
+            debug!("{}", format!("action=threads, process={:#?}", process));
+
+            let pid: u32 = process.pid()?;
+            let modules: Vec<winapi::ModuleInfo> = winapi::get_process_modules(pid).unwrap_or_default();
+
+            let mut builder = Builder::default();
+
+            for thread in &winapi::get_process_threads(pid)? {
+                builder.push_record([
+                    thread.tid.to_string(),
+                    thread.base_priority.to_string(),
+                    thread.start_address.map_or(String::new(), |address| format_address(&modules, address)),
+                ]);
+            }
+
+            print_table(builder);
+        },
+        Commands::Suspend { process } => {
+            debug!("{}", format!("action=suspend, process={:#?}", process));
+
+            let pid: u32 = process.pid()?;
+            let count: usize = winapi::suspend_process(pid)?;
+
+            info!("{}", format!("suspended {} threads of process ({})", count, pid));
+        },
+        Commands::Resume { process } => {
+            debug!("{}", format!("action=resume, process={:#?}", process));
+
+            let pid: u32 = process.pid()?;
+            let count: usize = winapi::resume_process(pid)?;
+
+            info!("{}", format!("resumed {} threads of process ({})", count, pid));
+        },
+        Commands::Regions { process, committed_only } => {
+            debug!("{}", format!("action=regions, process={:#?}", process));
+
+            let pid: u32 = process.pid()?;
+            let modules: Vec<winapi::ModuleInfo> = winapi::get_process_modules(pid).unwrap_or_default();
+
+            let mut builder = Builder::default();
+
+            for region in winapi::memory_regions(pid)? {
+                if *committed_only && !region.is_committed() {
+                    continue;
+                }
+
+                let module_path: &str = if region.is_image() {
+                    modules
+                        .iter()
+                        .find(|module| module.base_address == region.allocation_base)
+                        .map_or("", |module| &module.path)
+                } else {
+                    ""
+                };
+
+                builder.push_record([
+                    format!("{:#x}", region.base_address),
+                    format!("{:#x}", region.size),
+                    region.state_name().to_owned(),
+                    region.protection_name(),
+                    region.type_name().to_owned(),
+                    module_path.to_owned(),
+                ]);
+            }
+
+            print_table(builder);
+        },
+        Commands::Dump { process, address, size, out } => {
+            debug!("{}", format!("action=dump, process={:#?}, address={:#x}, size={:#x}, out={:#?}", process, address, size, out));
+
+            let (buffer, readable_bytes) = winapi::read_process_memory(process.pid()?, *address, *size)?;
+
+            if out.as_os_str() == "-" {
+                io::stdout().lock().write_all(&buffer)?;
+            } else {
+                fs::write(out, &buffer)?;
+            }
+
+            info!("{}", format!("{} of {} bytes were readable", readable_bytes, size));
+        },
+        Commands::DumpModule { process, module, out } => {
+            debug!("{}", format!("action=dump-module, process={:#?}, module={:#?}, out={:#?}", process, module, out));
+
+            let image: Vec<u8> = winapi::dump_module(process.pid()?, module)?;
+            fs::write(out, &image)?;
+
+            info!("{}", format!("{} dumped to {} ({} bytes)", module, out.display(), image.len()));
+        },
+        Commands::FindModule { module, path } => {
+            debug!("{}", format!("action=find-module, module={:#?}, path={:#?}", module, path));
+
+            let module_name: String = if *path {
+                dunce::canonicalize(module)
+                    .ok()
+                    .and_then(|path| path.to_str().map(str::to_owned))
+                    .unwrap_or_else(|| module.clone())
+            } else {
+                module.clone()
+            };
+
+            let mut builder = Builder::default();
+
+            for (pid, process_name, module) in &winapi::processes_with_module(&module_name)? {
+                builder.push_record([
+                    pid.to_string(),
+                    process_name.clone(),
+                    format!("{:#x}", module.base_address),
+                ]);
+            }
+
+            print_table(builder);
+        },
+        Commands::Enum { output, sort, desc, filter, watch, interval, tree, columns, path, user, session, accessible_only, no_header, pids_only } => {
+            debug!("{}", format!("action=enum, output={:#?}, sort={:#?}, desc={:#?}, filter={:#?}, watch={:#?}, interval={:#?}, tree={:#?}, columns={:#?}, path={:#?}, user={:#?}, session={:#?}, accessible_only={:#?}, no_header={:#?}, pids_only={:#?}", output, sort, desc, filter, watch, interval, tree, columns, path, user, session, accessible_only, no_header, pids_only));
+
+            enable_debug_privilege(args.no_debug_privilege);
+
+            if *tree {
+                return print_process_tree(*sort, *desc);
+            }
+
+            let filter: Option<Regex> = filter.as_ref().map(|pattern| {
+                RegexBuilder::new(pattern).case_insensitive(true).build().unwrap_or_else(|e| {
+                    usage_error(format!("invalid filter pattern {:?}: {}", pattern, e))
+                })
+            });
+
+            let mut columns: Vec<Column> = parse_columns(columns);
+            if *path && !columns.contains(&Column::Path) {
+                columns.push(Column::Path);
+            }
+
+            let options = EnumOptions {
+                filter,
+                user: user.clone(),
+                session: *session,
+                accessible_only: *accessible_only,
+                sort: *sort,
+                desc: *desc,
+                columns,
+                header: !*no_header,
+                color: args.color.is_enabled(),
+            };
+
+            if *watch {
+                return watch_processes(&options, Duration::from_secs(*interval));
+            }
+
+            let records: Vec<ProcessRecord> = enumerate_processes(&options)?;
+            let columns: &[Column] = &options.columns;
+
+            if *pids_only {
+                for record in &records {
+                    println!("{}", record.pid);
+                }
+                return Ok(());
+            }
+
+            match output {
+                OutputFormat::Table if records.is_empty() => {
+                    println!("no matching processes");
+                },
+                OutputFormat::Table => {
+                    let mut builder = Builder::default();
+
+                    if options.header {
+                        builder.push_record(columns.iter().map(Column::header));
+                    }
+                    for record in &records {
+                        builder.push_record(record.row(columns));
+                    }
+
+                    let row_records: Vec<&ProcessRecord> = records.iter().collect();
+                    println!("{}", build_process_table(builder, &row_records, &options));
+                    if options.header {
+                        println!("{}", format_process_summary(&records));
+                    }
+                },
+                OutputFormat::Json => {
+                    let objects: Vec<Value> = records.iter().map(|record| record.to_json(columns)).collect();
+                    println!("{}", serde_json::to_string_pretty(&objects).unwrap());
+                },
+                OutputFormat::Csv => {
+                    let header: Vec<&str> = columns.iter().map(Column::name).collect();
+                    print_csv(&header, records.iter().map(|record| record.row(columns)));
+                },
+            }
+        },
+        Commands::Completions { shell } => {
+            debug!("{}", format!("action=completions, shell={:#?}", shell));
+
+            // the script is generated from the definition of the CLI, so it covers every subcommand.
+            clap_complete::generate(*shell, &mut Cli::command(), env!("CARGO_PKG_NAME"), &mut io::stdout());
+        },
+        Commands::GenDocs { format } => {
+            debug!("{}", format!("action=gen-docs, format={:#?}", format));
+
+            // building the command propagates the global options and completes the usages.
+            let mut command: clap::Command = Cli::command().name(env!("CARGO_PKG_NAME"));
+            command.build();
+
+            match format {
+                DocsFormat::Man => print!("{}", docs::man_page(&command, EXIT_CODES)),
+                DocsFormat::Markdown => print!("{}", docs::markdown(&command, EXIT_CODES)),
+            }
+        },
+    }
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use windows::core::{Error, HRESULT};
+    use windows::Win32::Foundation::{ERROR_GEN_FAILURE, ERROR_INVALID_HANDLE, ERROR_PARTIAL_COPY};
+
+    #[test]
+    fn maps_errors_to_exit_codes() {
+        let cases: [(HRESULT, i32); 10] = [
+            (ERROR_FILE_NOT_FOUND.to_hresult(), EXIT_NOT_FOUND),
+            (ERROR_NOT_FOUND.to_hresult(), EXIT_NOT_FOUND),
+            (ERROR_ACCESS_DENIED.to_hresult(), EXIT_ACCESS_DENIED),
+            (ERROR_BAD_EXE_FORMAT.to_hresult(), EXIT_INVALID_DLL),
+            (ERROR_EXE_MACHINE_TYPE_MISMATCH.to_hresult(), EXIT_ARCHITECTURE_MISMATCH),
+            (ERROR_TIMEOUT.to_hresult(), EXIT_TIMEOUT),
+            (ERROR_OPERATION_ABORTED.to_hresult(), EXIT_TIMEOUT),
+            (ERROR_INVALID_PARAMETER.to_hresult(), EXIT_FAILURE),
+            (ERROR_GEN_FAILURE.to_hresult(), EXIT_FAILURE),
+            (HRESULT(-1), EXIT_FAILURE),
+        ];
+
+        for (code, expected) in cases {
+            assert_eq!(exit_code(&Error::new(code, "")), expected, "{:?}", code);
+        }
+        assert_eq!(exit_code(&Error::from(ERROR_INVALID_HANDLE)), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn documents_every_exit_code() {
+        let codes: Vec<i32> = EXIT_CODES.iter().map(|(code, _)| *code).collect();
+
+        assert_eq!(codes, [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        for code in [EXIT_FAILURE, EXIT_NOT_FOUND, EXIT_ACCESS_DENIED, EXIT_INVALID_DLL, EXIT_ARCHITECTURE_MISMATCH, EXIT_TIMEOUT, EXIT_PARTIAL_SUCCESS] {
+            assert!(codes.contains(&code), "exit code {} is not documented", code);
+        }
+        // 2 is the exit code of clap for invalid arguments.
+        assert_eq!(Cli::command().try_get_matches_from(["dll-spider", "--no-such-flag"]).unwrap_err().exit_code(), 2);
+    }
+
+    /// Returns the outcome of loading a DLL that failed with an error.
+    fn failed_load(e: Error, is_injected: bool) -> winapi::DllLoad {
+        winapi::DllLoad {
+            result: Err(e),
+            duration: Duration::ZERO,
+            is_detached: false,
+            is_already_loaded: false,
+            is_injected,
+            is_base_address_unknown: false,
+            remote_dll_path: None,
+        }
+    }
+
+    #[test]
+    fn retries_loads_only_before_injecting() {
+        let access_denied = || Error::from(ERROR_ACCESS_DENIED);
+        let partial_copy = || Error::from(ERROR_PARTIAL_COPY);
+
+        assert!(retryable_load_error(&Err(access_denied())).is_some());
+        assert!(retryable_load_error(&Ok(vec![failed_load(access_denied(), false)])).is_some());
+        // the DLL may have been loaded before its verification failed.
+        assert!(retryable_load_error(&Ok(vec![failed_load(partial_copy(), true)])).is_none());
+        assert!(retryable_load_error(&Ok(vec![failed_load(access_denied(), false), failed_load(partial_copy(), true)])).is_none());
+        assert!(retryable_load_error(&Ok(vec![winapi::DllLoad { result: Ok(0x1000), ..failed_load(access_denied(), true) }])).is_none());
+    }
+
+    /// Parses the arguments of an `eject` command and applies a configuration file to them.
+    fn eject_with_config(extra_args: &[&str], config: &config::Config) -> Process {
+        let mut args: Vec<&str> = vec!["dll-spider", "eject", "-m", "hook.dll"];
+        args.extend_from_slice(extra_args);
+
+        let matches: ArgMatches = with_env(&[], || Cli::command().try_get_matches_from(args).unwrap());
+        let mut cli: Cli = Cli::from_arg_matches(&matches).unwrap();
+        apply_config(&mut cli, &matches, config);
+
+        match cli.command {
+            Commands::Eject { process, .. } => process,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn applies_case_sensitive_to_name_matching() {
+        let config = config::Config { case_sensitive: Some(true), ..Default::default() };
+        let matches = |name: &str, config: &config::Config, process_name: &str| {
+            eject_with_config(&["-n", name], config).name_pattern().unwrap().matches(process_name)
+        };
+
+        // the wildcards and the optional extension still apply, unlike with --exact.
+        assert!(matches("note*", &config, "notepad.exe"));
+        assert!(matches("notepad", &config, "notepad.exe"));
+        assert!(!matches("Notepad", &config, "notepad.exe"));
+        assert!(!matches("NOTE*", &config, "notepad.exe"));
+        assert!(matches("Notepad", &config::Config::default(), "notepad.exe"));
+        assert!(!eject_with_config(&["-n", "notepad"], &config).exact);
+    }
+
+    #[test]
+    fn reports_excluded_targets_as_not_found() {
+        let process: Process = eject_with_config(&["-p", "1", "--exclude", "1"], &config::Config::default());
+
+        let e: Error = process.pids(false).unwrap_err();
+        assert_eq!(e.code(), ERROR_NOT_FOUND.to_hresult());
+        assert_eq!(exit_code(&e), EXIT_NOT_FOUND);
+    }
+
+    /// Environment variables read by the options of the commands.
+    const ENV_VARS: [&str; 4] = ["DLL_SPIDER_TIMEOUT", "DLL_SPIDER_OUTPUT", "DLL_SPIDER_LOG_FILE", "DLL_SPIDER_YES"];
+
+    /// Serializes the tests that parse commands, since the environment variables are shared by
+    /// the tests running in parallel.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Removes the environment variables of `ENV_VARS` when dropped, even if the test panics.
+    struct EnvGuard;
+
+    impl Drop for EnvGuard {
+        fn drop(&mut self) {
+            ENV_VARS.iter().for_each(|name| std::env::remove_var(name));
+        }
+    }
+
+    /// Runs a closure with only the given environment variables of `ENV_VARS` set, while no
+    /// other test parses a command.
+    fn with_env<T>(vars: &[(&str, &str)], f: impl FnOnce() -> T) -> T {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+        let _guard = EnvGuard;
+        ENV_VARS.iter().for_each(|name| std::env::remove_var(name));
+        for (name, value) in vars {
+            std::env::set_var(name, value);
+        }
+
+        f()
+    }
+
+    /// Parses the arguments of a `load` command and applies a configuration file to them.
+    /// 
+    /// Returns the options that the configuration file and the environment variables can set.
+    fn load_with_config(extra_args: &[&str], config: &config::Config) -> (u64, ReportFormat, Vec<String>) {
+        // the DLL only has to exist to be resolved.
+        let module: PathBuf = std::env::current_exe().unwrap();
+        let mut args: Vec<&str> = vec!["dll-spider", "load", "-p", "1", module.to_str().unwrap()];
+        args.extend_from_slice(extra_args);
+
+        let matches: ArgMatches = Cli::command().try_get_matches_from(args).unwrap();
+        let mut cli: Cli = Cli::from_arg_matches(&matches).unwrap();
+        apply_config(&mut cli, &matches, config);
+
+        match cli.command {
+            Commands::Load { timeout, output, process, .. } => (timeout, output, process.exclude),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn applies_the_configuration_file_by_precedence() {
+        let config = config::Config {
+            timeout_ms: Some(1234),
+            output: Some("json".to_owned()),
+            exclude: vec!["config.exe".to_owned()],
+            ..Default::default()
+        };
+        let env: [(&str, &str); 2] = [("DLL_SPIDER_TIMEOUT", "42"), ("DLL_SPIDER_OUTPUT", "table")];
+        let flags: [&str; 6] = ["--timeout", "99", "--output", "json", "--exclude", "flag.exe"];
+
+        // default < configuration file < environment variables < flags.
+        assert_eq!(
+            with_env(&[], || load_with_config(&[], &config::Config::default())),
+            (winapi::DEFAULT_LOAD_TIMEOUT_MS, ReportFormat::Table, vec![]),
+        );
+        assert_eq!(
+            with_env(&[], || load_with_config(&[], &config)),
+            (1234, ReportFormat::Json, vec!["config.exe".to_owned()]),
+        );
+        assert_eq!(
+            with_env(&env, || load_with_config(&[], &config)),
+            (42, ReportFormat::Table, vec!["config.exe".to_owned()]),
+        );
+        assert_eq!(
+            with_env(&env, || load_with_config(&flags, &config)),
+            (99, ReportFormat::Json, vec!["flag.exe".to_owned()]),
+        );
+    }
+
+    #[test]
+    fn reads_the_options_from_the_environment() {
+        let parse = |args: &[&str]| {
+            let module: PathBuf = std::env::current_exe().unwrap();
+            let mut all_args: Vec<&str> = vec!["dll-spider", "load", "-p", "1", module.to_str().unwrap()];
+            all_args.extend_from_slice(args);
+            let cli: Cli = Cli::try_parse_from(all_args).unwrap();
+
+            match cli.command {
+                Commands::Load { yes, .. } => (yes, cli.log_file),
+                _ => unreachable!(),
+            }
+        };
+        let env: [(&str, &str); 2] = [("DLL_SPIDER_YES", "1"), ("DLL_SPIDER_LOG_FILE", "env.log")];
+
+        assert_eq!(with_env(&[], || parse(&[])), (false, None));
+        assert_eq!(with_env(&env, || parse(&[])), (true, Some(PathBuf::from("env.log"))));
+        assert_eq!(with_env(&env, || parse(&["--log-file", "flag.log"])), (true, Some(PathBuf::from("flag.log"))));
+        assert_eq!(with_env(&[("DLL_SPIDER_YES", "off")], || parse(&["--yes"])), (true, None));
     }
 }
@@ -0,0 +1,701 @@
+//! Contains helper functions that parse the headers of PE images.
+
+#![warn(missing_docs)]
+
+
+use windows::core::{
+    Error,
+    HRESULT,
+    Result,
+};
+
+
+/// `IMAGE_OPTIONAL_HEADER.Magic` value of 32-bit images.
+const IMAGE_NT_OPTIONAL_HDR32_MAGIC: u16 = 0x10b;
+
+/// `IMAGE_OPTIONAL_HEADER.Magic` value of 64-bit images.
+const IMAGE_NT_OPTIONAL_HDR64_MAGIC: u16 = 0x20b;
+
+/// Size of an `IMAGE_SECTION_HEADER` structure, in bytes.
+const IMAGE_SIZEOF_SECTION_HEADER: usize = 40;
+
+/// Size of an `IMAGE_EXPORT_DIRECTORY` structure, in bytes.
+const IMAGE_SIZEOF_EXPORT_DIRECTORY: usize = 40;
+
+/// Maximum number of exported functions and names accepted in an export directory.
+const MAX_EXPORTS: usize = 0x10000;
+
+/// Maximum length of an export name, in bytes.
+const MAX_EXPORT_NAME_LENGTH: usize = 512;
+
+/// Size of an `IMAGE_IMPORT_DESCRIPTOR` structure, in bytes.
+const IMAGE_SIZEOF_IMPORT_DESCRIPTOR: usize = 20;
+
+/// Bit of a 64-bit import thunk set when the function is imported by ordinal.
+const IMAGE_ORDINAL_FLAG64: u64 = 1 << 63;
+
+/// Base relocation that is skipped, used to pad the blocks.
+const IMAGE_REL_BASED_ABSOLUTE: u16 = 0;
+
+/// Base relocation of a 32-bit address.
+const IMAGE_REL_BASED_HIGHLOW: u16 = 3;
+
+/// Base relocation of a 64-bit address.
+const IMAGE_REL_BASED_DIR64: u16 = 10;
+
+/// Index of the export directory in the data directories.
+pub const IMAGE_DIRECTORY_ENTRY_EXPORT: usize = 0;
+
+/// Index of the import directory in the data directories.
+pub const IMAGE_DIRECTORY_ENTRY_IMPORT: usize = 1;
+
+/// Index of the exception directory (the `RUNTIME_FUNCTION` table) in the data directories.
+pub const IMAGE_DIRECTORY_ENTRY_EXCEPTION: usize = 3;
+
+/// Index of the base relocation directory in the data directories.
+pub const IMAGE_DIRECTORY_ENTRY_BASERELOC: usize = 5;
+
+/// Index of the TLS directory in the data directories.
+pub const IMAGE_DIRECTORY_ENTRY_TLS: usize = 9;
+
+/// Index of the delay-load import directory in the data directories.
+pub const IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT: usize = 13;
+
+/// Index of the CLR runtime header in the data directories, present in .NET images.
+pub const IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR: usize = 14;
+
+/// `IMAGE_FILE_HEADER.Characteristics` flag set for DLL images.
+pub const IMAGE_FILE_DLL: u16 = 0x2000;
+
+/// Machine types of the images that can be loaded: x86, x64, ARM and ARM64.
+const SUPPORTED_MACHINES: [u16; 4] = [0x014c, 0x8664, 0x01c4, 0xaa64];
+
+/// Section characteristic of sections that contain executable code.
+pub const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+
+/// Section characteristic of sections that can be read.
+pub const IMAGE_SCN_MEM_READ: u32 = 0x4000_0000;
+
+/// Section characteristic of sections that can be written to.
+pub const IMAGE_SCN_MEM_WRITE: u32 = 0x8000_0000;
+
+
+/// Header of a section of a PE image.
+#[derive(Clone, Debug)]
+pub struct SectionHeader {
+    /// Name of the section.
+    pub name: String,
+    /// Size of the section once loaded in memory.
+    pub virtual_size: u32,
+    /// Address of the section once loaded in memory, relative to the image base.
+    pub virtual_address: u32,
+    /// Size of the section on disk.
+    pub size_of_raw_data: u32,
+    /// Offset of the section in the file.
+    pub pointer_to_raw_data: u32,
+    /// Flags of the section (e.g. `IMAGE_SCN_MEM_EXECUTE`).
+    pub characteristics: u32,
+    /// Offset of the section header in the image.
+    pub header_offset: usize,
+}
+
+/// Headers of a PE image.
+#[derive(Clone, Debug)]
+pub struct PeHeaders {
+    /// `IMAGE_FILE_HEADER.Machine`.
+    pub machine: u16,
+    /// `IMAGE_FILE_HEADER.Characteristics`.
+    pub characteristics: u16,
+    /// Whether the optional header is a 64-bit (PE32+) header.
+    pub is_64_bit: bool,
+    /// Offset of the optional header in the image.
+    pub optional_header_offset: usize,
+    /// `IMAGE_OPTIONAL_HEADER.AddressOfEntryPoint`.
+    pub address_of_entry_point: u32,
+    /// `IMAGE_OPTIONAL_HEADER.ImageBase`.
+    pub image_base: u64,
+    /// `IMAGE_OPTIONAL_HEADER.FileAlignment`.
+    pub file_alignment: u32,
+    /// `IMAGE_OPTIONAL_HEADER.SizeOfImage`.
+    pub size_of_image: u32,
+    /// `IMAGE_OPTIONAL_HEADER.SizeOfHeaders`.
+    pub size_of_headers: u32,
+    /// RVA and size of the export directory, if the image has one.
+    pub export_directory: Option<(u32, u32)>,
+    /// RVA and size of the data directories, in order (e.g. `IMAGE_DIRECTORY_ENTRY_IMPORT`).
+    pub data_directories: Vec<(u32, u32)>,
+    /// Section table of the image.
+    pub sections: Vec<SectionHeader>,
+}
+
+impl PeHeaders {
+    /// Returns the offset of `IMAGE_OPTIONAL_HEADER.ImageBase` in the image.
+    pub fn image_base_offset(&self) -> usize {
+        // ImageBase is preceded by BaseOfData in 32-bit images.
+        self.optional_header_offset + if self.is_64_bit { 24 } else { 28 }
+    }
+
+    /// Returns the RVA and size of a data directory, if the image has one.
+    pub fn data_directory(&self, index: usize) -> Option<(u32, u32)> {
+        self.data_directories.get(index).copied().filter(|&(rva, _)| rva != 0)
+    }
+}
+
+
+/// Function imported by a PE image.
+#[derive(Clone, Debug)]
+pub struct Import {
+    /// Name of the module that exports the function.
+    pub module_name: String,
+    /// Function imported from the module.
+    pub symbol: ImportSymbol,
+    /// Address of the slot of the import address table that receives the address of the
+    /// function, relative to the image base.
+    pub thunk_rva: u32,
+}
+
+/// Function imported by a PE image, by name or by ordinal.
+#[derive(Clone, Debug)]
+pub enum ImportSymbol {
+    /// Function imported by name.
+    Name(String),
+    /// Function imported by ordinal.
+    Ordinal(u16),
+}
+
+impl std::fmt::Display for ImportSymbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportSymbol::Name(name) => write!(f, "{}", name),
+            ImportSymbol::Ordinal(ordinal) => write!(f, "#{}", ordinal),
+        }
+    }
+}
+
+
+/// TLS directory of a PE image.
+#[derive(Clone, Debug)]
+pub struct TlsDirectory {
+    /// Size of the template of the static TLS data, including the zero-filled part, in bytes.
+    pub static_data_size: u64,
+    /// Addresses of the TLS callbacks, relative to the image base.
+    pub callbacks: Vec<u32>,
+}
+
+
+/// Reads a little-endian u16 from a buffer.
+pub fn read_u16(buffer: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(buffer.get(offset..offset.checked_add(2)?)?.try_into().ok()?))
+}
+
+/// Reads a little-endian u32 from a buffer.
+pub fn read_u32(buffer: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(buffer.get(offset..offset.checked_add(4)?)?.try_into().ok()?))
+}
+
+/// Reads a little-endian u64 from a buffer.
+pub fn read_u64(buffer: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(buffer.get(offset..offset.checked_add(8)?)?.try_into().ok()?))
+}
+
+/// Reads a null-terminated string from a buffer.
+fn read_c_string(buffer: &[u8], offset: usize) -> Option<String> {
+    let bytes: &[u8] = buffer.get(offset..)?;
+    let length: usize = bytes.iter().position(|&c| c == 0)?;
+
+    Some(String::from_utf8_lossy(&bytes[..length]).into_owned())
+}
+
+
+/// Writes a little-endian u32 to a buffer.
+pub fn write_u32(buffer: &mut [u8], offset: usize, value: u32) {
+    buffer[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Writes a little-endian u64 to a buffer.
+pub fn write_u64(buffer: &mut [u8], offset: usize, value: u64) {
+    buffer[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+
+/// Parses the headers of a PE image.
+///
+/// Works both on files and on images loaded in memory, as long as the buffer starts with
+/// the DOS header and contains the whole section table.
+pub fn parse_headers(image: &[u8]) -> Result<PeHeaders> {
+    let malformed = || Error::new(HRESULT(-1), "malformed PE headers");
+
+    // IMAGE_DOS_HEADER.e_lfanew points to the PE signature, followed by the IMAGE_FILE_HEADER.
+    if image.get(..2) != Some(b"MZ") {
+        return Err(Error::new(HRESULT(-1), "not a PE file"));
+    }
+    let e_lfanew: usize = read_u32(image, 0x3c).ok_or_else(malformed)? as usize;
+    if image.get(e_lfanew..e_lfanew.saturating_add(4)) != Some(b"PE\0\0") {
+        return Err(Error::new(HRESULT(-1), "not a PE file"));
+    }
+
+    let file_header_offset: usize = e_lfanew + 4;
+    let machine: u16 = read_u16(image, file_header_offset).ok_or_else(malformed)?;
+    let number_of_sections: u16 = read_u16(image, file_header_offset + 2).ok_or_else(malformed)?;
+    let size_of_optional_header: u16 = read_u16(image, file_header_offset + 16).ok_or_else(malformed)?;
+    let characteristics: u16 = read_u16(image, file_header_offset + 18).ok_or_else(malformed)?;
+
+    let optional_header_offset: usize = file_header_offset + 20;
+    let is_64_bit: bool = match read_u16(image, optional_header_offset).ok_or_else(malformed)? {
+        IMAGE_NT_OPTIONAL_HDR32_MAGIC => false,
+        IMAGE_NT_OPTIONAL_HDR64_MAGIC => true,
+        magic => return Err(Error::new(HRESULT(-1), format!("unsupported optional header magic {:#x}", magic))),
+    };
+    let address_of_entry_point: u32 = read_u32(image, optional_header_offset + 16).ok_or_else(malformed)?;
+    let image_base: u64 = if is_64_bit {
+        read_u64(image, optional_header_offset + 24).ok_or_else(malformed)?
+    } else {
+        read_u32(image, optional_header_offset + 28).ok_or_else(malformed)? as u64
+    };
+    let file_alignment: u32 = read_u32(image, optional_header_offset + 36).ok_or_else(malformed)?;
+    let size_of_image: u32 = read_u32(image, optional_header_offset + 56).ok_or_else(malformed)?;
+    let size_of_headers: u32 = read_u32(image, optional_header_offset + 60).ok_or_else(malformed)?;
+
+    // the data directories are the last fields of the optional header, the export directory comes first.
+    let data_directories_offset: usize = optional_header_offset + if is_64_bit { 112 } else { 96 };
+    let number_of_rva_and_sizes: u32 = read_u32(image, data_directories_offset - 4).ok_or_else(malformed)?;
+    let data_directories: Vec<(u32, u32)> = (0..number_of_rva_and_sizes.min(16) as usize)
+        .map(|i| {
+            let rva: u32 = read_u32(image, data_directories_offset + i * 8).ok_or_else(malformed)?;
+            let size: u32 = read_u32(image, data_directories_offset + i * 8 + 4).ok_or_else(malformed)?;
+            Ok((rva, size))
+        })
+        .collect::<Result<Vec<(u32, u32)>>>()?;
+    let export_directory: Option<(u32, u32)> = data_directories
+        .get(IMAGE_DIRECTORY_ENTRY_EXPORT)
+        .copied()
+        .filter(|&(rva, _)| rva != 0);
+
+    let section_table_offset: usize = optional_header_offset + size_of_optional_header as usize;
+    let sections: Vec<SectionHeader> = (0..number_of_sections as usize)
+        .map(|i| {
+            let header_offset: usize = section_table_offset + i * IMAGE_SIZEOF_SECTION_HEADER;
+            let name: &[u8] = image.get(header_offset..header_offset + 8).ok_or_else(malformed)?;
+
+            Ok(SectionHeader {
+                name: String::from_utf8_lossy(name).trim_end_matches('\0').to_owned(),
+                virtual_size: read_u32(image, header_offset + 8).ok_or_else(malformed)?,
+                virtual_address: read_u32(image, header_offset + 12).ok_or_else(malformed)?,
+                size_of_raw_data: read_u32(image, header_offset + 16).ok_or_else(malformed)?,
+                pointer_to_raw_data: read_u32(image, header_offset + 20).ok_or_else(malformed)?,
+                characteristics: read_u32(image, header_offset + 36).ok_or_else(malformed)?,
+                header_offset,
+            })
+        })
+        .collect::<Result<Vec<SectionHeader>>>()?;
+
+    Ok(PeHeaders {
+        machine,
+        characteristics,
+        is_64_bit,
+        optional_header_offset,
+        address_of_entry_point,
+        image_base,
+        file_alignment,
+        size_of_image,
+        size_of_headers,
+        export_directory,
+        data_directories,
+        sections,
+    })
+}
+
+
+/// Parses the headers of a DLL file, and checks that the image is a DLL for a machine that can
+/// load it.
+pub fn parse_dll_headers(file: &[u8]) -> Result<PeHeaders> {
+    let headers: PeHeaders = parse_headers(file)?;
+
+    if headers.characteristics & IMAGE_FILE_DLL == 0 {
+        return Err(Error::new(HRESULT(-1), "is an EXE, not a DLL"));
+    }
+    if !SUPPORTED_MACHINES.contains(&headers.machine) {
+        return Err(Error::new(HRESULT(-1), format!("unsupported machine {:#06x}", headers.machine)));
+    }
+
+    Ok(headers)
+}
+
+
+/// Function exported by name by a PE image.
+#[derive(Clone, Debug)]
+pub struct Export {
+    /// Name of the export.
+    pub name: String,
+    /// Ordinal of the export, including the ordinal base of the image.
+    pub ordinal: u32,
+    /// Address of the export, relative to the image base.
+    pub rva: u32,
+    /// Whether the export is forwarded to another module, in which case `rva` points to
+    /// the name of the forwarder rather than to code.
+    pub is_forwarded: bool,
+}
+
+
+/// Parses the functions exported by name by a PE image.
+///
+/// The image is accessed through `read`, which returns `size` bytes at an RVA, so that the
+/// exports of images loaded in the memory of other processes can be parsed without copying
+/// the whole image.
+pub fn parse_exports(headers: &PeHeaders, mut read: impl FnMut(u32, usize) -> Result<Vec<u8>>) -> Result<Vec<Export>> {
+    let malformed = || Error::new(HRESULT(-1), "malformed export directory");
+
+    let Some((directory_rva, directory_size)) = headers.export_directory else {
+        return Ok(Vec::new());
+    };
+
+    let directory: Vec<u8> = read(directory_rva, IMAGE_SIZEOF_EXPORT_DIRECTORY)?;
+    let ordinal_base: u32 = read_u32(&directory, 16).ok_or_else(malformed)?;
+    let number_of_functions: usize = read_u32(&directory, 20).ok_or_else(malformed)? as usize;
+    let number_of_names: usize = read_u32(&directory, 24).ok_or_else(malformed)? as usize;
+    if number_of_functions > MAX_EXPORTS || number_of_names > MAX_EXPORTS {
+        return Err(malformed());
+    }
+
+    let functions: Vec<u8> = read(read_u32(&directory, 28).ok_or_else(malformed)?, number_of_functions * 4)?;
+    let names: Vec<u8> = read(read_u32(&directory, 32).ok_or_else(malformed)?, number_of_names * 4)?;
+    let ordinals: Vec<u8> = read(read_u32(&directory, 36).ok_or_else(malformed)?, number_of_names * 2)?;
+
+    (0..number_of_names)
+        .map(|i| {
+            let name_rva: u32 = read_u32(&names, i * 4).ok_or_else(malformed)?;
+            let ordinal: usize = read_u16(&ordinals, i * 2).ok_or_else(malformed)? as usize;
+            let rva: u32 = read_u32(&functions, ordinal * 4).ok_or_else(malformed)?;
+
+            let name: Vec<u8> = read(name_rva, MAX_EXPORT_NAME_LENGTH)?;
+            let name_length: usize = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+
+            Ok(Export {
+                name: String::from_utf8_lossy(&name[..name_length]).into_owned(),
+                ordinal: ordinal_base.wrapping_add(ordinal as u32),
+                rva,
+                is_forwarded: rva >= directory_rva && rva < directory_rva.saturating_add(directory_size),
+            })
+        })
+        .collect()
+}
+
+
+/// Converts an RVA to an offset in the file of a PE image, if it lies in the headers or in the
+/// raw data of a section.
+pub fn rva_to_offset(headers: &PeHeaders, rva: u32) -> Option<usize> {
+    if rva < headers.size_of_headers {
+        return Some(rva as usize);
+    }
+
+    headers
+        .sections
+        .iter()
+        .find(|section| rva >= section.virtual_address && rva - section.virtual_address < section.size_of_raw_data)
+        .and_then(|section| (rva - section.virtual_address).checked_add(section.pointer_to_raw_data))
+        .map(|offset| offset as usize)
+}
+
+
+/// Rounds a value up to a multiple of an alignment.
+pub fn align_up(value: u32, alignment: u32) -> u32 {
+    if alignment == 0 {
+        return value;
+    }
+
+    value.div_ceil(alignment) * alignment
+}
+
+
+/// Copies the headers and the sections of a PE file to a buffer laid out as the image is once
+/// loaded in memory, where RVAs are offsets.
+pub fn map_image(file: &[u8], headers: &PeHeaders) -> Result<Vec<u8>> {
+    let malformed = || Error::new(HRESULT(-1), "malformed PE file");
+
+    let mut image: Vec<u8> = vec![0; headers.size_of_image as usize];
+
+    let size_of_headers: usize = headers.size_of_headers as usize;
+    image
+        .get_mut(..size_of_headers)
+        .ok_or_else(malformed)?
+        .copy_from_slice(file.get(..size_of_headers).ok_or_else(malformed)?);
+
+    for section in &headers.sections {
+        // the raw data is padded to the file alignment, the virtual size is the size that matters.
+        let size: usize = match section.virtual_size {
+            0 => section.size_of_raw_data,
+            virtual_size => section.size_of_raw_data.min(virtual_size),
+        } as usize;
+        if size == 0 {
+            continue;
+        }
+
+        let source: usize = section.pointer_to_raw_data as usize;
+        let destination: usize = section.virtual_address as usize;
+        image
+            .get_mut(destination..destination + size)
+            .ok_or_else(malformed)?
+            .copy_from_slice(file.get(source..source + size).ok_or_else(malformed)?);
+    }
+
+    Ok(image)
+}
+
+
+/// Applies the base relocations of a mapped image, for an image loaded `delta` bytes away
+/// from its preferred base address.
+pub fn apply_relocations(image: &mut [u8], headers: &PeHeaders, delta: u64) -> Result<()> {
+    let malformed = || Error::new(HRESULT(-1), "malformed base relocation directory");
+
+    let Some((directory_rva, directory_size)) = headers.data_directory(IMAGE_DIRECTORY_ENTRY_BASERELOC) else {
+        return Ok(());
+    };
+    let directory_end: usize = directory_rva as usize + directory_size as usize;
+
+    // the directory is a sequence of IMAGE_BASE_RELOCATION blocks, one per page, each followed by its entries.
+    let mut block_offset: usize = directory_rva as usize;
+    while block_offset + 8 <= directory_end {
+        let page_rva: usize = read_u32(image, block_offset).ok_or_else(malformed)? as usize;
+        let size_of_block: usize = read_u32(image, block_offset + 4).ok_or_else(malformed)? as usize;
+        if size_of_block < 8 || block_offset + size_of_block > directory_end {
+            return Err(malformed());
+        }
+
+        for entry_offset in (block_offset + 8..block_offset + size_of_block).step_by(2) {
+            let entry: u16 = read_u16(image, entry_offset).ok_or_else(malformed)?;
+            let offset: usize = page_rva + (entry & 0xfff) as usize;
+
+            match entry >> 12 {
+                IMAGE_REL_BASED_ABSOLUTE => {},
+                IMAGE_REL_BASED_HIGHLOW => {
+                    let value: u32 = read_u32(image, offset).ok_or_else(malformed)?;
+                    write_u32(image, offset, value.wrapping_add(delta as u32));
+                },
+                IMAGE_REL_BASED_DIR64 => {
+                    let value: u64 = read_u64(image, offset).ok_or_else(malformed)?;
+                    write_u64(image, offset, value.wrapping_add(delta));
+                },
+                relocation_type => {
+                    return Err(Error::new(HRESULT(-1), format!("unsupported base relocation type {}", relocation_type)));
+                },
+            }
+        }
+
+        block_offset += size_of_block;
+    }
+
+    Ok(())
+}
+
+
+/// Parses the functions imported by a mapped 64-bit image.
+pub fn parse_imports(image: &[u8], headers: &PeHeaders) -> Result<Vec<Import>> {
+    let malformed = || Error::new(HRESULT(-1), "malformed import directory");
+
+    let Some((directory_rva, _)) = headers.data_directory(IMAGE_DIRECTORY_ENTRY_IMPORT) else {
+        return Ok(Vec::new());
+    };
+
+    let mut imports: Vec<Import> = Vec::new();
+
+    // the directory is an array of IMAGE_IMPORT_DESCRIPTOR, one per module, terminated by a zeroed one.
+    for descriptor_offset in (directory_rva as usize..).step_by(IMAGE_SIZEOF_IMPORT_DESCRIPTOR) {
+        let original_first_thunk: u32 = read_u32(image, descriptor_offset).ok_or_else(malformed)?;
+        let name_rva: u32 = read_u32(image, descriptor_offset + 12).ok_or_else(malformed)?;
+        let first_thunk: u32 = read_u32(image, descriptor_offset + 16).ok_or_else(malformed)?;
+        if name_rva == 0 && first_thunk == 0 {
+            break;
+        }
+
+        let module_name: String = read_c_string(image, name_rva as usize).ok_or_else(malformed)?;
+
+        // the lookup table is the pristine copy of the import address table, which some linkers omit.
+        let lookup_rva: u32 = if original_first_thunk != 0 { original_first_thunk } else { first_thunk };
+        for i in 0.. {
+            let thunk: u64 = read_u64(image, lookup_rva as usize + i * 8).ok_or_else(malformed)?;
+            if thunk == 0 {
+                break;
+            }
+
+            let symbol: ImportSymbol = if thunk & IMAGE_ORDINAL_FLAG64 != 0 {
+                ImportSymbol::Ordinal(thunk as u16)
+            } else {
+                // IMAGE_IMPORT_BY_NAME starts with a hint, followed by the name.
+                let hint_name_rva: usize = (thunk as u32) as usize;
+                ImportSymbol::Name(read_c_string(image, hint_name_rva + 2).ok_or_else(malformed)?)
+            };
+
+            imports.push(Import {
+                module_name: module_name.clone(),
+                symbol,
+                thunk_rva: first_thunk + (i * 8) as u32,
+            });
+        }
+    }
+
+    Ok(imports)
+}
+
+
+/// Parses the TLS directory of a mapped 64-bit image, if it has one.
+///
+/// The image must already be relocated for `image_base`, since the TLS directory holds
+/// absolute addresses.
+pub fn parse_tls_directory(image: &[u8], headers: &PeHeaders, image_base: u64) -> Result<Option<TlsDirectory>> {
+    let malformed = || Error::new(HRESULT(-1), "malformed TLS directory");
+
+    let Some((directory_rva, _)) = headers.data_directory(IMAGE_DIRECTORY_ENTRY_TLS) else {
+        return Ok(None);
+    };
+    let to_rva = |address: u64| -> Result<usize> {
+        address.checked_sub(image_base).map(|rva| rva as usize).ok_or_else(malformed)
+    };
+
+    // IMAGE_TLS_DIRECTORY64: StartAddressOfRawData, EndAddressOfRawData, AddressOfIndex, AddressOfCallBacks, SizeOfZeroFill.
+    let directory_offset: usize = directory_rva as usize;
+    let start_of_raw_data: u64 = read_u64(image, directory_offset).ok_or_else(malformed)?;
+    let end_of_raw_data: u64 = read_u64(image, directory_offset + 8).ok_or_else(malformed)?;
+    let address_of_callbacks: u64 = read_u64(image, directory_offset + 24).ok_or_else(malformed)?;
+    let size_of_zero_fill: u32 = read_u32(image, directory_offset + 32).ok_or_else(malformed)?;
+
+    let mut callbacks: Vec<u32> = Vec::new();
+    if address_of_callbacks != 0 {
+        let callbacks_offset: usize = to_rva(address_of_callbacks)?;
+        for i in 0.. {
+            let callback: u64 = read_u64(image, callbacks_offset + i * 8).ok_or_else(malformed)?;
+            if callback == 0 {
+                break;
+            }
+            callbacks.push(to_rva(callback)?.try_into().map_err(|_| malformed())?);
+        }
+    }
+
+    Ok(Some(TlsDirectory {
+        static_data_size: end_of_raw_data.saturating_sub(start_of_raw_data) + size_of_zero_fill as u64,
+        callbacks,
+    }))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Machine type of x86 images.
+    const IMAGE_FILE_MACHINE_I386: u16 = 0x014c;
+
+    /// Machine type of x64 images.
+    const IMAGE_FILE_MACHINE_AMD64: u16 = 0x8664;
+
+    /// Offset of the PE signature in the crafted images.
+    const E_LFANEW: usize = 0x40;
+
+    /// Returns the headers of an image without sections, with 16 empty data directories.
+    fn craft_headers(machine: u16, characteristics: u16, is_64_bit: bool) -> Vec<u8> {
+        let size_of_optional_header: usize = if is_64_bit { 240 } else { 224 };
+        let optional_header_offset: usize = E_LFANEW + 24;
+        let mut image: Vec<u8> = vec![0; optional_header_offset + size_of_optional_header];
+
+        image[..2].copy_from_slice(b"MZ");
+        write_u32(&mut image, 0x3c, E_LFANEW as u32);
+        image[E_LFANEW..E_LFANEW + 4].copy_from_slice(b"PE\0\0");
+        image[E_LFANEW + 4..E_LFANEW + 6].copy_from_slice(&machine.to_le_bytes());
+        image[E_LFANEW + 20..E_LFANEW + 22].copy_from_slice(&(size_of_optional_header as u16).to_le_bytes());
+        image[E_LFANEW + 22..E_LFANEW + 24].copy_from_slice(&characteristics.to_le_bytes());
+
+        let magic: u16 = if is_64_bit { IMAGE_NT_OPTIONAL_HDR64_MAGIC } else { IMAGE_NT_OPTIONAL_HDR32_MAGIC };
+        image[optional_header_offset..optional_header_offset + 2].copy_from_slice(&magic.to_le_bytes());
+        let number_of_rva_and_sizes_offset: usize = optional_header_offset + if is_64_bit { 108 } else { 92 };
+        write_u32(&mut image, number_of_rva_and_sizes_offset, 16);
+
+        image
+    }
+
+    /// Returns the message of the error returned for an image.
+    fn error_message(image: &[u8]) -> String {
+        parse_dll_headers(image).expect_err("the image should be rejected").message()
+    }
+
+    #[test]
+    fn rejects_files_without_mz_signature() {
+        let mut image: Vec<u8> = craft_headers(IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_DLL, true);
+        image[..2].copy_from_slice(b"ZM");
+
+        assert_eq!(error_message(&image), "not a PE file");
+        assert_eq!(error_message(b""), "not a PE file");
+    }
+
+    #[test]
+    fn rejects_files_without_pe_signature() {
+        let mut image: Vec<u8> = craft_headers(IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_DLL, true);
+        image[E_LFANEW..E_LFANEW + 4].copy_from_slice(b"NE\0\0");
+        assert_eq!(error_message(&image), "not a PE file");
+
+        let mut image: Vec<u8> = craft_headers(IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_DLL, true);
+        write_u32(&mut image, 0x3c, 0xffff_fff0);
+        assert_eq!(error_message(&image), "not a PE file");
+    }
+
+    #[test]
+    fn rejects_executables() {
+        let image: Vec<u8> = craft_headers(IMAGE_FILE_MACHINE_AMD64, 0x0002, true);
+
+        assert_eq!(error_message(&image), "is an EXE, not a DLL");
+    }
+
+    #[test]
+    fn rejects_unknown_machines() {
+        let image: Vec<u8> = craft_headers(0x0200, IMAGE_FILE_DLL, true);
+
+        assert_eq!(error_message(&image), "unsupported machine 0x0200");
+    }
+
+    #[test]
+    fn parses_x86_dll_headers() {
+        let headers: PeHeaders = parse_dll_headers(&craft_headers(IMAGE_FILE_MACHINE_I386, IMAGE_FILE_DLL, false)).unwrap();
+
+        assert_eq!(headers.machine, IMAGE_FILE_MACHINE_I386);
+        assert!(!headers.is_64_bit);
+        assert_eq!(headers.data_directories.len(), 16);
+        assert!(headers.sections.is_empty());
+    }
+
+    #[test]
+    fn parses_x64_dll_headers() {
+        let headers: PeHeaders = parse_dll_headers(&craft_headers(IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_DLL, true)).unwrap();
+
+        assert_eq!(headers.machine, IMAGE_FILE_MACHINE_AMD64);
+        assert!(headers.is_64_bit);
+        assert_eq!(headers.data_directories.len(), 16);
+        assert!(headers.sections.is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_headers_without_panicking() {
+        for is_64_bit in [false, true] {
+            let machine: u16 = if is_64_bit { IMAGE_FILE_MACHINE_AMD64 } else { IMAGE_FILE_MACHINE_I386 };
+            let image: Vec<u8> = craft_headers(machine, IMAGE_FILE_DLL, is_64_bit);
+
+            for length in 0..image.len() {
+                assert!(parse_dll_headers(&image[..length]).is_err(), "{} bytes should be rejected", length);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_section_offsets_past_the_address_space() {
+        let mut headers: PeHeaders = parse_dll_headers(&craft_headers(IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_DLL, true)).unwrap();
+        headers.sections.push(SectionHeader {
+            name: ".text".to_owned(),
+            virtual_size: 0x1000,
+            virtual_address: 0x1000,
+            size_of_raw_data: 0x1000,
+            pointer_to_raw_data: 0xffff_f000,
+            characteristics: 0,
+            header_offset: 0,
+        });
+
+        assert_eq!(rva_to_offset(&headers, 0x1800), None);
+        assert_eq!(rva_to_offset(&headers, 0x1000), Some(0xffff_f000));
+    }
+}
@@ -0,0 +1,77 @@
+//! Contains helper functions that retry the operations on target processes.
+//!
+//! A process that was just started may not let itself be opened, or have its modules listed,
+//! for the first few hundred milliseconds, so these errors are retried with an exponential
+//! backoff, while the others are returned at once.
+
+#![warn(missing_docs)]
+
+
+use std::thread;
+use std::time::Duration;
+
+use log::warn;
+use windows::core::{Error, Result};
+use windows::Win32::Foundation::{ERROR_ACCESS_DENIED, ERROR_PARTIAL_COPY};
+
+
+/// Default delay before the first retry, in milliseconds.
+pub const DEFAULT_RETRY_DELAY_MS: u64 = 100;
+
+/// Longest delay between two attempts, however many retries were made.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+
+/// Policy for retrying an operation on a target process that fails with a transient error.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt.
+    pub retries: u32,
+    /// Delay before the first retry, doubled before each of the next ones.
+    pub delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Runs an operation, retrying it while it fails with a transient error.
+    ///
+    /// `operation` describes the operation in the logs (e.g. `unloading hook.dll from process
+    /// (1234)`). Returns the outcome of the last attempt.
+    pub fn run<T>(&self, operation: &str, attempt: impl FnMut() -> Result<T>) -> Result<T> {
+        self.run_with(operation, attempt, |outcome| outcome.as_ref().err())
+    }
+
+    /// Runs an operation whose outcome is not a plain `Result`, retrying it while `error_of`
+    /// returns a transient error for its outcome.
+    ///
+    /// Returns the outcome of the last attempt.
+    pub fn run_with<T>(&self, operation: &str, mut attempt: impl FnMut() -> T, error_of: impl Fn(&T) -> Option<&Error>) -> T {
+        let mut delay: Duration = self.delay;
+
+        for retry in 1..=self.retries {
+            let outcome: T = attempt();
+            match error_of(&outcome) {
+                Some(e) if is_transient(e) => {
+                    warn!("{}", format!(
+                        "{} failed: {}, retrying in {} ms ({} of {})",
+                        operation, e.message(), delay.as_millis(), retry, self.retries,
+                    ));
+                    thread::sleep(delay);
+                    delay = delay.saturating_mul(2).min(MAX_RETRY_DELAY);
+                },
+                _ => return outcome,
+            }
+        }
+
+        attempt()
+    }
+}
+
+
+/// Returns whether an error may go away once the target process is done initializing.
+///
+/// Processes deny access to themselves until their token and security descriptor are set up,
+/// and their module list cannot be read until the loader has initialized it. Other errors,
+/// e.g. a missing DLL or an architecture mismatch, are permanent.
+fn is_transient(e: &Error) -> bool {
+    e.code() == ERROR_ACCESS_DENIED.to_hresult() || e.code() == ERROR_PARTIAL_COPY.to_hresult()
+}
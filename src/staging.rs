@@ -0,0 +1,136 @@
+//! Contains helper functions that stage copies of the DLLs before they are loaded.
+//!
+//! A DLL cannot be overwritten while a process has it loaded, so loading a copy leaves the
+//! original free to be rebuilt.
+
+#![warn(missing_docs)]
+
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use log::{debug, info, warn};
+use windows::core::{Error, Result};
+use windows::Win32::Foundation::ERROR_INVALID_PARAMETER;
+
+use crate::winapi;
+
+
+/// Name of the staging directory created in the temporary directory.
+const STAGING_DIRECTORY_NAME: &str = "dll-spider";
+
+/// Number of hexadecimal digits of the SHA-256 digest of a DLL used to name its directory.
+const DIGEST_PREFIX_LENGTH: usize = 16;
+
+/// Age after which a staged copy is deleted, unless it is loaded by a process.
+pub const MAX_STAGED_COPY_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+
+/// Returns the default staging directory, `%TEMP%\dll-spider`.
+pub fn default_directory() -> PathBuf {
+    env::temp_dir().join(STAGING_DIRECTORY_NAME)
+}
+
+
+/// Copies a DLL to `<directory>\<digest>\<name>`, where `digest` is the start of the SHA-256
+/// digest of the DLL, creating the directories as needed.
+///
+/// An existing copy is reused, since it has the same content and may be loaded by a process.
+/// Returns the path of the copy.
+pub fn stage(path: &Path, directory: &Path) -> Result<PathBuf> {
+    let Some(file_name) = path.file_name() else {
+        return Err(Error::new(ERROR_INVALID_PARAMETER.to_hresult(), format!("invalid DLL path {}", path.display())));
+    };
+    let digest: String = winapi::get_file_sha256(path)?;
+
+    let copy_directory: PathBuf = directory.join(&digest[..DIGEST_PREFIX_LENGTH]);
+    let copy_path: PathBuf = copy_directory.join(file_name);
+    if copy_path.is_file() {
+        debug!("{}", format!("reusing the copy of {} staged at {}", path.display(), copy_path.display()));
+    } else {
+        fs::create_dir_all(&copy_directory)?;
+        fs::copy(path, &copy_path)?;
+        info!("{}", format!("staged {} at {}", path.display(), copy_path.display()));
+    }
+
+    Ok(dunce::canonicalize(&copy_path).unwrap_or(copy_path))
+}
+
+
+/// Deletes the copies staged in a directory more than `max_age` ago, and the directories they
+/// leave empty.
+///
+/// Only the `<digest>\<name>.dll` entries created by `stage` are considered, since the
+/// directory passed with `--copy-to` may hold other files. The copies loaded by a process are
+/// never deleted. Failures are logged and ignored, since the cleanup is opportunistic.
+pub fn remove_stale_copies(directory: &Path, max_age: Duration) {
+    let Ok(copy_directories) = fs::read_dir(directory) else {
+        return;
+    };
+
+    for copy_directory in copy_directories.flatten().map(|entry| entry.path()).filter(|path| is_copy_directory(path)) {
+        let Ok(copies) = fs::read_dir(&copy_directory) else {
+            continue;
+        };
+
+        for copy_path in copies.flatten().map(|entry| entry.path()) {
+            if !is_dll_file(&copy_path) || !is_stale(&copy_path, max_age) {
+                continue;
+            }
+
+            let copy_path_string: String = copy_path.to_string_lossy().into_owned();
+            match winapi::processes_with_module(&copy_path_string) {
+                Ok(processes) if processes.is_empty() => {},
+                Ok(processes) => {
+                    debug!("{}", format!("keeping stale copy {}, loaded by {} processes", copy_path.display(), processes.len()));
+                    continue;
+                },
+                Err(e) => {
+                    warn!("{}", format!("failed to check whether {} is loaded, keeping it: {}", copy_path.display(), e.message()));
+                    continue;
+                },
+            }
+
+            match fs::remove_file(&copy_path) {
+                Ok(()) => info!("{}", format!("deleted stale copy {}", copy_path.display())),
+                Err(e) => warn!("{}", format!("failed to delete stale copy {}: {}", copy_path.display(), e)),
+            }
+        }
+
+        // fails if the directory still holds copies.
+        let _ = fs::remove_dir(&copy_directory);
+    }
+}
+
+
+/// Returns whether a path is a directory named by `stage`, after the start of the digest of a DLL.
+fn is_copy_directory(path: &Path) -> bool {
+    path.is_dir() && path.file_name().and_then(|name| name.to_str()).is_some_and(is_digest_prefix)
+}
+
+
+/// Returns whether a name is the start of a SHA-256 digest, as formatted by `stage`.
+fn is_digest_prefix(name: &str) -> bool {
+    name.len() == DIGEST_PREFIX_LENGTH && name.bytes().all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+
+/// Returns whether a path is a file with the `.dll` extension (case-insensitive).
+fn is_dll_file(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("dll"))
+}
+
+
+/// Returns whether a file was created more than `max_age` ago.
+///
+/// The creation time is used rather than the last write time, since copying a file preserves
+/// its last write time.
+fn is_stale(path: &Path, max_age: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.created())
+        .ok()
+        .and_then(|created| SystemTime::now().duration_since(created).ok())
+        .is_some_and(|age| age > max_age)
+}
@@ -4,71 +4,568 @@
 
 
 use core::ffi::c_void;
-use std::collections::HashMap;
-use std::mem::transmute;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::RandomState;
+use std::ffi::CString;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::mem::{transmute, ManuallyDrop};
+use std::os::windows::io::FromRawHandle;
+use std::path::Path;
 use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use log::{debug, info, warn};
+use regex::{Regex, RegexBuilder};
 
 use windows::core::{
+    BOOL,
     Error,
     HRESULT,
     HSTRING, 
+    PCSTR,
+    PWSTR,
     Result,
 };
+use windows::Wdk::System::Threading::{
+    NtQueryInformationThread,
+    ThreadQuerySetWin32StartAddress,
+};
 use windows::Win32::Foundation::{
     CloseHandle,
+    ERROR_ACCESS_DENIED,
+    ERROR_ALREADY_EXISTS,
+    ERROR_BAD_EXE_FORMAT,
+    ERROR_DYNAMIC_CODE_BLOCKED,
+    ERROR_EXE_MACHINE_TYPE_MISMATCH,
+    ERROR_INSUFFICIENT_BUFFER,
+    ERROR_INVALID_IMAGE_HASH,
+    ERROR_INVALID_PARAMETER,
+    ERROR_MOD_NOT_FOUND,
+    ERROR_NONE_MAPPED,
+    ERROR_NOT_ALL_ASSIGNED,
+    ERROR_NOT_FOUND,
+    ERROR_NOT_SUPPORTED,
+    ERROR_OPERATION_ABORTED,
+    ERROR_PARTIAL_COPY,
+    ERROR_PIPE_CONNECTED,
+    ERROR_PROC_NOT_FOUND,
+    ERROR_TIMEOUT,
     FARPROC,
+    FILETIME,
+    FreeLibrary,
+    GetLastError,
     HANDLE,
+    HINSTANCE,
+    HLOCAL,
     HMODULE,
+    HWND,
+    INVALID_HANDLE_VALUE,
+    LocalFree,
+    LPARAM,
+    LUID,
     MAX_PATH,
+    NTSTATUS,
+    PAPCFUNC,
+    SYSTEMTIME,
+    WAIT_EVENT,
+    WAIT_FAILED,
+    WAIT_OBJECT_0,
+    WAIT_TIMEOUT,
+    WPARAM,
+};
+use windows::Win32::Security::{
+    AdjustTokenPrivileges,
+    GetSidSubAuthority,
+    GetSidSubAuthorityCount,
+    GetTokenInformation,
+    LookupAccountSidW,
+    LookupPrivilegeValueW,
+    LUID_AND_ATTRIBUTES,
+    PSECURITY_DESCRIPTOR,
+    PSID,
+    SECURITY_ATTRIBUTES,
+    SE_DEBUG_NAME,
+    SE_PRIVILEGE_ENABLED,
+    SID_NAME_USE,
+    TOKEN_ADJUST_PRIVILEGES,
+    TOKEN_ELEVATION,
+    TOKEN_INFORMATION_CLASS,
+    TOKEN_MANDATORY_LABEL,
+    TOKEN_PRIVILEGES,
+    TOKEN_QUERY,
+    TOKEN_USER,
+    TokenElevation,
+    TokenIntegrityLevel,
+    TokenUser,
+};
+use windows::Win32::Security::Authorization::{
+    ConvertSidToStringSidW,
+    ConvertStringSecurityDescriptorToSecurityDescriptorW,
+    SDDL_REVISION_1,
+};
+use windows::Win32::Security::Cryptography::{
+    BCryptHash,
+    BCRYPT_SHA256_ALG_HANDLE,
+};
+use windows::Win32::System::Diagnostics::Debug::{
+    CONTEXT,
+    CONTEXT_CONTROL_AMD64,
+    CONTEXT_FULL_AMD64,
+    FlushInstructionCache,
+    GetThreadContext,
+    ReadProcessMemory,
+    SetThreadContext,
+    WriteProcessMemory,
+};
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot,
+    PROCESSENTRY32W,
+    Process32FirstW,
+    Process32NextW,
+    TH32CS_SNAPPROCESS,
+    TH32CS_SNAPTHREAD,
+    THREADENTRY32,
+    Thread32First,
+    Thread32Next,
 };
-use windows::Win32::System::Diagnostics::Debug::WriteProcessMemory;
 use windows::Win32::System::LibraryLoader::{
+    DONT_RESOLVE_DLL_REFERENCES,
     GetModuleHandleA,
     GetProcAddress,
+    LOAD_IGNORE_CODE_AUTHZ_LEVEL,
+    LOAD_LIBRARY_AS_DATAFILE,
+    LOAD_LIBRARY_AS_DATAFILE_EXCLUSIVE,
+    LOAD_LIBRARY_AS_IMAGE_RESOURCE,
+    LOAD_LIBRARY_FLAGS,
+    LOAD_LIBRARY_REQUIRE_SIGNED_TARGET,
+    LOAD_LIBRARY_SAFE_CURRENT_DIRS,
+    LOAD_LIBRARY_SEARCH_APPLICATION_DIR,
+    LOAD_LIBRARY_SEARCH_DEFAULT_DIRS,
+    LOAD_LIBRARY_SEARCH_DLL_LOAD_DIR,
+    LOAD_LIBRARY_SEARCH_SYSTEM32,
+    LOAD_LIBRARY_SEARCH_USER_DIRS,
+    LOAD_WITH_ALTERED_SEARCH_PATH,
+    LoadLibraryExW,
 };
 use windows::Win32::System::Memory::{
+    CreateFileMappingW,
+    FILE_MAP_ALL_ACCESS,
+    MapViewOfFile,
     MEM_COMMIT,
+    MEM_FREE,
+    MEM_IMAGE,
+    MEM_MAPPED,
+    MEM_PRIVATE,
     MEM_RELEASE,
     MEM_RESERVE,
+    MEMORY_BASIC_INFORMATION,
+    MEMORY_MAPPED_VIEW_ADDRESS,
+    PAGE_EXECUTE,
+    PAGE_EXECUTE_READ,
+    PAGE_EXECUTE_READWRITE,
+    PAGE_EXECUTE_WRITECOPY,
+    PAGE_GUARD,
+    PAGE_NOACCESS,
+    PAGE_NOCACHE,
+    PAGE_PROTECTION_FLAGS,
+    PAGE_READONLY,
     PAGE_READWRITE,
+    PAGE_TYPE,
+    PAGE_WRITECOMBINE,
+    PAGE_WRITECOPY,
+    UnmapViewOfFile,
+    VIRTUAL_ALLOCATION_TYPE,
     VirtualAllocEx,
     VirtualFreeEx,
+    VirtualProtectEx,
+    VirtualQueryEx,
+};
+use windows::Win32::Storage::FileSystem::{
+    FILE_FLAG_FIRST_PIPE_INSTANCE,
+    PIPE_ACCESS_DUPLEX,
+};
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe,
+    CreateNamedPipeW,
+    PIPE_READMODE_BYTE,
+    PIPE_REJECT_REMOTE_CLIENTS,
+    PIPE_TYPE_BYTE,
+    PIPE_WAIT,
 };
 use windows::Win32::System::ProcessStatus::{
     EnumProcessModules,
+    EnumProcessModulesEx,
     EnumProcesses,
     GetModuleBaseNameW,
+    GetModuleFileNameExW,
+    GetModuleInformation,
+    GetProcessMemoryInfo,
+    ENUM_PROCESS_MODULES_EX_FLAGS,
+    LIST_MODULES_32BIT,
+    LIST_MODULES_ALL,
+    MODULEINFO,
+    PROCESS_MEMORY_COUNTERS,
+};
+use windows::Win32::System::RemoteDesktop::ProcessIdToSessionId;
+use windows::Win32::System::SystemInformation::{
+    GetNativeSystemInfo,
+    GetSystemInfo,
+    IMAGE_FILE_MACHINE,
+    IMAGE_FILE_MACHINE_AMD64,
+    IMAGE_FILE_MACHINE_ARM64,
+    IMAGE_FILE_MACHINE_ARMNT,
+    IMAGE_FILE_MACHINE_I386,
+    IMAGE_FILE_MACHINE_UNKNOWN,
+    PROCESSOR_ARCHITECTURE_AMD64,
+    PROCESSOR_ARCHITECTURE_ARM,
+    PROCESSOR_ARCHITECTURE_ARM64,
+    PROCESSOR_ARCHITECTURE_INTEL,
+    SYSTEM_INFO,
 };
 use windows::Win32::System::Threading::{
+    CREATE_SUSPENDED,
+    CreateEventW,
+    CreateProcessW,
     CreateRemoteThread,
+    GetCurrentProcess,
+    GetCurrentProcessId,
+    GetExitCodeProcess,
+    GetExitCodeThread,
+    GetProcessHandleCount,
+    GetProcessInformation,
+    GetProcessMitigationPolicy,
+    GetProcessTimes,
+    GetThreadId,
+    INFINITE,
+    IsProcessCritical,
+    IsWow64Process,
+    LPTHREAD_START_ROUTINE,
     OpenProcess,
+    OpenProcessToken,
+    OpenThread,
+    PROCESS_ACCESS_RIGHTS,
     PROCESS_ALL_ACCESS,
+    PROCESS_INFORMATION,
+    PROCESS_MITIGATION_POLICY,
+    PROCESS_NAME_WIN32,
+    PROCESS_PROTECTION_LEVEL,
+    PROCESS_PROTECTION_LEVEL_INFORMATION,
+    PROCESS_QUERY_INFORMATION,
+    PROCESS_QUERY_LIMITED_INFORMATION,
+    PROCESS_SYNCHRONIZE,
+    PROCESS_VM_READ,
+    ProcessDynamicCodePolicy,
+    ProcessProtectionLevelInfo,
+    ProcessSignaturePolicy,
+    PROTECTION_LEVEL_ANTIMALWARE_LIGHT,
+    PROTECTION_LEVEL_AUTHENTICODE,
+    PROTECTION_LEVEL_CODEGEN_LIGHT,
+    PROTECTION_LEVEL_LSA_LIGHT,
+    PROTECTION_LEVEL_NONE,
+    PROTECTION_LEVEL_PPL_APP,
+    PROTECTION_LEVEL_WINDOWS,
+    PROTECTION_LEVEL_WINDOWS_LIGHT,
+    PROTECTION_LEVEL_WINTCB,
+    PROTECTION_LEVEL_WINTCB_LIGHT,
+    QueryFullProcessImageNameW,
+    QueueUserAPC,
+    ResetEvent,
+    ResumeThread,
+    STACK_SIZE_PARAM_IS_A_RESERVATION,
+    STARTUPINFOW,
+    SuspendThread,
+    TerminateProcess,
+    THREAD_ALL_ACCESS,
+    THREAD_CREATION_FLAGS,
+    THREAD_GET_CONTEXT,
+    THREAD_QUERY_INFORMATION,
+    THREAD_SET_CONTEXT,
+    THREAD_SUSPEND_RESUME,
+    TerminateThread,
+    WaitForSingleObject,
+};
+use windows::Win32::System::Time::{
+    FileTimeToSystemTime,
+    SystemTimeToTzSpecificLocalTime,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows,
+    GetWindowLongW,
+    GetWindowTextW,
+    GetWindowThreadProcessId,
+    GWL_EXSTYLE,
+    HHOOK,
+    HOOKPROC,
+    IsWindowVisible,
+    PostThreadMessageW,
+    SetWindowsHookExW,
+    UnhookWindowsHookEx,
+    WH_GETMESSAGE,
+    WM_NULL,
+    WS_EX_TOOLWINDOW,
 };
 use windows_strings::s;
 
+use crate::pe;
+
+
+/// Processor architecture of a process or of a PE image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Arch {
+    /// 32-bit x86.
+    X86,
+    /// 64-bit x86.
+    X64,
+    /// 32-bit ARM.
+    Arm,
+    /// 64-bit ARM.
+    Arm64,
+}
+
+impl Arch {
+    /// Converts a PE machine type to an architecture.
+    fn from_machine(machine: IMAGE_FILE_MACHINE) -> Result<Arch> {
+        match machine {
+            IMAGE_FILE_MACHINE_I386 => Ok(Arch::X86),
+            IMAGE_FILE_MACHINE_AMD64 => Ok(Arch::X64),
+            IMAGE_FILE_MACHINE_ARMNT => Ok(Arch::Arm),
+            IMAGE_FILE_MACHINE_ARM64 => Ok(Arch::Arm64),
+            _ => Err(Error::new(HRESULT(-1), format!("unsupported machine type {:#x}", machine.0))),
+        }
+    }
+
+    /// Returns the architecture dll-spider is built for.
+    /// 
+    /// The architecture of the build is used rather than the one of the current process, which
+    /// is reported as the native one for x64 builds emulated on ARM64.
+    pub fn current() -> Arch {
+        if cfg!(target_arch = "x86_64") {
+            Arch::X64
+        } else if cfg!(target_arch = "aarch64") {
+            Arch::Arm64
+        } else if cfg!(target_arch = "arm") {
+            Arch::Arm
+        } else {
+            Arch::X86
+        }
+    }
+}
+
+impl fmt::Display for Arch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Arch::X86 => "x86",
+            Arch::X64 => "x64",
+            Arch::Arm => "arm",
+            Arch::Arm64 => "arm64",
+        })
+    }
+}
+
 
-/// Returns the base address of the `LoadLibraryW` WinAPI function.
-fn get_load_library_w_handle() -> Result<FARPROC> {
-    let h_kernel32: HMODULE = unsafe {
+/// Returns the handle, i.e. the base address, of a module loaded by the current process.
+fn get_module_handle(module_name: PCSTR) -> Result<HMODULE> {
+    unsafe {
         // https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-getmodulehandlea.
         // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/LibraryLoader/fn.GetModuleHandleA.html.
         // https://microsoft.github.io/windows-rs/features/#/latest/search/GetModuleHandleA.
-        GetModuleHandleA(s!("kernel32.dll"))
-    }?;
+        GetModuleHandleA(module_name)
+    }
+}
+
+/// Returns the base address of a function exported by a module loaded by the current process.
+fn get_proc_address(module_name: PCSTR, proc_name: PCSTR) -> Result<FARPROC> {
+    let h_module: HMODULE = get_module_handle(module_name)?;
 
     let p_address: FARPROC = unsafe {
         // https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-getprocaddress.
         // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/LibraryLoader/fn.GetProcAddress.html.
         // https://microsoft.github.io/windows-rs/features/#/latest/search/GetProcAddress.
-        GetProcAddress(h_kernel32, s!("LoadLibraryW"))
+        GetProcAddress(h_module, proc_name)
     };
+    if p_address.is_none() {
+        return Err(Error::from_win32());
+    }
+
     Ok(p_address)
 }
 
+/// Returns the base address of a function exported by `kernel32.dll`.
+fn get_kernel32_proc_address(proc_name: PCSTR) -> Result<FARPROC> {
+    get_proc_address(s!("kernel32.dll"), proc_name)
+}
+
+/// Returns the address of a function exported by `kernel32.dll` in a target process.
+/// 
+/// The address is derived from the `kernel32.dll` of the target. In 32-bit (WOW64) processes,
+/// it is read from the export table of their 32-bit `kernel32.dll`. Otherwise, the offset of
+/// the function in the `kernel32.dll` of this process is added to the base address of the one
+/// of the target, which is the same in every process of the same architecture today. The
+/// address in this process is only used as is if the loader of the target has not listed its
+/// modules yet, e.g. in a process created suspended, which fails with `ERROR_PARTIAL_COPY`.
+fn get_remote_kernel32_proc_address(target: &ProcessHandle, proc_name: &str) -> Result<usize> {
+    if is_wow64_target(get_process_architecture(target.pid)?) {
+        return get_wow64_proc_address(target.handle, "kernel32.dll", proc_name);
+    }
+
+    let proc_name_c: CString = CString::new(proc_name)
+        .map_err(|_| Error::new(ERROR_INVALID_PARAMETER.to_hresult(), format!("invalid function name {:?}", proc_name)))?;
+    let local_address: usize = get_kernel32_proc_address(PCSTR(proc_name_c.as_ptr() as *const u8))?.map_or(0, |f| f as usize);
+    let local_base_address: usize = get_module_handle(s!("kernel32.dll"))?.0 as usize;
+
+    let modules: Vec<ModuleInfo> = match get_process_modules_by_handle(target.handle) {
+        Ok(modules) => modules,
+        Err(e) if e.code() == ERROR_PARTIAL_COPY.to_hresult() => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    if modules.is_empty() {
+        debug!("{}", format!("using the local address of {} for process ({}), whose modules are not listed yet", proc_name, target.pid));
+        return Ok(local_address);
+    }
+
+    let module: ModuleInfo = modules
+        .into_iter()
+        .find(|module| module_matches(module, "kernel32.dll"))
+        .ok_or_else(|| Error::new(HRESULT(-1), "module kernel32.dll is not loaded in the target process"))?;
+    if module.base_address != local_base_address {
+        debug!("{}", format!("kernel32.dll is loaded at {:#x} in process ({}), and at {:#x} here", module.base_address, target.pid, local_base_address));
+    }
+
+    Ok(module.base_address + (local_address - local_base_address))
+}
+
+/// Returns the address of `LoadLibraryW` in a target process, as the start routine of a
+/// remote thread.
+fn get_remote_load_library_w(target: &ProcessHandle) -> Result<LPTHREAD_START_ROUTINE> {
+    let address: usize = get_remote_kernel32_proc_address(target, "LoadLibraryW")?;
+
+    Ok(unsafe { transmute::<usize, LPTHREAD_START_ROUTINE>(address) })
+}
+
+/// Opens a handle to a process with the given access rights.
+fn open_process(pid: u32, access: PROCESS_ACCESS_RIGHTS) -> Result<HANDLE> {
+    let h_process: HANDLE = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-openprocess.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.OpenProcess.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/OpenProcess.
+        OpenProcess(
+            access,
+            false,
+            pid,
+        )
+    }
+    // OpenProcess fails with ERROR_INVALID_PARAMETER if no process has the PID.
+    .map_err(|e| if e.code() == ERROR_INVALID_PARAMETER.to_hresult() {
+        Error::new(ERROR_NOT_FOUND.to_hresult(), format!("process ({}) not found", pid))
+    } else if e.code() == ERROR_ACCESS_DENIED.to_hresult() && access == PROCESS_ALL_ACCESS {
+        // protected processes only grant limited access rights, which is worth telling apart.
+        protected_process_error(pid).unwrap_or(e)
+    } else {
+        e
+    })?;
+    debug!("target process handle: {:?}", h_process);
+
+    Ok(h_process)
+}
+
+/// Opens a handle to a process, passes it to a closure, and closes it once the closure returns.
+fn with_process<T>(pid: u32, access: PROCESS_ACCESS_RIGHTS, f: impl FnOnce(HANDLE) -> Result<T>) -> Result<T> {
+    let h_process: HANDLE = open_process(pid, access)?;
+    let result: Result<T> = f(h_process);
+    close_handle(h_process)?;
+
+    result
+}
+
+/// Closes an open object handle.
+fn close_handle(handle: HANDLE) -> Result<()> {
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/handleapi/nf-handleapi-closehandle.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Foundation/fn.CloseHandle.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/CloseHandle.
+        CloseHandle(handle)
+    }
+}
+
+
+/// Enables the `SeDebugPrivilege` privilege in the access token of the current process.
+/// 
+/// The privilege allows opening handles to processes owned by other users (e.g. services),
+/// but it can only be acquired if the current process is running elevated.
+pub fn enable_debug_privilege() -> Result<()> {
+    let mut h_token: HANDLE = Default::default();
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-openprocesstoken.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.OpenProcessToken.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/OpenProcessToken.
+        OpenProcessToken(
+            GetCurrentProcess(),
+            TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY,
+            &mut h_token,
+        )
+    }?;
+
+    let mut luid: LUID = Default::default();
+    let result: Result<()> = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/winbase/nf-winbase-lookupprivilegevaluew.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Security/fn.LookupPrivilegeValueW.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/LookupPrivilegeValueW.
+        LookupPrivilegeValueW(
+            None,
+            SE_DEBUG_NAME,
+            &mut luid,
+        )
+    }.and_then(|_| {
+        let token_privileges = TOKEN_PRIVILEGES {
+            PrivilegeCount: 1,
+            Privileges: [LUID_AND_ATTRIBUTES {
+                Luid: luid,
+                Attributes: SE_PRIVILEGE_ENABLED,
+            }],
+        };
+
+        unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/securitybaseapi/nf-securitybaseapi-adjusttokenprivileges.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Security/fn.AdjustTokenPrivileges.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/AdjustTokenPrivileges.
+            AdjustTokenPrivileges(
+                h_token,
+                false,
+                Some(&token_privileges),
+                0,
+                None,
+                None,
+            )
+        }?;
+
+        // AdjustTokenPrivileges succeeds even if the privilege is not held by the token.
+        if unsafe { GetLastError() } == ERROR_NOT_ALL_ASSIGNED {
+            return Err(Error::new(
+                ERROR_NOT_ALL_ASSIGNED.to_hresult(),
+                "failed to acquire SeDebugPrivilege, the current process is not elevated",
+            ));
+        }
+
+        Ok(())
+    });
+
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/handleapi/nf-handleapi-closehandle.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Foundation/fn.CloseHandle.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/CloseHandle.
+        CloseHandle(h_token)?;
+    }
+
+    result
+}
+
+
 /// Returns a vector containg the PIDs of all running processes.
 fn get_process_ids() -> Result<Vec<u32>> {
     let mut vec_capacity: usize = 1024;
@@ -99,200 +596,5779 @@ fn get_process_ids() -> Result<Vec<u32>> {
 }
 
 
-/// Returns a hashmap that maps process names to their respective PIDs.
+/// Information about a module loaded by a process.
+#[derive(Clone, Debug)]
+pub struct ModuleInfo {
+    /// Base name of the module.
+    pub name: String,
+    /// Full path of the module.
+    pub path: String,
+    /// Address at which the module is loaded.
+    pub base_address: usize,
+    /// Size of the module image, in bytes.
+    pub size: u32,
+}
+
+impl ModuleInfo {
+    /// Returns whether an address falls inside the module image.
+    pub fn contains(&self, address: usize) -> bool {
+        (self.base_address..self.base_address + self.size as usize).contains(&address)
+    }
+}
+
+/// Returns the handles of the modules loaded by a process, filtered with a `LIST_MODULES_*`
+/// flag.
 /// 
-/// The hashmap does NOT contain all name/pid associations, but only the ones of processes
-/// to which a handle with `PROCESS_ALL_ACCESS` permissions can be opened.
-pub fn get_process_name_pid_mapping() -> Result<HashMap<String, u32>> {
-    let mut name_and_pid: HashMap<String, u32> = HashMap::new();
-    let process_ids = get_process_ids()?;
-
-    for pid in &process_ids {
-        let Ok(h_process) = (unsafe {
-            // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-openprocess.
-            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.OpenProcess.html.
-            // https://microsoft.github.io/windows-rs/features/#/latest/search/OpenProcess.
-            OpenProcess(
-                PROCESS_ALL_ACCESS,
-                false,
-                *pid,
-            )
-        }) else { continue };
-
-        let mut h_module: HMODULE = Default::default();
-        let mut dw_return_len: u32 = 0;
-            
-        if unsafe {
-            // https://learn.microsoft.com/en-us/windows/win32/api/psapi/nf-psapi-enumprocessmodules.
-            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/ProcessStatus/fn.EnumProcesses.html.
-            // https://microsoft.github.io/windows-rs/features/#/latest/search/EnumProcessModules.
-            EnumProcessModules(
-                h_process,
-                &mut h_module,
-                std::mem::size_of::<HMODULE>().try_into().unwrap(),
-                &mut dw_return_len,
-            )
-        }.is_err() {
-            continue;
-        }
+/// Both 32-bit and 64-bit modules are listed with `LIST_MODULES_ALL`.
+fn get_process_module_handles(h_process: HANDLE, filter: ENUM_PROCESS_MODULES_EX_FLAGS) -> Result<Vec<HMODULE>> {
+    let mut vec_capacity: usize = 256;
+    let mut module_handles: Vec<HMODULE> = Vec::with_capacity(vec_capacity);
+
+    let mut cb_needed: u32 = 0;
+    for _ in 0..3 {
+        module_handles.resize(vec_capacity, Default::default());
 
-        let mut module_base_name_w: [u16; MAX_PATH as usize] = [0; MAX_PATH as usize];
-        if unsafe {
-            // https://learn.microsoft.com/en-us/windows/win32/api/psapi/nf-psapi-getmodulebasenamew.
-            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/ProcessStatus/fn.GetModuleBaseNameW.html.
-            // https://microsoft.github.io/windows-rs/features/#/latest/search/GetModuleBaseNameW.
-            GetModuleBaseNameW(
+        unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/psapi/nf-psapi-enumprocessmodulesex.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/ProcessStatus/fn.EnumProcessModulesEx.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/EnumProcessModulesEx.
+            EnumProcessModulesEx(
                 h_process,
-                Some(h_module),
-                &mut module_base_name_w,
+                module_handles.as_mut_ptr(),
+                (module_handles.len() * size_of::<HMODULE>()).try_into()?,
+                &mut cb_needed,
+                filter,
             )
-        } == 0 {
-            continue
-        };
+        }?;
 
-        let module_base_name_h: HSTRING = HSTRING::from_wide(&module_base_name_w);
-        name_and_pid.insert(module_base_name_h.to_string().trim_matches(char::from(0)).to_owned(), *pid);
+        let modules_count: usize = cb_needed as usize / size_of::<HMODULE>();
+        if modules_count <= module_handles.len() {
+            module_handles.truncate(modules_count);
+            return Ok(module_handles);
+        }
+
+        debug!("buffer passed to EnumProcessModulesEx is too small ({})", vec_capacity);
+        vec_capacity = modules_count;
     }
 
-    Ok(name_and_pid)
+    Err(Error::new(HRESULT(-1), "Maximum amount of reallocations reached"))
 }
 
 
-/// Returns the PID of a process given its name.
-pub fn find_process_by_name(name: &str, case_insensitive: Option<bool>) -> Result<u32> {
-    let case_insensitive: bool = case_insensitive.unwrap_or(false);
+/// Maximum length of a path, in UTF-16 code units, including the terminating null character.
+const MAX_LONG_PATH: usize = 32768;
 
-    for (key, value) in &get_process_name_pid_mapping()? {
-        if name == key {
-            return Ok(*value);
+
+/// Reads a string with a function that copies it to a buffer and returns its length, such as
+/// `GetModuleFileNameExW`, growing the buffer until the string fits.
+/// 
+/// These functions truncate the string to the buffer instead of failing, so a string that
+/// fills the buffer is read again with a larger one.
+fn read_wide_string(mut read: impl FnMut(&mut [u16]) -> u32) -> Result<String> {
+    let mut buffer_w: Vec<u16> = vec![0; MAX_PATH as usize];
+
+    loop {
+        let len: usize = read(&mut buffer_w) as usize;
+        if len == 0 {
+            return Err(Error::from_win32());
         }
-        if case_insensitive && name.to_uppercase() == key.to_uppercase() {
-            warn!("{}", format!("partial match found {} <- {}", key, name));
-            return Ok(*value);
+        if len + 1 < buffer_w.len() || buffer_w.len() >= MAX_LONG_PATH {
+            return Ok(String::from_utf16_lossy(&buffer_w[..len]));
         }
+
+        debug!("buffer of {} characters is too small for the string", buffer_w.len());
+        buffer_w.resize(buffer_w.len() * 2, 0);
     }
-    
-    Err(Error::new(HRESULT(-1), format!("process {:#} not found", name)))
 }
 
 
-/// Loads a DLL into a target process.
-pub fn load_dll(pid: u32, dll_path: &str) -> Result<()> {
-    let dll_path_w: HSTRING = HSTRING::from(dll_path);
-    let dw_size_to_write: usize = dll_path_w.len() * 2 + 1; // 2 bytes per character + \0.
-
-    // https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-loadlibraryw.
-    let p_load_library_w: *mut c_void = unsafe {
-        transmute(get_load_library_w_handle()?)
-    };
-    debug!("LoadLibraryW address: {:#x}", p_load_library_w as isize);
-
-    let h_process: HANDLE = unsafe {
-        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-openprocess.
-        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.OpenProcess.html.
-        // https://microsoft.github.io/windows-rs/features/#/latest/search/OpenProcess.
-        OpenProcess(
-            PROCESS_ALL_ACCESS,
-            false,
-            pid,
-        )?
-    };
-    debug!("target process handle: {:?}", h_process);
-
-    let p_address: *mut c_void = unsafe {
-        // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-virtualallocex.
-        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Memory/fn.VirtualAllocEx.html.
-        // https://microsoft.github.io/windows-rs/features/#/latest/search/VirtualAllocEx.
-        VirtualAllocEx(
+/// Returns information about a module loaded by a process.
+fn get_module_info(h_process: HANDLE, h_module: HMODULE) -> Result<ModuleInfo> {
+    let name: String = read_wide_string(|module_base_name_w| unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/psapi/nf-psapi-getmodulebasenamew.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/ProcessStatus/fn.GetModuleBaseNameW.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetModuleBaseNameW.
+        GetModuleBaseNameW(
             h_process,
-            None,
-            dw_size_to_write,
-            MEM_COMMIT | MEM_RESERVE,
-            PAGE_READWRITE,
+            Some(h_module),
+            module_base_name_w,
         )
-    };
-    if p_address.is_null() {
-        return Err(Error::from_win32());
-    }
-    debug!("address of externally allocated memory: {:?}", p_address.clone());
+    })?;
 
-    let mut lp_number_of_bytes_written: usize = 0;
+    let path: String = read_wide_string(|module_file_name_w| unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/psapi/nf-psapi-getmodulefilenameexw.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/ProcessStatus/fn.GetModuleFileNameExW.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetModuleFileNameExW.
+        GetModuleFileNameExW(
+            Some(h_process),
+            Some(h_module),
+            module_file_name_w,
+        )
+    })?;
+
+    let mut module_info: MODULEINFO = Default::default();
     unsafe {
-        // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-writeprocessmemory.
-        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/Debug/fn.WriteProcessMemory.html.
-        // https://microsoft.github.io/windows-rs/features/#/latest/search/WriteProcessMemory.
-        WriteProcessMemory(
+        // https://learn.microsoft.com/en-us/windows/win32/api/psapi/nf-psapi-getmoduleinformation.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/ProcessStatus/fn.GetModuleInformation.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetModuleInformation.
+        GetModuleInformation(
             h_process,
-            p_address,
-            dll_path_w.as_ptr() as *const c_void,
-            dw_size_to_write,
-            Some(&mut lp_number_of_bytes_written),
+            h_module,
+            &mut module_info,
+            size_of::<MODULEINFO>().try_into()?,
         )
     }?;
 
-    if lp_number_of_bytes_written != dw_size_to_write {
-        return Err(Error::new(HRESULT(-1), "failed to write the DLL path in memory"));
+    Ok(ModuleInfo {
+        name,
+        path,
+        base_address: module_info.lpBaseOfDll as usize,
+        size: module_info.SizeOfImage,
+    })
+}
+
+
+/// Returns information about all the modules loaded by a process, given its handle.
+/// 
+/// Modules that are unloaded while they are being enumerated are skipped.
+fn get_process_modules_by_handle(h_process: HANDLE) -> Result<Vec<ModuleInfo>> {
+    Ok(
+        get_process_module_handles(h_process, LIST_MODULES_ALL)?
+            .into_iter()
+            .filter_map(|h_module| get_module_info(h_process, h_module).ok())
+            .collect()
+    )
+}
+
+
+/// Returns information about all the modules loaded by a process.
+pub fn get_process_modules(pid: u32) -> Result<Vec<ModuleInfo>> {
+    with_process(pid, PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, get_process_modules_by_handle)
+}
+
+
+/// Returns whether a module matches the given name.
+/// 
+/// The name is compared case-insensitively against the full path of the module if it
+/// contains a path separator, otherwise against the module base name. The `\\?\` prefix of
+/// long paths is ignored on both sides, since the module list may or may not keep it.
+fn module_matches(module: &ModuleInfo, module_name: &str) -> bool {
+    if !module_name.contains(['\\', '/']) {
+        return module.name.to_uppercase() == module_name.to_uppercase();
     }
 
-    let h_thread = unsafe {
-        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-createremotethread.
-        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.CreateRemoteThread.html.
-        // https://microsoft.github.io/windows-rs/features/#/latestsearch/CreateRemoteThread.
-        CreateRemoteThread(
-            h_process,
-            None, 
-            0,
-            Some(transmute(p_load_library_w)),
-            Some(p_address),
-            0,
-            None,
-        )
-    }?;
+    strip_long_path_prefix(&module.path).to_uppercase() == strip_long_path_prefix(&module_name.replace('/', "\\")).to_uppercase()
+}
 
-    if h_thread == HANDLE(ptr::null_mut()) {
-        return Err(Error::from_win32());
+
+/// Prefix of the paths that are passed to the file system without being parsed, which lifts
+/// the `MAX_PATH` limit.
+const LONG_PATH_PREFIX: &str = "\\\\?\\";
+
+/// Prefix of the UNC paths that are passed to the file system without being parsed.
+const LONG_UNC_PATH_PREFIX: &str = "\\\\?\\UNC\\";
+
+
+/// Returns a path with the `\\?\` prefix if it is too long for `MAX_PATH`, so that
+/// `LoadLibraryW` accepts it. UNC paths get the `\\?\UNC\` prefix.
+/// 
+/// The path must be absolute, since prefixed paths are not normalized.
+fn to_long_path(path: &str) -> String {
+    // MAX_PATH includes the terminating null character.
+    if path.encode_utf16().count() < MAX_PATH as usize || path.starts_with(LONG_PATH_PREFIX) {
+        return path.to_owned();
     }
-    info!("{}", format!("remote thread started in process ({}): {:?}", pid, h_thread));
 
-    thread::sleep(Duration::from_millis(400));
+    match path.strip_prefix("\\\\") {
+        Some(unc_path) => format!("{}{}", LONG_UNC_PATH_PREFIX, unc_path),
+        None => format!("{}{}", LONG_PATH_PREFIX, path),
+    }
+}
+
 
-    unsafe {
-        // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-writeprocessmemory.
-        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/Debug/fn.WriteProcessMemory.html.
-        // https://microsoft.github.io/windows-rs/features/#/latest/search/WriteProcessMemory.
-        WriteProcessMemory(
-            h_process,
-            p_address,
-            vec![0; dw_size_to_write].as_ptr() as *const c_void,
-            dw_size_to_write,
-            Some(&mut lp_number_of_bytes_written),
-        )
-    }?;
+/// Returns a path without its `\\?\` or `\\?\UNC\` prefix, if any.
+fn strip_long_path_prefix(path: &str) -> Cow<'_, str> {
+    if let Some(unc_path) = path.strip_prefix(LONG_UNC_PATH_PREFIX) {
+        return Cow::Owned(format!("\\\\{}", unc_path));
+    }
 
-    if lp_number_of_bytes_written != dw_size_to_write {
-        return Err(Error::new(HRESULT(-1), "failed to zero out the allocated memory"));
+    Cow::Borrowed(path.strip_prefix(LONG_PATH_PREFIX).unwrap_or(path))
+}
+
+
+/// Returns the module loaded by a process that matches the given name.
+/// 
+/// A module that is not loaded is reported with `ERROR_MOD_NOT_FOUND`, to tell it apart from
+/// a failure to enumerate the modules.
+fn find_remote_module(h_process: HANDLE, module_name: &str) -> Result<ModuleInfo> {
+    get_process_modules_by_handle(h_process)?
+        .into_iter()
+        .find(|module| module_matches(module, module_name))
+        .ok_or_else(|| Error::new(ERROR_MOD_NOT_FOUND.to_hresult(), format!("module {:#} is not loaded in the target process", module_name)))
+}
+
+
+/// Returns the address of a function exported by a 32-bit module loaded by a WOW64 process.
+/// 
+/// The module is looked up among the 32-bit modules of the process, which have the same names
+/// as their 64-bit counterparts (e.g. `kernel32.dll`), and the function is looked up in its
+/// export table, read from the memory of the process.
+fn get_wow64_proc_address(h_process: HANDLE, module_name: &str, proc_name: &str) -> Result<usize> {
+    let module: ModuleInfo = get_process_module_handles(h_process, LIST_MODULES_32BIT)?
+        .into_iter()
+        .filter_map(|h_module| get_module_info(h_process, h_module).ok())
+        .find(|module| module_matches(module, module_name))
+        .ok_or_else(|| Error::new(HRESULT(-1), format!("32-bit module {:#} is not loaded in the target process", module_name)))?;
+
+    let (_, headers) = read_remote_headers(h_process, module.base_address)?;
+    let exports: Vec<pe::Export> = pe::parse_exports(&headers, |rva, size| {
+        read_process_memory_by_handle(h_process, module.base_address + rva as usize, size).map(|(data, _)| data)
+    })?;
+
+    match exports.iter().find(|export| export.name == proc_name) {
+        Some(export) if !export.is_forwarded => Ok(module.base_address + export.rva as usize),
+        Some(_) => Err(Error::new(ERROR_NOT_SUPPORTED.to_hresult(), format!("{}!{} is forwarded to another module", module.name, proc_name))),
+        None => Err(Error::new(ERROR_PROC_NOT_FOUND.to_hresult(), format!("{} does not export {}", module.name, proc_name))),
     }
+}
 
-    unsafe {
-        // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-virtualfreeex.
-        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Memory/fn.VirtualFreeEx.html.
-        // https://microsoft.github.io/windows-rs/features/#/latest/search/VirtualFreeEx.
-        VirtualFreeEx(
-            h_process,
-            p_address,
-            0,
-            MEM_RELEASE,
-        )
-    }?;
-    debug!("releasing the allocated memory");
 
+/// Returns the processes that have loaded a module matching the given name.
+/// 
+/// Each entry contains the PID of the process, its name, and the matching module.
+/// Processes that cannot be opened are skipped.
+pub fn processes_with_module(module_name: &str) -> Result<Vec<(u32, String, ModuleInfo)>> {
+    let mut matches: Vec<(u32, String, ModuleInfo)> = Vec::new();
+
+    for pid in get_process_ids()? {
+        let modules: Vec<ModuleInfo> = match get_process_modules(pid) {
+            Ok(modules) => modules,
+            Err(e) => {
+                debug!("{}", format!("skipping process ({}): {}", pid, e.message()));
+                continue;
+            },
+        };
+
+        // the first module of a process is its executable.
+        let Some(process_name) = modules.first().map(|module| module.name.clone()) else { continue };
+
+        for module in modules {
+            if module_matches(&module, module_name) {
+                matches.push((pid, process_name.clone(), module));
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+
+/// Signature of `IsWow64Process2`, which is resolved at runtime since it is only available
+/// starting from Windows 10 1511.
+type IsWow64Process2Fn = unsafe extern "system" fn(HANDLE, *mut IMAGE_FILE_MACHINE, *mut IMAGE_FILE_MACHINE) -> BOOL;
+
+
+/// Returns the architecture of the operating system.
+fn get_native_architecture() -> Result<Arch> {
+    let mut system_info: SYSTEM_INFO = Default::default();
     unsafe {
-        // https://learn.microsoft.com/en-us/windows/win32/api/handleapi/nf-handleapi-closehandle.
-        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Foundation/fn.CloseHandle.html.
-        // https://microsoft.github.io/windows-rs/features/#/latest/search/CloseHandle.
-        CloseHandle(h_thread)?;
-        CloseHandle(h_process)?;
+        // https://learn.microsoft.com/en-us/windows/win32/api/sysinfoapi/nf-sysinfoapi-getnativesysteminfo.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/SystemInformation/fn.GetNativeSystemInfo.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetNativeSystemInfo.
+        GetNativeSystemInfo(&mut system_info);
     }
 
-    Ok(())
-}
\ No newline at end of file
+    match unsafe { system_info.Anonymous.Anonymous.wProcessorArchitecture } {
+        PROCESSOR_ARCHITECTURE_INTEL => Ok(Arch::X86),
+        PROCESSOR_ARCHITECTURE_AMD64 => Ok(Arch::X64),
+        PROCESSOR_ARCHITECTURE_ARM => Ok(Arch::Arm),
+        PROCESSOR_ARCHITECTURE_ARM64 => Ok(Arch::Arm64),
+        architecture => Err(Error::new(HRESULT(-1), format!("unsupported processor architecture {}", architecture.0))),
+    }
+}
+
+
+/// Returns the architecture of a process.
+/// 
+/// Falls back to `IsWow64Process` on systems where `IsWow64Process2` is not available, in
+/// which case WOW64 processes are assumed to be x86 processes.
+pub fn get_process_architecture(pid: u32) -> Result<Arch> {
+    let is_wow64_process2: Option<IsWow64Process2Fn> = get_kernel32_proc_address(s!("IsWow64Process2"))
+        .ok()
+        .and_then(|p_is_wow64_process2| unsafe { transmute::<FARPROC, Option<IsWow64Process2Fn>>(p_is_wow64_process2) });
+
+    let Some(is_wow64_process2) = is_wow64_process2 else {
+        debug!("IsWow64Process2 is not available, falling back to IsWow64Process");
+
+        let mut is_wow64: BOOL = Default::default();
+        with_process(pid, PROCESS_QUERY_LIMITED_INFORMATION, |h_process| unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/wow64apiset/nf-wow64apiset-iswow64process.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.IsWow64Process.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/IsWow64Process.
+            IsWow64Process(h_process, &mut is_wow64)
+        })?;
+
+        return if is_wow64.as_bool() { Ok(Arch::X86) } else { get_native_architecture() };
+    };
+
+    let mut process_machine: IMAGE_FILE_MACHINE = Default::default();
+    let mut native_machine: IMAGE_FILE_MACHINE = Default::default();
+    with_process(pid, PROCESS_QUERY_LIMITED_INFORMATION, |h_process| unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/wow64apiset/nf-wow64apiset-iswow64process2.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.IsWow64Process2.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/IsWow64Process2.
+        is_wow64_process2(
+            h_process,
+            &mut process_machine,
+            &mut native_machine,
+        ).ok()
+    })?;
+
+    // the process machine is unknown if the process is not running under WOW64.
+    Arch::from_machine(
+        if process_machine == IMAGE_FILE_MACHINE_UNKNOWN { native_machine } else { process_machine }
+    )
+}
+
+
+/// Protection level of a process.
+/// 
+/// Protected processes can only be opened with limited access rights from user mode, even by
+/// elevated processes with `SeDebugPrivilege`, so DLLs cannot be loaded into them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProtectionLevel {
+    /// Not a protected process.
+    #[default]
+    None,
+    /// Protected process (PP), with the name of its signer (e.g. `WinTcb`).
+    Protected(&'static str),
+    /// Protected process light (PPL), with the name of its signer (e.g. `Antimalware`).
+    Light(&'static str),
+}
+
+impl ProtectionLevel {
+    /// Returns the protection level matching a `PROTECTION_LEVEL_*` value.
+    fn from_level(level: PROCESS_PROTECTION_LEVEL) -> ProtectionLevel {
+        match level {
+            PROTECTION_LEVEL_NONE => ProtectionLevel::None,
+            PROTECTION_LEVEL_WINTCB_LIGHT => ProtectionLevel::Light("WinTcb"),
+            PROTECTION_LEVEL_WINDOWS => ProtectionLevel::Protected("Windows"),
+            PROTECTION_LEVEL_WINDOWS_LIGHT => ProtectionLevel::Light("Windows"),
+            PROTECTION_LEVEL_ANTIMALWARE_LIGHT => ProtectionLevel::Light("Antimalware"),
+            PROTECTION_LEVEL_LSA_LIGHT => ProtectionLevel::Light("Lsa"),
+            PROTECTION_LEVEL_WINTCB => ProtectionLevel::Protected("WinTcb"),
+            PROTECTION_LEVEL_CODEGEN_LIGHT => ProtectionLevel::Light("CodeGen"),
+            PROTECTION_LEVEL_AUTHENTICODE => ProtectionLevel::Protected("Authenticode"),
+            PROTECTION_LEVEL_PPL_APP => ProtectionLevel::Light("App"),
+            _ => ProtectionLevel::Protected("unknown"),
+        }
+    }
+
+    /// Returns whether the process is a protected process (PP or PPL).
+    pub fn is_protected(self) -> bool {
+        self != ProtectionLevel::None
+    }
+}
+
+impl fmt::Display for ProtectionLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtectionLevel::None => f.write_str("none"),
+            ProtectionLevel::Protected(signer) => write!(f, "PP, {}", signer),
+            ProtectionLevel::Light(signer) => write!(f, "PPL, {}", signer),
+        }
+    }
+}
+
+
+/// Protection and mitigation policies of a process that can prevent DLL injection.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProcessProtection {
+    /// Protection level of the process.
+    pub level: ProtectionLevel,
+    /// Whether the arbitrary code guard (ACG) policy prohibits dynamic code.
+    pub prohibits_dynamic_code: bool,
+    /// Whether the process only loads images signed by Microsoft.
+    pub microsoft_signed_only: bool,
+}
+
+
+/// Returns the value of a mitigation policy of a process.
+/// 
+/// The `PROCESS_MITIGATION_*_POLICY` structures queried here hold a single bit field, whose
+/// first bit is the flag enabling the policy.
+fn get_process_mitigation_flags(h_process: HANDLE, policy: PROCESS_MITIGATION_POLICY) -> Result<u32> {
+    let mut flags: u32 = 0;
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getprocessmitigationpolicy.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.GetProcessMitigationPolicy.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetProcessMitigationPolicy.
+        GetProcessMitigationPolicy(
+            h_process,
+            policy,
+            &mut flags as *mut u32 as *mut c_void,
+            size_of::<u32>(),
+        )
+    }?;
+
+    Ok(flags)
+}
+
+
+/// Returns the protection level of a process from a handle opened with at least
+/// `PROCESS_QUERY_LIMITED_INFORMATION` access, which protected processes grant.
+fn query_protection_level(h_process: HANDLE) -> Result<ProtectionLevel> {
+    let mut protection_level: PROCESS_PROTECTION_LEVEL_INFORMATION = Default::default();
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getprocessinformation.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.GetProcessInformation.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetProcessInformation.
+        GetProcessInformation(
+            h_process,
+            ProcessProtectionLevelInfo,
+            &mut protection_level as *mut PROCESS_PROTECTION_LEVEL_INFORMATION as *mut c_void,
+            size_of::<PROCESS_PROTECTION_LEVEL_INFORMATION>() as u32,
+        )
+    }?;
+
+    Ok(ProtectionLevel::from_level(protection_level.ProtectionLevel))
+}
+
+
+/// Returns the protection level of a process.
+pub fn get_process_protection_level(pid: u32) -> Result<ProtectionLevel> {
+    with_process(pid, PROCESS_QUERY_LIMITED_INFORMATION, query_protection_level)
+}
+
+
+/// Returns the error explaining why a protected process cannot be opened with the access
+/// rights needed to load DLLs, or `None` if the process is not protected.
+fn protected_process_error(pid: u32) -> Option<Error> {
+    let level: ProtectionLevel = get_process_protection_level(pid).ok().filter(|level| level.is_protected())?;
+    let target: String = match get_process_name(pid) {
+        Ok(name) => format!("PID {} ({})", pid, name),
+        Err(_) => format!("PID {}", pid),
+    };
+
+    Some(Error::new(
+        ERROR_ACCESS_DENIED.to_hresult(),
+        format!("{} is a protected process ({}), injection is not possible from user mode", target, level),
+    ))
+}
+
+
+/// Returns the protection level and the mitigation policies of a process.
+pub fn get_process_protection(pid: u32) -> Result<ProcessProtection> {
+    with_process(pid, PROCESS_QUERY_LIMITED_INFORMATION, |h_process| {
+        Ok(ProcessProtection {
+            level: query_protection_level(h_process)?,
+            prohibits_dynamic_code: get_process_mitigation_flags(h_process, ProcessDynamicCodePolicy)? & 1 != 0,
+            microsoft_signed_only: get_process_mitigation_flags(h_process, ProcessSignaturePolicy)? & 1 != 0,
+        })
+    })
+}
+
+
+/// Checks that the mitigation policies of a target process let an injector load DLLs into it.
+/// 
+/// A process that only loads images signed by Microsoft (`ProcessSignaturePolicy`) rejects
+/// the DLLs whatever the technique, and a process with the arbitrary code guard policy
+/// (`ProcessDynamicCodePolicy`) rejects the techniques that write executable code to it. The
+/// DLLs loaded into a process with ACG by the other techniques cannot generate code, which
+/// is only logged. The policies that cannot be queried are not checked.
+pub fn check_mitigation_policies(pid: u32, injector: &dyn Injector) -> Result<()> {
+    let protection: ProcessProtection = match get_process_protection(pid) {
+        Ok(protection) => protection,
+        Err(e) => {
+            debug!("{}", format!("failed to query the mitigation policies of process ({}): {}", pid, e.message()));
+            return Ok(());
+        },
+    };
+
+    if protection.microsoft_signed_only {
+        return Err(Error::new(
+            ERROR_INVALID_IMAGE_HASH.to_hresult(),
+            format!("process ({}) only loads images signed by Microsoft (ProcessSignaturePolicy), use --ignore-mitigations to try anyway", pid),
+        ));
+    }
+    if protection.prohibits_dynamic_code {
+        if injector.writes_executable_code() {
+            return Err(Error::new(
+                ERROR_DYNAMIC_CODE_BLOCKED.to_hresult(),
+                format!(
+                    "process ({}) prohibits dynamic code (ProcessDynamicCodePolicy), which {} writes, use --ignore-mitigations to try anyway",
+                    pid, injector.name(),
+                ),
+            ));
+        }
+        warn!("{}", format!("process ({}) prohibits dynamic code (ProcessDynamicCodePolicy), the DLLs will not be able to generate code", pid));
+    }
+
+    Ok(())
+}
+
+
+/// Checks that a process can be opened with the access rights needed to inject a DLL.
+pub fn check_injection_access(pid: u32) -> Result<()> {
+    with_process(pid, PROCESS_ALL_ACCESS, |_| Ok(()))
+}
+
+
+/// Names of the system processes that crash or deadlock the system when they misbehave.
+const CRITICAL_PROCESS_NAMES: [&str; 6] = [
+    "csrss.exe",
+    "lsass.exe",
+    "services.exe",
+    "smss.exe",
+    "wininit.exe",
+    "winlogon.exe",
+];
+
+/// PIDs of the System Idle process and of the System process.
+const SYSTEM_PIDS: [u32; 2] = [0, 4];
+
+
+/// Returns whether a process is one of the critical system processes, given its PID and name.
+fn is_critical_system_process(pid: u32, name: &str) -> bool {
+    SYSTEM_PIDS.contains(&pid) || CRITICAL_PROCESS_NAMES.iter().any(|critical_name| critical_name.eq_ignore_ascii_case(name))
+}
+
+
+/// Checks that a process is not critical to the system, i.e. one of the well-known critical
+/// system processes or a process marked as critical (which bugchecks the system on exit).
+pub fn check_not_critical_process(pid: u32) -> Result<()> {
+    let name: String = get_process_name(pid).unwrap_or_default();
+    if is_critical_system_process(pid, &name) {
+        return Err(Error::new(HRESULT(-1), format!(
+            "process ({}) {} is a critical system process, breaking it can crash the system (use --force to inject anyway)",
+            pid, name,
+        )));
+    }
+
+    let mut is_critical: BOOL = BOOL(0);
+    let result: Result<()> = with_process(pid, PROCESS_QUERY_LIMITED_INFORMATION, |h_process| unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-isprocesscritical.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.IsProcessCritical.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/IsProcessCritical.
+        IsProcessCritical(h_process, &mut is_critical)
+    });
+    if let Err(e) = result {
+        debug!("{}", format!("failed to check whether process ({}) is critical: {}", pid, e.message()));
+    }
+
+    if is_critical.as_bool() {
+        return Err(Error::new(HRESULT(-1), format!(
+            "process ({}) {} is marked as critical, the system crashes if it exits (use --force to inject anyway)",
+            pid, name,
+        )));
+    }
+
+    Ok(())
+}
+
+
+/// Reads a PE file and parses its headers with a function of the `pe` module.
+/// 
+/// Errors name the file.
+fn read_pe_file_headers(path: &Path, parse: fn(&[u8]) -> Result<pe::PeHeaders>) -> Result<pe::PeHeaders> {
+    let file: Vec<u8> = fs::read(path)
+        .map_err(|e| Error::new(ERROR_BAD_EXE_FORMAT.to_hresult(), format!("file {:#} is unreadable: {}", path.display(), e)))?;
+
+    parse(&file).map_err(|e| Error::new(ERROR_BAD_EXE_FORMAT.to_hresult(), format!("{:#}: {}", path.display(), e.message())))
+}
+
+
+/// Checks that a file is a valid PE image of a DLL, for a machine that can load it.
+pub fn validate_pe(path: &Path) -> Result<()> {
+    read_pe_file_headers(path, pe::parse_dll_headers)?;
+
+    Ok(())
+}
+
+
+/// Returns the architecture of a DLL, reading the machine type from its PE header.
+pub fn get_dll_architecture(path: &Path) -> Result<Arch> {
+    let headers: pe::PeHeaders = read_pe_file_headers(path, pe::parse_headers)?;
+
+    Arch::from_machine(IMAGE_FILE_MACHINE(headers.machine))
+}
+
+
+/// Returns the SHA-256 digest of a file, as a lowercase hexadecimal string.
+pub fn get_file_sha256(path: &Path) -> Result<String> {
+    let content: Vec<u8> = fs::read(path)?;
+    let mut digest: [u8; 32] = [0; 32];
+
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/bcrypt/nf-bcrypt-bcrypthash.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Security/Cryptography/fn.BCryptHash.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/BCryptHash.
+        BCryptHash(
+            BCRYPT_SHA256_ALG_HANDLE,
+            None,
+            &content,
+            &mut digest,
+        )
+    }.ok()?;
+
+    Ok(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+
+/// Returns the full path of the executable image of a process.
+pub fn get_process_image_path(pid: u32) -> Result<String> {
+    with_process(pid, PROCESS_QUERY_LIMITED_INFORMATION, |h_process| {
+        let mut image_path_w: Vec<u16> = vec![0; MAX_PATH as usize];
+
+        loop {
+            let mut dw_size: u32 = image_path_w.len().try_into()?;
+            let result: Result<()> = unsafe {
+                // https://learn.microsoft.com/en-us/windows/win32/api/winbase/nf-winbase-queryfullprocessimagenamew.
+                // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.QueryFullProcessImageNameW.html.
+                // https://microsoft.github.io/windows-rs/features/#/latest/search/QueryFullProcessImageNameW.
+                QueryFullProcessImageNameW(
+                    h_process,
+                    PROCESS_NAME_WIN32,
+                    PWSTR(image_path_w.as_mut_ptr()),
+                    &mut dw_size,
+                )
+            };
+
+            match result {
+                Ok(()) => return Ok(String::from_utf16_lossy(&image_path_w[..dw_size as usize])),
+                // paths can be up to 32,767 characters long.
+                Err(e) if e.code() == ERROR_INSUFFICIENT_BUFFER.to_hresult() && image_path_w.len() < MAX_LONG_PATH => {
+                    debug!("buffer passed to QueryFullProcessImageNameW is too small ({})", image_path_w.len());
+                    image_path_w.resize(image_path_w.len() * 2, 0);
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    })
+}
+
+
+/// Returns a snapshot of the entries of all running processes.
+fn get_process_entries() -> Result<Vec<PROCESSENTRY32W>> {
+    let h_snapshot: HANDLE = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/tlhelp32/nf-tlhelp32-createtoolhelp32snapshot.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/ToolHelp/fn.CreateToolhelp32Snapshot.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/CreateToolhelp32Snapshot.
+        CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0)
+    }?;
+
+    let mut process_entries: Vec<PROCESSENTRY32W> = Vec::new();
+    let mut process_entry = PROCESSENTRY32W {
+        dwSize: size_of::<PROCESSENTRY32W>().try_into()?,
+        ..Default::default()
+    };
+
+    let mut result: Result<()> = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/tlhelp32/nf-tlhelp32-process32firstw.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/ToolHelp/fn.Process32FirstW.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/Process32FirstW.
+        Process32FirstW(h_snapshot, &mut process_entry)
+    };
+    while result.is_ok() {
+        process_entries.push(process_entry);
+
+        result = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/tlhelp32/nf-tlhelp32-process32nextw.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/ToolHelp/fn.Process32NextW.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/Process32NextW.
+            Process32NextW(h_snapshot, &mut process_entry)
+        };
+    }
+    close_handle(h_snapshot)?;
+
+    Ok(process_entries)
+}
+
+
+/// Returns the snapshot entry of a process.
+fn get_process_entry(pid: u32) -> Result<PROCESSENTRY32W> {
+    get_process_entries()?
+        .into_iter()
+        .find(|process_entry| process_entry.th32ProcessID == pid)
+        .ok_or_else(|| Error::new(ERROR_NOT_FOUND.to_hresult(), format!("process ({}) not found", pid)))
+}
+
+
+/// Returns the name of the executable of a process snapshot entry.
+fn get_process_entry_name(process_entry: &PROCESSENTRY32W) -> String {
+    let len: usize = process_entry.szExeFile.iter().position(|&c| c == 0).unwrap_or(process_entry.szExeFile.len());
+
+    String::from_utf16_lossy(&process_entry.szExeFile[..len])
+}
+
+
+/// Returns the name of the executable of a process.
+pub fn get_process_name(pid: u32) -> Result<String> {
+    Ok(get_process_entry_name(&get_process_entry(pid)?))
+}
+
+
+/// Running process and its parent.
+#[derive(Clone, Debug)]
+pub struct ProcessEntry {
+    /// PID of the process.
+    pub pid: u32,
+    /// PID of the process that created the process, which may have exited since.
+    pub parent_pid: u32,
+    /// Name of the executable of the process.
+    pub name: String,
+    /// Creation time of the process, if it can be queried.
+    pub start_time: Option<SystemTime>,
+}
+
+
+/// Returns a snapshot of all running processes along with their parents.
+pub fn get_process_entries_with_parents() -> Result<Vec<ProcessEntry>> {
+    Ok(get_process_entries()?
+        .iter()
+        .map(|process_entry| ProcessEntry {
+            pid: process_entry.th32ProcessID,
+            parent_pid: process_entry.th32ParentProcessID,
+            name: get_process_entry_name(process_entry),
+            start_time: get_process_start_time(process_entry.th32ProcessID).ok(),
+        })
+        .collect())
+}
+
+
+/// Returns the PID of the parent of a process.
+pub fn get_parent_process_id(pid: u32) -> Result<u32> {
+    Ok(get_process_entry(pid)?.th32ParentProcessID)
+}
+
+
+/// Returns the number of threads started by a process.
+pub fn get_process_thread_count(pid: u32) -> Result<u32> {
+    Ok(get_process_entry(pid)?.cntThreads)
+}
+
+
+/// Information about a thread of a process.
+#[derive(Clone, Debug)]
+pub struct ThreadInfo {
+    /// ID of the thread.
+    pub tid: u32,
+    /// Base priority of the thread.
+    pub base_priority: i32,
+    /// Address of the start routine of the thread, if it can be queried.
+    pub start_address: Option<usize>,
+}
+
+/// Returns the address of the start routine of a thread.
+fn get_thread_start_address(tid: u32) -> Result<usize> {
+    let h_thread: HANDLE = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-openthread.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.OpenThread.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/OpenThread.
+        OpenThread(THREAD_QUERY_INFORMATION, false, tid)
+    }?;
+
+    let mut start_address: usize = 0;
+    let result: Result<()> = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/winternl/nf-winternl-ntqueryinformationthread.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Wdk/System/Threading/fn.NtQueryInformationThread.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/NtQueryInformationThread.
+        NtQueryInformationThread(
+            h_thread,
+            ThreadQuerySetWin32StartAddress,
+            &mut start_address as *mut usize as *mut c_void,
+            size_of::<usize>().try_into()?,
+            ptr::null_mut(),
+        )
+    }.ok();
+    close_handle(h_thread)?;
+    result?;
+
+    Ok(start_address)
+}
+
+/// Returns the snapshot entries of all the threads of a process.
+fn get_thread_entries(pid: u32) -> Result<Vec<THREADENTRY32>> {
+    let h_snapshot: HANDLE = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/tlhelp32/nf-tlhelp32-createtoolhelp32snapshot.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/ToolHelp/fn.CreateToolhelp32Snapshot.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/CreateToolhelp32Snapshot.
+        CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0)
+    }?;
+
+    let mut thread_entries: Vec<THREADENTRY32> = Vec::new();
+    let mut thread_entry = THREADENTRY32 {
+        dwSize: size_of::<THREADENTRY32>().try_into()?,
+        ..Default::default()
+    };
+
+    let mut result: Result<()> = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/tlhelp32/nf-tlhelp32-thread32first.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/ToolHelp/fn.Thread32First.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/Thread32First.
+        Thread32First(h_snapshot, &mut thread_entry)
+    };
+    while result.is_ok() {
+        // the snapshot contains the threads of every process in the system.
+        if thread_entry.th32OwnerProcessID == pid {
+            thread_entries.push(thread_entry);
+        }
+
+        result = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/tlhelp32/nf-tlhelp32-thread32next.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/ToolHelp/fn.Thread32Next.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/Thread32Next.
+            Thread32Next(h_snapshot, &mut thread_entry)
+        };
+    }
+    close_handle(h_snapshot)?;
+
+    Ok(thread_entries)
+}
+
+/// Returns information about all the threads of a process.
+pub fn get_process_threads(pid: u32) -> Result<Vec<ThreadInfo>> {
+    Ok(
+        get_thread_entries(pid)?
+            .into_iter()
+            .map(|thread_entry| ThreadInfo {
+                tid: thread_entry.th32ThreadID,
+                base_priority: thread_entry.tpBasePri,
+                start_address: get_thread_start_address(thread_entry.th32ThreadID).ok(),
+            })
+            .collect()
+    )
+}
+
+
+/// PID of the System process.
+const SYSTEM_PID: u32 = 4;
+
+/// Returns an error if a process must never be suspended.
+fn check_suspendable(pid: u32) -> Result<()> {
+    if pid == 0 || pid == SYSTEM_PID || pid == unsafe { GetCurrentProcessId() } {
+        return Err(Error::new(HRESULT(-1), format!("refusing to suspend process ({})", pid)));
+    }
+
+    Ok(())
+}
+
+/// Calls `SuspendThread` or `ResumeThread` on a thread, returning whether the call succeeded.
+fn suspend_or_resume_thread(tid: u32, suspend: bool) -> bool {
+    let Ok(h_thread) = (unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-openthread.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.OpenThread.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/OpenThread.
+        OpenThread(THREAD_SUSPEND_RESUME, false, tid)
+    }) else {
+        // the thread exited after the snapshot was taken.
+        return false;
+    };
+
+    let previous_suspend_count: u32 = unsafe {
+        if suspend {
+            // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-suspendthread.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.SuspendThread.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/SuspendThread.
+            SuspendThread(h_thread)
+        } else {
+            // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-resumethread.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.ResumeThread.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/ResumeThread.
+            ResumeThread(h_thread)
+        }
+    };
+    let _ = close_handle(h_thread);
+
+    previous_suspend_count != u32::MAX
+}
+
+/// Suspends all the threads of a process, returning the number of suspended threads.
+/// 
+/// Threads created while the process is being suspended are suspended as well.
+pub fn suspend_process(pid: u32) -> Result<usize> {
+    check_suspendable(pid)?;
+
+    let mut suspended_tids: HashSet<u32> = HashSet::new();
+    loop {
+        let mut suspended_new_threads: bool = false;
+
+        for thread_entry in get_thread_entries(pid)? {
+            if suspended_tids.contains(&thread_entry.th32ThreadID) {
+                continue;
+            }
+
+            if suspend_or_resume_thread(thread_entry.th32ThreadID, true) {
+                debug!("suspended thread ({})", thread_entry.th32ThreadID);
+                suspended_tids.insert(thread_entry.th32ThreadID);
+                suspended_new_threads = true;
+            }
+        }
+
+        // no new threads can be started once every thread is suspended.
+        if !suspended_new_threads {
+            return Ok(suspended_tids.len());
+        }
+    }
+}
+
+/// Resumes all the threads of a process, returning the number of resumed threads.
+pub fn resume_process(pid: u32) -> Result<usize> {
+    check_suspendable(pid)?;
+
+    Ok(
+        get_thread_entries(pid)?
+            .into_iter()
+            .filter(|thread_entry| suspend_or_resume_thread(thread_entry.th32ThreadID, false))
+            .count()
+    )
+}
+
+
+/// A region of the virtual address space of a process.
+#[derive(Clone, Debug)]
+pub struct MemoryRegion {
+    /// Base address of the region.
+    pub base_address: usize,
+    /// Base address of the allocation the region belongs to.
+    pub allocation_base: usize,
+    /// Size of the region, in bytes.
+    pub size: usize,
+    /// State of the pages in the region.
+    pub state: VIRTUAL_ALLOCATION_TYPE,
+    /// Access protection of the pages in the region.
+    pub protection: PAGE_PROTECTION_FLAGS,
+    /// Type of the pages in the region.
+    pub region_type: PAGE_TYPE,
+}
+
+impl MemoryRegion {
+    /// Returns whether the pages in the region are committed.
+    pub fn is_committed(&self) -> bool {
+        self.state == MEM_COMMIT
+    }
+
+    /// Returns whether the pages in the region can be read.
+    pub fn is_readable(&self) -> bool {
+        const READABLE: PAGE_PROTECTION_FLAGS = PAGE_PROTECTION_FLAGS(
+            PAGE_READONLY.0
+                | PAGE_READWRITE.0
+                | PAGE_WRITECOPY.0
+                | PAGE_EXECUTE_READ.0
+                | PAGE_EXECUTE_READWRITE.0
+                | PAGE_EXECUTE_WRITECOPY.0
+        );
+
+        self.is_committed() && self.protection.0 & READABLE.0 != 0 && self.protection.0 & PAGE_GUARD.0 == 0
+    }
+
+    /// Returns whether the pages in the region are mapped into the view of an image section.
+    pub fn is_image(&self) -> bool {
+        self.region_type == MEM_IMAGE
+    }
+
+    /// Returns the name of the state of the region (`commit`, `reserve`, or `free`).
+    pub fn state_name(&self) -> &'static str {
+        match self.state {
+            MEM_COMMIT => "commit",
+            MEM_RESERVE => "reserve",
+            MEM_FREE => "free",
+            _ => "?",
+        }
+    }
+
+    /// Returns the name of the type of the region (`image`, `mapped`, or `private`).
+    pub fn type_name(&self) -> &'static str {
+        match self.region_type {
+            MEM_IMAGE => "image",
+            MEM_MAPPED => "mapped",
+            MEM_PRIVATE => "private",
+            _ => "",
+        }
+    }
+
+    /// Returns the access protection of the region, formatted as a list of flags.
+    pub fn protection_name(&self) -> String {
+        const PROTECTIONS: [(PAGE_PROTECTION_FLAGS, &str); 8] = [
+            (PAGE_NOACCESS, "noaccess"),
+            (PAGE_READONLY, "readonly"),
+            (PAGE_READWRITE, "readwrite"),
+            (PAGE_WRITECOPY, "writecopy"),
+            (PAGE_EXECUTE, "execute"),
+            (PAGE_EXECUTE_READ, "execute_read"),
+            (PAGE_EXECUTE_READWRITE, "execute_readwrite"),
+            (PAGE_EXECUTE_WRITECOPY, "execute_writecopy"),
+        ];
+        const MODIFIERS: [(PAGE_PROTECTION_FLAGS, &str); 3] = [
+            (PAGE_GUARD, "guard"),
+            (PAGE_NOCACHE, "nocache"),
+            (PAGE_WRITECOMBINE, "writecombine"),
+        ];
+
+        PROTECTIONS
+            .iter()
+            .chain(MODIFIERS.iter())
+            .filter(|(flag, _)| self.protection.0 & flag.0 != 0)
+            .map(|(_, name)| *name)
+            .collect::<Vec<&str>>()
+            .join("+")
+    }
+}
+
+/// Returns the memory region that contains an address in the virtual address space of a process.
+/// 
+/// Returns `None` past the end of the address space of the process.
+fn query_memory_region(h_process: HANDLE, address: usize) -> Option<MemoryRegion> {
+    let mut memory_info: MEMORY_BASIC_INFORMATION = Default::default();
+    let dw_length: usize = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-virtualqueryex.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Memory/fn.VirtualQueryEx.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/VirtualQueryEx.
+        VirtualQueryEx(
+            h_process,
+            Some(address as *const c_void),
+            &mut memory_info,
+            size_of::<MEMORY_BASIC_INFORMATION>(),
+        )
+    };
+    if dw_length == 0 {
+        return None;
+    }
+
+    Some(MemoryRegion {
+        base_address: memory_info.BaseAddress as usize,
+        allocation_base: memory_info.AllocationBase as usize,
+        size: memory_info.RegionSize,
+        state: memory_info.State,
+        protection: memory_info.Protect,
+        region_type: memory_info.Type,
+    })
+}
+
+/// Iterator over the regions of the virtual address space of a process.
+/// 
+/// The regions are returned in ascending address order, up to the highest address
+/// accessible to user-mode applications.
+pub struct MemoryRegions {
+    h_process: HANDLE,
+    address: usize,
+    maximum_address: usize,
+}
+
+impl Iterator for MemoryRegions {
+    type Item = MemoryRegion;
+
+    fn next(&mut self) -> Option<MemoryRegion> {
+        if self.address > self.maximum_address {
+            return None;
+        }
+
+        let region: MemoryRegion = query_memory_region(self.h_process, self.address)?;
+        self.address = match region.base_address.checked_add(region.size) {
+            Some(address) if address > self.address => address,
+            _ => usize::MAX,
+        };
+
+        Some(region)
+    }
+}
+
+impl Drop for MemoryRegions {
+    fn drop(&mut self) {
+        let _ = close_handle(self.h_process);
+    }
+}
+
+/// Returns an iterator over the regions of the virtual address space of a process.
+pub fn memory_regions(pid: u32) -> Result<MemoryRegions> {
+    let mut system_info: SYSTEM_INFO = Default::default();
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/sysinfoapi/nf-sysinfoapi-getsysteminfo.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/SystemInformation/fn.GetSystemInfo.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetSystemInfo.
+        GetSystemInfo(&mut system_info);
+    }
+
+    Ok(MemoryRegions {
+        h_process: open_process(pid, PROCESS_QUERY_INFORMATION)?,
+        address: 0,
+        maximum_address: system_info.lpMaximumApplicationAddress as usize,
+    })
+}
+
+
+/// Reads a range of the virtual address space of a process, given its handle.
+/// 
+/// Returns the bytes that were read and how many of them were actually readable.
+fn read_process_memory_by_handle(h_process: HANDLE, address: usize, size: usize) -> Result<(Vec<u8>, usize)> {
+    let mut buffer: Vec<u8> = vec![0; size];
+    let mut readable_bytes: usize = 0;
+
+    let end_address: usize = address.checked_add(size)
+        .ok_or_else(|| Error::new(HRESULT(-1), "the range exceeds the address space"))?;
+    let mut cursor: usize = address;
+    while cursor < end_address {
+        // unreadable bytes past the end of the address space are left zeroed.
+        let Some(region) = query_memory_region(h_process, cursor) else { break };
+        let chunk_end: usize = region.base_address.saturating_add(region.size).min(end_address);
+
+        if region.is_readable() {
+            let offset: usize = cursor - address;
+            let mut lp_number_of_bytes_read: usize = 0;
+            let result: Result<()> = unsafe {
+                // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-readprocessmemory.
+                // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/Debug/fn.ReadProcessMemory.html.
+                // https://microsoft.github.io/windows-rs/features/#/latest/search/ReadProcessMemory.
+                ReadProcessMemory(
+                    h_process,
+                    cursor as *const c_void,
+                    buffer[offset..].as_mut_ptr() as *mut c_void,
+                    chunk_end - cursor,
+                    Some(&mut lp_number_of_bytes_read),
+                )
+            };
+            if let Err(e) = result {
+                debug!("{}", format!("partial read at {:#x}: {}", cursor, e.message()));
+            }
+
+            readable_bytes += lp_number_of_bytes_read;
+        } else {
+            debug!("{}", format!("skipping unreadable region at {:#x}", region.base_address));
+        }
+
+        cursor = chunk_end;
+    }
+
+    Ok((buffer, readable_bytes))
+}
+
+
+/// Reads a range of the virtual address space of a process.
+/// 
+/// Bytes that cannot be read (e.g. in `PAGE_NOACCESS` or `PAGE_GUARD` pages) are filled
+/// with zeros. Returns the bytes that were read and how many of them were actually readable.
+pub fn read_process_memory(pid: u32, address: usize, size: usize) -> Result<(Vec<u8>, usize)> {
+    with_process(pid, PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, |h_process| {
+        read_process_memory_by_handle(h_process, address, size)
+    })
+}
+
+
+/// Reads and parses the headers of a module loaded in the memory of a process.
+/// 
+/// Returns the raw headers along with their parsed form.
+fn read_remote_headers(h_process: HANDLE, base_address: usize) -> Result<(Vec<u8>, pe::PeHeaders)> {
+    // the headers of a module always fit in its first page.
+    let (headers_page, _) = read_process_memory_by_handle(h_process, base_address, 0x1000)?;
+    let size_of_headers: usize = pe::parse_headers(&headers_page)?.size_of_headers as usize;
+
+    let (headers, _) = read_process_memory_by_handle(h_process, base_address, size_of_headers)?;
+    let parsed_headers: pe::PeHeaders = pe::parse_headers(&headers)?;
+
+    Ok((headers, parsed_headers))
+}
+
+
+/// Reconstructs the PE image of a module loaded by a process.
+/// 
+/// Each section is copied from the memory of the process and laid out in the new image
+/// with file offsets recomputed from its in-memory layout. The image base is set to the
+/// address at which the module is loaded, since relocations have already been applied.
+/// Unreadable parts of the sections are filled with zeros.
+pub fn dump_module(pid: u32, module_name: &str) -> Result<Vec<u8>> {
+    with_process(pid, PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, |h_process| {
+        let module: ModuleInfo = find_remote_module(h_process, module_name)?;
+        debug!("remote module base address: {:#x}", module.base_address);
+
+        let (mut image, headers) = read_remote_headers(h_process, module.base_address)?;
+
+        for section in &headers.sections {
+            let size: u32 = pe::align_up(
+                section.virtual_size.max(section.size_of_raw_data),
+                headers.file_alignment,
+            );
+            let pointer_to_raw_data: u32 = pe::align_up(image.len().try_into()?, headers.file_alignment);
+
+            let (data, readable_bytes) = read_process_memory_by_handle(
+                h_process,
+                module.base_address + section.virtual_address as usize,
+                size as usize,
+            )?;
+            if readable_bytes < size as usize {
+                warn!("{}", format!("section {} is partially unreadable ({} of {} bytes)", section.name, readable_bytes, size));
+            }
+
+            image.resize(pointer_to_raw_data as usize, 0);
+            image.extend_from_slice(&data);
+
+            pe::write_u32(&mut image, section.header_offset + 16, size);
+            pe::write_u32(&mut image, section.header_offset + 20, pointer_to_raw_data);
+        }
+
+        if headers.is_64_bit {
+            pe::write_u64(&mut image, headers.image_base_offset(), module.base_address as u64);
+        } else {
+            pe::write_u32(&mut image, headers.image_base_offset(), module.base_address.try_into()?);
+        }
+
+        Ok(image)
+    })
+}
+
+
+/// Returns the ID of the Remote Desktop Services session a process belongs to.
+pub fn get_process_session_id(pid: u32) -> Result<u32> {
+    let mut dw_session_id: u32 = 0;
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-processidtosessionid.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/RemoteDesktop/fn.ProcessIdToSessionId.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/ProcessIdToSessionId.
+        ProcessIdToSessionId(pid, &mut dw_session_id)
+    }?;
+
+    Ok(dw_session_id)
+}
+
+
+/// ID of the session that hosts the services, isolated from interactive users since Windows Vista.
+pub const SERVICES_SESSION_ID: u32 = 0;
+
+
+/// Returns the ID of the Remote Desktop Services session the current process belongs to.
+pub fn get_current_session_id() -> Result<u32> {
+    get_process_session_id(unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getcurrentprocessid.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.GetCurrentProcessId.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetCurrentProcessId.
+        GetCurrentProcessId()
+    })
+}
+
+
+/// Number of 100-nanosecond intervals between the FILETIME epoch (1601-01-01) and the UNIX epoch.
+const FILETIME_UNIX_EPOCH_OFFSET: u64 = 116_444_736_000_000_000;
+
+/// Converts a FILETIME to a system time.
+fn filetime_to_system_time(filetime: FILETIME) -> SystemTime {
+    let intervals: u64 = ((filetime.dwHighDateTime as u64) << 32) | filetime.dwLowDateTime as u64;
+
+    if intervals >= FILETIME_UNIX_EPOCH_OFFSET {
+        UNIX_EPOCH + Duration::from_nanos((intervals - FILETIME_UNIX_EPOCH_OFFSET) * 100)
+    } else {
+        UNIX_EPOCH - Duration::from_nanos((FILETIME_UNIX_EPOCH_OFFSET - intervals) * 100)
+    }
+}
+
+/// Converts a system time to a FILETIME.
+fn system_time_to_filetime(time: SystemTime) -> FILETIME {
+    let intervals: u64 = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => FILETIME_UNIX_EPOCH_OFFSET + (duration.as_nanos() / 100) as u64,
+        Err(e) => FILETIME_UNIX_EPOCH_OFFSET.saturating_sub((e.duration().as_nanos() / 100) as u64),
+    };
+
+    FILETIME {
+        dwLowDateTime: intervals as u32,
+        dwHighDateTime: (intervals >> 32) as u32,
+    }
+}
+
+
+/// Returns the time at which a process was started.
+pub fn get_process_start_time(pid: u32) -> Result<SystemTime> {
+    let mut creation_time: FILETIME = Default::default();
+    let mut exit_time: FILETIME = Default::default();
+    let mut kernel_time: FILETIME = Default::default();
+    let mut user_time: FILETIME = Default::default();
+
+    with_process(pid, PROCESS_QUERY_LIMITED_INFORMATION, |h_process| unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getprocesstimes.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.GetProcessTimes.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetProcessTimes.
+        GetProcessTimes(
+            h_process,
+            &mut creation_time,
+            &mut exit_time,
+            &mut kernel_time,
+            &mut user_time,
+        )
+    })?;
+
+    Ok(filetime_to_system_time(creation_time))
+}
+
+
+/// Formats a system time as a local date and time (`YYYY-MM-DD HH:MM:SS`).
+pub fn format_local_time(time: SystemTime) -> Result<String> {
+    let mut universal_time: SYSTEMTIME = Default::default();
+    let mut local_time: SYSTEMTIME = Default::default();
+
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/timezoneapi/nf-timezoneapi-filetimetosystemtime.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Time/fn.FileTimeToSystemTime.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/FileTimeToSystemTime.
+        FileTimeToSystemTime(&system_time_to_filetime(time), &mut universal_time)?;
+
+        // https://learn.microsoft.com/en-us/windows/win32/api/timezoneapi/nf-timezoneapi-systemtimetotzspecificlocaltime.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Time/fn.SystemTimeToTzSpecificLocalTime.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/SystemTimeToTzSpecificLocalTime.
+        SystemTimeToTzSpecificLocalTime(None, &universal_time, &mut local_time)?;
+    }
+
+    Ok(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        local_time.wYear,
+        local_time.wMonth,
+        local_time.wDay,
+        local_time.wHour,
+        local_time.wMinute,
+        local_time.wSecond,
+    ))
+}
+
+
+/// Converts a SID to its string form (e.g. `S-1-5-18`).
+fn sid_to_string(sid: PSID) -> Result<String> {
+    let mut sid_w: PWSTR = PWSTR::null();
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/sddl/nf-sddl-convertsidtostringsidw.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Security/Authorization/fn.ConvertSidToStringSidW.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/ConvertSidToStringSidW.
+        ConvertSidToStringSidW(sid, &mut sid_w)
+    }?;
+
+    let sid_string: Result<String> = unsafe { sid_w.to_string() }
+        .map_err(|_| Error::new(HRESULT(-1), "SID string is not valid UTF-16"));
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/winbase/nf-winbase-localfree.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Foundation/fn.LocalFree.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/LocalFree.
+        LocalFree(Some(HLOCAL(sid_w.0 as *mut c_void)));
+    }
+
+    sid_string
+}
+
+
+/// Returns a class of information about an access token.
+/// 
+/// The information is returned in a u64 buffer, which guarantees the alignment required by
+/// the `TOKEN_*` structures.
+fn get_token_information(h_token: HANDLE, class: TOKEN_INFORMATION_CLASS) -> Result<Vec<u64>> {
+    let mut dw_return_len: u32 = 0;
+    // the first call fails, but returns the size of the buffer needed to hold the information.
+    let _ = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/securitybaseapi/nf-securitybaseapi-gettokeninformation.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Security/fn.GetTokenInformation.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetTokenInformation.
+        GetTokenInformation(h_token, class, None, 0, &mut dw_return_len)
+    };
+
+    let mut buffer: Vec<u64> = vec![0; (dw_return_len as usize).div_ceil(size_of::<u64>())];
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/securitybaseapi/nf-securitybaseapi-gettokeninformation.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Security/fn.GetTokenInformation.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetTokenInformation.
+        GetTokenInformation(
+            h_token,
+            class,
+            Some(buffer.as_mut_ptr() as *mut c_void),
+            dw_return_len,
+            &mut dw_return_len,
+        )
+    }?;
+
+    Ok(buffer)
+}
+
+
+/// Returns the account that owns an access token, formatted as `DOMAIN\user`.
+/// 
+/// If the SID of the account cannot be mapped to an account name, its string form is returned.
+fn get_token_user(h_token: HANDLE) -> Result<String> {
+    let token_user_buffer: Vec<u64> = get_token_information(h_token, TokenUser)?;
+    let token_user: &TOKEN_USER = unsafe { &*(token_user_buffer.as_ptr() as *const TOKEN_USER) };
+
+    let mut name_w: [u16; 256] = [0; 256];
+    let mut domain_w: [u16; 256] = [0; 256];
+    let mut cch_name: u32 = name_w.len().try_into()?;
+    let mut cch_domain: u32 = domain_w.len().try_into()?;
+    let mut sid_name_use: SID_NAME_USE = Default::default();
+    let result: Result<()> = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/winbase/nf-winbase-lookupaccountsidw.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Security/fn.LookupAccountSidW.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/LookupAccountSidW.
+        LookupAccountSidW(
+            None,
+            token_user.User.Sid,
+            Some(PWSTR(name_w.as_mut_ptr())),
+            &mut cch_name,
+            Some(PWSTR(domain_w.as_mut_ptr())),
+            &mut cch_domain,
+            &mut sid_name_use,
+        )
+    };
+
+    match result {
+        Ok(()) => {},
+        Err(e) if e.code() == ERROR_NONE_MAPPED.to_hresult() => return sid_to_string(token_user.User.Sid),
+        Err(e) => return Err(e),
+    }
+
+    Ok(format!(
+        "{}\\{}",
+        String::from_utf16_lossy(&domain_w[..cch_domain as usize]),
+        String::from_utf16_lossy(&name_w[..cch_name as usize]),
+    ))
+}
+
+
+/// Opens the access token of a process and runs a closure with its handle.
+/// 
+/// The token and process handles are closed once the closure returns, whether it succeeded
+/// or not.
+fn with_process_token<T>(pid: u32, f: impl FnOnce(HANDLE) -> Result<T>) -> Result<T> {
+    with_process(pid, PROCESS_QUERY_LIMITED_INFORMATION, |h_process| {
+        let mut h_token: HANDLE = Default::default();
+        unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-openprocesstoken.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.OpenProcessToken.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/OpenProcessToken.
+            OpenProcessToken(h_process, TOKEN_QUERY, &mut h_token)
+        }?;
+
+        let result: Result<T> = f(h_token);
+        close_handle(h_token)?;
+
+        result
+    })
+}
+
+
+/// Returns the account that owns a process, formatted as `DOMAIN\user`.
+/// 
+/// Accounts without a name are returned as SID strings.
+pub fn get_process_user(pid: u32) -> Result<String> {
+    with_process_token(pid, get_token_user)
+}
+
+
+/// Mandatory integrity level of a process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IntegrityLevel {
+    /// Untrusted, e.g. anonymous processes.
+    Untrusted,
+    /// Low, e.g. sandboxed browser processes.
+    Low,
+    /// Medium, the level of the processes of standard users.
+    Medium,
+    /// High, the level of elevated processes.
+    High,
+    /// System, the level of services.
+    System,
+    /// Protected process.
+    Protected,
+}
+
+impl IntegrityLevel {
+    /// Returns the integrity level matching the RID of a mandatory label SID.
+    fn from_rid(rid: u32) -> IntegrityLevel {
+        // SECURITY_MANDATORY_*_RID values.
+        match rid {
+            0..0x1000 => IntegrityLevel::Untrusted,
+            0x1000..0x2000 => IntegrityLevel::Low,
+            0x2000..0x3000 => IntegrityLevel::Medium,
+            0x3000..0x4000 => IntegrityLevel::High,
+            0x4000..0x5000 => IntegrityLevel::System,
+            _ => IntegrityLevel::Protected,
+        }
+    }
+}
+
+impl fmt::Display for IntegrityLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            IntegrityLevel::Untrusted => "untrusted",
+            IntegrityLevel::Low => "low",
+            IntegrityLevel::Medium => "medium",
+            IntegrityLevel::High => "high",
+            IntegrityLevel::System => "system",
+            IntegrityLevel::Protected => "protected",
+        })
+    }
+}
+
+
+/// Returns the mandatory integrity level of a process.
+pub fn get_process_integrity_level(pid: u32) -> Result<IntegrityLevel> {
+    with_process_token(pid, |h_token| {
+        let buffer: Vec<u64> = get_token_information(h_token, TokenIntegrityLevel)?;
+        let label: &TOKEN_MANDATORY_LABEL = unsafe { &*(buffer.as_ptr() as *const TOKEN_MANDATORY_LABEL) };
+
+        // the integrity level is the last subauthority of the label SID.
+        let rid: u32 = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/securitybaseapi/nf-securitybaseapi-getsidsubauthoritycount.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Security/fn.GetSidSubAuthorityCount.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/GetSidSubAuthorityCount.
+            let sub_authority_count: u8 = *GetSidSubAuthorityCount(label.Label.Sid);
+
+            // https://learn.microsoft.com/en-us/windows/win32/api/securitybaseapi/nf-securitybaseapi-getsidsubauthority.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Security/fn.GetSidSubAuthority.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/GetSidSubAuthority.
+            *GetSidSubAuthority(label.Label.Sid, u32::from(sub_authority_count).saturating_sub(1))
+        };
+
+        Ok(IntegrityLevel::from_rid(rid))
+    })
+}
+
+
+/// Returns whether a process runs with an elevated token.
+pub fn is_process_elevated(pid: u32) -> Result<bool> {
+    with_process_token(pid, |h_token| {
+        let buffer: Vec<u64> = get_token_information(h_token, TokenElevation)?;
+        let elevation: &TOKEN_ELEVATION = unsafe { &*(buffer.as_ptr() as *const TOKEN_ELEVATION) };
+
+        Ok(elevation.TokenIsElevated != 0)
+    })
+}
+
+
+/// Returns a hint about why a process could not be opened, if its elevation explains it.
+/// 
+/// Elevated processes cannot be opened with full access by non-elevated processes. Protected
+/// processes cannot be opened with full access at all, so they get no hint.
+pub fn elevation_hint(pid: u32, error: &Error) -> Option<&'static str> {
+    if error.code() != ERROR_ACCESS_DENIED.to_hresult() {
+        return None;
+    }
+    // running as administrator does not help with protected processes.
+    if get_process_protection_level(pid).is_ok_and(|level| level.is_protected()) {
+        return None;
+    }
+
+    let current_pid: u32 = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getcurrentprocessid.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.GetCurrentProcessId.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetCurrentProcessId.
+        GetCurrentProcessId()
+    };
+
+    match (is_process_elevated(pid), is_process_elevated(current_pid)) {
+        (Ok(true), Ok(false)) => Some("target is elevated, run dll-spider as administrator"),
+        _ => None,
+    }
+}
+
+
+/// Returns the number of handles opened by a process.
+pub fn get_process_handle_count(pid: u32) -> Result<u32> {
+    let mut dw_handle_count: u32 = 0;
+    with_process(pid, PROCESS_QUERY_LIMITED_INFORMATION, |h_process| unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getprocesshandlecount.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.GetProcessHandleCount.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetProcessHandleCount.
+        GetProcessHandleCount(h_process, &mut dw_handle_count)
+    })?;
+
+    Ok(dw_handle_count)
+}
+
+
+/// Returns the working set size of a process, in bytes.
+pub fn get_process_working_set_size(pid: u32) -> Result<usize> {
+    let mut memory_counters: PROCESS_MEMORY_COUNTERS = Default::default();
+    with_process(pid, PROCESS_QUERY_LIMITED_INFORMATION, |h_process| unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/psapi/nf-psapi-getprocessmemoryinfo.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/ProcessStatus/fn.GetProcessMemoryInfo.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetProcessMemoryInfo.
+        GetProcessMemoryInfo(
+            h_process,
+            &mut memory_counters,
+            size_of::<PROCESS_MEMORY_COUNTERS>().try_into()?,
+        )
+    })?;
+
+    Ok(memory_counters.WorkingSetSize)
+}
+
+
+/// Returns the name of the main module of a process, if a handle with `PROCESS_ALL_ACCESS`
+/// permissions can be opened to it.
+fn get_accessible_process_name(pid: u32) -> Result<Option<String>> {
+    let Ok(h_process) = (unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-openprocess.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.OpenProcess.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/OpenProcess.
+        OpenProcess(
+            PROCESS_ALL_ACCESS,
+            false,
+            pid,
+        )
+    }) else { return Ok(None) };
+
+    let mut h_module: HMODULE = Default::default();
+    let mut dw_return_len: u32 = 0;
+
+    let module_base_name: Option<String> = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/psapi/nf-psapi-enumprocessmodules.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/ProcessStatus/fn.EnumProcesses.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/EnumProcessModules.
+        EnumProcessModules(
+            h_process,
+            &mut h_module,
+            std::mem::size_of::<HMODULE>().try_into().unwrap(),
+            &mut dw_return_len,
+        )
+    }.ok().and_then(|_| read_wide_string(|module_base_name_w| unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/psapi/nf-psapi-getmodulebasenamew.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/ProcessStatus/fn.GetModuleBaseNameW.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetModuleBaseNameW.
+        GetModuleBaseNameW(
+            h_process,
+            Some(h_module),
+            module_base_name_w,
+        )
+    }).ok());
+    close_handle(h_process)?;
+
+    Ok(module_base_name)
+}
+
+
+/// Running process listed by `get_process_list`.
+#[derive(Clone, Debug)]
+pub struct ProcessListEntry {
+    /// PID of the process.
+    pub pid: u32,
+    /// Name of the process, or `<access denied>` if it cannot be queried.
+    pub name: String,
+    /// Whether a handle with `PROCESS_ALL_ACCESS` permissions can be opened to the process.
+    pub is_accessible: bool,
+}
+
+
+/// Returns all running processes, in ascending PID order.
+/// 
+/// Processes to which a handle with `PROCESS_ALL_ACCESS` permissions cannot be opened are
+/// still listed, named after their snapshot entry.
+pub fn get_process_list() -> Result<Vec<ProcessListEntry>> {
+    let mut process_ids: Vec<u32> = get_process_ids()?;
+    process_ids.sort_unstable();
+
+    // the snapshot is only taken if it is needed to name an inaccessible process.
+    let mut snapshot_names: Option<HashMap<u32, String>> = None;
+
+    let mut process_list: Vec<ProcessListEntry> = Vec::with_capacity(process_ids.len());
+    for pid in process_ids {
+        let entry: ProcessListEntry = match get_accessible_process_name(pid)? {
+            Some(name) => ProcessListEntry { pid, name, is_accessible: true },
+            None => {
+                let snapshot_names: &HashMap<u32, String> = match &mut snapshot_names {
+                    Some(snapshot_names) => snapshot_names,
+                    None => snapshot_names.insert(
+                        get_process_entries()?
+                            .iter()
+                            .map(|process_entry| (process_entry.th32ProcessID, get_process_entry_name(process_entry)))
+                            .collect()
+                    ),
+                };
+
+                ProcessListEntry {
+                    pid,
+                    name: snapshot_names.get(&pid).cloned().unwrap_or_else(|| "<access denied>".to_owned()),
+                    is_accessible: false,
+                }
+            },
+        };
+        process_list.push(entry);
+    }
+
+    Ok(process_list)
+}
+
+
+/// Returns a hashmap that maps process names to their respective PIDs.
+/// 
+/// Processes sharing the same name are mapped to all of their PIDs, in ascending order.
+/// The hashmap does NOT contain all name/pid associations, but only the ones of processes
+/// to which a handle with `PROCESS_ALL_ACCESS` permissions can be opened.
+pub fn get_process_name_pid_mapping() -> Result<HashMap<String, Vec<u32>>> {
+    let mut name_and_pid: HashMap<String, Vec<u32>> = HashMap::new();
+
+    for entry in get_process_list()?.into_iter().filter(|entry| entry.is_accessible) {
+        name_and_pid.entry(entry.name).or_default().push(entry.pid);
+    }
+
+    Ok(name_and_pid)
+}
+
+
+/// Returns whether a name contains glob metacharacters (`*` or `?`).
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+
+/// Returns whether a name matches a glob pattern, ignoring case unless `case_sensitive` is set.
+/// 
+/// `*` matches any sequence of characters, including an empty one, and `?` matches exactly
+/// one character. Every other character matches itself.
+fn glob_matches(pattern: &str, name: &str, case_sensitive: bool) -> bool {
+    let fold = |value: &str| if case_sensitive { value.to_owned() } else { value.to_lowercase() };
+    let pattern: Vec<char> = fold(pattern).chars().collect();
+    let name: Vec<char> = fold(name).chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // position of the last `*` in the pattern, and of the character of the name it was tried against.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            },
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            },
+            _ => match backtrack {
+                // let the last `*` match one more character.
+                Some((star, star_n)) => {
+                    backtrack = Some((star, star_n + 1));
+                    p = star + 1;
+                    n = star_n + 1;
+                },
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+
+/// Returns whether a process name matches a name given by the user.
+/// 
+/// The names are compared ignoring case unless `case_sensitive` is set, and the `.exe`
+/// extension of the process name can be omitted (e.g. `notepad` matches `Notepad.exe`).
+fn name_matches(name: &str, process_name: &str, case_sensitive: bool) -> bool {
+    let fold = |value: &str| if case_sensitive { value.to_owned() } else { value.to_lowercase() };
+    let name: String = fold(name);
+    let process_name: String = fold(process_name);
+
+    process_name == name || process_name.strip_suffix(".exe") == Some(name.as_str())
+}
+
+
+/// Pattern that selects processes by name.
+#[derive(Clone, Debug)]
+pub enum NamePattern {
+    /// Name compared with `name_matches`, preferring the processes with exactly this name, and
+    /// whether it is compared case-sensitively.
+    Name(String, bool),
+    /// Name compared strictly, including case and extension.
+    Exact(String),
+    /// Glob pattern containing `*` or `?` wildcards, and whether it is matched
+    /// case-sensitively.
+    Glob(String, bool),
+    /// Regex matched against the whole name, and its source.
+    Regex(Regex, String),
+}
+
+impl NamePattern {
+    /// Creates a pattern from a name, which is matched as a glob if it contains wildcards, and
+    /// ignoring case unless `case_sensitive` is set.
+    pub fn new(name: &str, case_sensitive: bool) -> Self {
+        if is_glob(name) {
+            NamePattern::Glob(name.to_owned(), case_sensitive)
+        } else {
+            NamePattern::Name(name.to_owned(), case_sensitive)
+        }
+    }
+
+    /// Creates a pattern from a regex, anchored to match whole names and case-insensitive
+    /// unless it disables the `i` flag (e.g. `(?-i)`).
+    pub fn regex(pattern: &str) -> Result<Self> {
+        let regex: Regex = RegexBuilder::new(&format!("^(?:{})$", pattern))
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| Error::new(HRESULT(-1), format!("invalid name regex {:?}: {}", pattern, e)))?;
+
+        Ok(NamePattern::Regex(regex, pattern.to_owned()))
+    }
+
+    /// Returns whether a process name matches the pattern.
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Name(pattern_name, case_sensitive) => name_matches(pattern_name, name, *case_sensitive),
+            NamePattern::Exact(exact_name) => exact_name == name,
+            NamePattern::Glob(pattern, case_sensitive) => glob_matches(pattern, name, *case_sensitive),
+            NamePattern::Regex(regex, _) => regex.is_match(name),
+        }
+    }
+}
+
+impl fmt::Display for NamePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NamePattern::Name(name, _)
+            | NamePattern::Exact(name)
+            | NamePattern::Glob(name, _)
+            | NamePattern::Regex(_, name) => write!(f, "{}", name),
+        }
+    }
+}
+
+
+/// Returns the PIDs of all processes whose name matches a pattern, in ascending order.
+/// 
+/// The processes with exactly the name of a `Name` pattern are preferred over the ones that
+/// only match it ignoring case or extension.
+pub fn find_processes_by_name(pattern: &NamePattern) -> Result<Vec<u32>> {
+    let mapping: HashMap<String, Vec<u32>> = get_process_name_pid_mapping()?;
+
+    if let NamePattern::Name(name, _) = pattern {
+        if let Some(pids) = mapping.get(name) {
+            return Ok(pids.clone());
+        }
+    }
+
+    let mut pids: Vec<u32> = Vec::new();
+    for (key, value) in &mapping {
+        if pattern.matches(key) {
+            if let NamePattern::Name(name, _) = pattern {
+                warn!("{}", format!("partial match found {} <- {}", key, name));
+            }
+            pids.extend(value);
+        }
+    }
+
+    if pids.is_empty() {
+        return Err(Error::new(ERROR_NOT_FOUND.to_hresult(), match pattern {
+            NamePattern::Name(name, _) | NamePattern::Exact(name) => format!("process {:#} not found", name),
+            _ => format!("no process matching {:#} found", pattern),
+        }));
+    }
+
+    pids.sort_unstable();
+    Ok(pids)
+}
+
+
+/// Returns the error for a pattern that matches multiple processes, listing their PIDs and
+/// names.
+/// 
+/// The caller passes the processes that remain once the excluded ones are removed, so that
+/// only the processes that can still be selected are listed.
+pub fn ambiguous_process_error(pattern: &NamePattern, pids: &[u32]) -> Error {
+    Error::new(HRESULT(-1), format!(
+        "multiple processes {} {} found ({}), select one by PID",
+        if matches!(pattern, NamePattern::Name(..) | NamePattern::Exact(_)) { "named" } else { "matching" },
+        pattern,
+        pids.iter()
+            .map(|pid| match get_process_name(*pid) {
+                Ok(process_name) => format!("{} {}", pid, process_name),
+                Err(_) => pid.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(", "),
+    ))
+}
+
+
+/// Returns the PIDs of all processes running the executable at a given path, in ascending order.
+/// 
+/// The paths are canonicalized and compared ignoring case, so that `/` separators and short
+/// (8.3) names match the image paths of the processes. Only the processes whose name matches
+/// the one of the executable have their image path queried.
+pub fn find_processes_by_path(path: &Path) -> Result<Vec<u32>> {
+    /// Canonicalizes a path for comparison, falling back to normalizing its separators.
+    fn normalize(path: &Path) -> String {
+        match dunce::canonicalize(path) {
+            Ok(canonical_path) => canonical_path.to_string_lossy().to_lowercase(),
+            Err(_) => path.to_string_lossy().replace('/', "\\").to_lowercase(),
+        }
+    }
+
+    let target_path: String = normalize(path);
+    let Some(target_name) = Path::new(&target_path).file_name().map(|name| name.to_string_lossy().into_owned()) else {
+        return Err(Error::new(HRESULT(-1), format!("invalid executable path {}", path.display())));
+    };
+
+    let mut pids: Vec<u32> = Vec::new();
+    for entry in get_process_list()? {
+        if !entry.is_accessible || entry.name.to_lowercase() != target_name {
+            continue;
+        }
+
+        match get_process_image_path(entry.pid) {
+            Ok(image_path) if normalize(Path::new(&image_path)) == target_path => pids.push(entry.pid),
+            Ok(_) => {},
+            Err(e) => debug!("{}", format!("failed to get the image path of process ({}): {}", entry.pid, e.message())),
+        }
+    }
+
+    if pids.is_empty() {
+        return Err(Error::new(ERROR_NOT_FOUND.to_hresult(), format!("no process running {} found", path.display())));
+    }
+
+    Ok(pids)
+}
+
+
+/// Returns the PID of the process running the executable at a given path.
+/// 
+/// Fails if multiple processes run the executable, listing their PIDs.
+pub fn find_process_by_path(path: &Path) -> Result<u32> {
+    match find_processes_by_path(path)?.as_slice() {
+        [pid] => Ok(*pid),
+        pids => Err(Error::new(HRESULT(-1), format!(
+            "multiple processes running {} found ({}), select one by PID",
+            path.display(),
+            pids.iter().map(u32::to_string).collect::<Vec<String>>().join(", "),
+        ))),
+    }
+}
+
+
+/// Top-level window of a process.
+#[derive(Clone, Debug)]
+pub struct WindowInfo {
+    /// PID of the process that owns the window.
+    pub pid: u32,
+    /// ID of the thread that owns the window.
+    pub tid: u32,
+    /// Title of the window.
+    pub title: String,
+}
+
+
+/// Returns the top-level windows that have a title.
+/// 
+/// Invisible windows and tool windows (e.g. floating toolbars) are skipped unless
+/// `include_hidden` is set.
+pub fn get_windows(include_hidden: bool) -> Result<Vec<WindowInfo>> {
+    /// Appends the handle of each enumerated window to the vector pointed to by `lparam`.
+    unsafe extern "system" fn enum_windows_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let windows: &mut Vec<HWND> = unsafe { &mut *(lparam.0 as *mut Vec<HWND>) };
+        windows.push(hwnd);
+
+        BOOL(1)
+    }
+
+    let mut hwnds: Vec<HWND> = Vec::new();
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-enumwindows.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/UI/WindowsAndMessaging/fn.EnumWindows.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/EnumWindows.
+        EnumWindows(Some(enum_windows_callback), LPARAM(&mut hwnds as *mut Vec<HWND> as isize))?;
+    }
+
+    let mut windows: Vec<WindowInfo> = Vec::new();
+    for hwnd in hwnds {
+        if !include_hidden {
+            let is_visible: bool = unsafe {
+                // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-iswindowvisible.
+                // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/UI/WindowsAndMessaging/fn.IsWindowVisible.html.
+                // https://microsoft.github.io/windows-rs/features/#/latest/search/IsWindowVisible.
+                IsWindowVisible(hwnd).as_bool()
+            };
+            let ex_style: u32 = unsafe {
+                // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getwindowlongw.
+                // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/UI/WindowsAndMessaging/fn.GetWindowLongW.html.
+                // https://microsoft.github.io/windows-rs/features/#/latest/search/GetWindowLongW.
+                GetWindowLongW(hwnd, GWL_EXSTYLE) as u32
+            };
+            if !is_visible || ex_style & WS_EX_TOOLWINDOW.0 != 0 {
+                continue;
+            }
+        }
+
+        let mut title: [u16; 512] = [0; 512];
+        let length: i32 = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getwindowtextw.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/UI/WindowsAndMessaging/fn.GetWindowTextW.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/GetWindowTextW.
+            GetWindowTextW(hwnd, &mut title)
+        };
+        if length <= 0 {
+            continue;
+        }
+
+        let mut pid: u32 = 0;
+        let tid: u32 = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getwindowthreadprocessid.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/UI/WindowsAndMessaging/fn.GetWindowThreadProcessId.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/GetWindowThreadProcessId.
+            GetWindowThreadProcessId(hwnd, Some(&mut pid))
+        };
+        if pid == 0 {
+            continue;
+        }
+
+        windows.push(WindowInfo { pid, tid, title: String::from_utf16_lossy(&title[..length as usize]) });
+    }
+
+    Ok(windows)
+}
+
+
+/// Returns the PID of the process that owns a top-level window whose title contains `title`
+/// (case-insensitive).
+/// 
+/// Fails if windows of multiple processes match, listing the candidates.
+pub fn find_process_by_window(title: &str, include_hidden: bool) -> Result<u32> {
+    let title_lowercase: String = title.to_lowercase();
+    let mut candidates: Vec<WindowInfo> = get_windows(include_hidden)?
+        .into_iter()
+        .filter(|window| window.title.to_lowercase().contains(&title_lowercase))
+        .collect();
+    candidates.sort_by_key(|window| window.pid);
+    candidates.dedup_by_key(|window| window.pid);
+
+    match candidates.as_slice() {
+        [] => Err(Error::new(ERROR_NOT_FOUND.to_hresult(), format!("no window titled {:?} found", title))),
+        [window] => Ok(window.pid),
+        windows => Err(Error::new(HRESULT(-1), format!(
+            "windows of multiple processes match {:?} ({}), select one by PID",
+            title,
+            windows.iter().map(|window| format!("{} {:?}", window.pid, window.title)).collect::<Vec<String>>().join(", "),
+        ))),
+    }
+}
+
+
+/// Waits for a process to exit and returns its exit code.
+/// 
+/// Waits indefinitely if `timeout` is `None`. Returns `None` if the process is still running
+/// when the timeout elapses.
+pub fn wait_for_process_exit(pid: u32, timeout: Option<Duration>) -> Result<Option<u32>> {
+    let dw_milliseconds: u32 = match timeout {
+        // INFINITE is u32::MAX, so longer timeouts are clamped just below it.
+        Some(timeout) => timeout.as_millis().min((INFINITE - 1) as u128) as u32,
+        None => INFINITE,
+    };
+
+    with_process(pid, PROCESS_SYNCHRONIZE | PROCESS_QUERY_LIMITED_INFORMATION, |h_process| {
+        let wait_result: WAIT_EVENT = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/synchapi/nf-synchapi-waitforsingleobject.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.WaitForSingleObject.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/WaitForSingleObject.
+            WaitForSingleObject(h_process, dw_milliseconds)
+        };
+
+        match wait_result {
+            WAIT_OBJECT_0 => {},
+            WAIT_TIMEOUT => return Ok(None),
+            WAIT_FAILED => return Err(Error::from_win32()),
+            _ => return Err(Error::new(HRESULT(-1), format!("unexpected wait result {:?}", wait_result))),
+        }
+
+        let mut dw_exit_code: u32 = 0;
+        unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getexitcodeprocess.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.GetExitCodeProcess.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/GetExitCodeProcess.
+            GetExitCodeProcess(h_process, &mut dw_exit_code)?;
+        }
+
+        Ok(Some(dw_exit_code))
+    })
+}
+
+
+/// Checks that the architecture of a DLL matches the one of a target process.
+fn check_dll_architecture(pid: u32, dll_path: &str) -> Result<()> {
+    let dll_arch: Arch = get_dll_architecture(Path::new(dll_path))?;
+    let process_arch: Arch = get_process_architecture(pid)?;
+
+    if dll_arch != process_arch {
+        return Err(Error::new(
+            ERROR_EXE_MACHINE_TYPE_MISMATCH.to_hresult(),
+            format!("cannot inject {} DLL into {} process", dll_arch, process_arch),
+        ));
+    }
+
+    Ok(())
+}
+
+
+/// Returns whether a process of the given architecture is a 32-bit (WOW64) process for the x64
+/// build of dll-spider.
+pub fn is_wow64_target(process_arch: Arch) -> bool {
+    Arch::current() == Arch::X64 && process_arch == Arch::X86
+}
+
+
+/// Checks that the architecture of a target process matches the one of dll-spider, or that
+/// the process is a WOW64 process if `allow_wow64` is set, and returns it.
+/// 
+/// The remote threads start at the address of `LoadLibraryW` in this process, which is only
+/// valid in processes of the same architecture, so the check cannot be skipped with `--force`.
+/// Only the techniques that resolve `LoadLibraryW` in the 32-bit modules of the target support
+/// WOW64 processes.
+pub fn check_process_architecture(pid: u32, allow_wow64: bool) -> Result<Arch> {
+    let current_arch: Arch = Arch::current();
+    let process_arch: Arch = get_process_architecture(pid)?;
+
+    if current_arch != process_arch && !(allow_wow64 && is_wow64_target(process_arch)) {
+        let alternative: &str = if is_wow64_target(process_arch) { "use the create-remote-thread method or" } else { "use" };
+        return Err(Error::new(
+            ERROR_EXE_MACHINE_TYPE_MISMATCH.to_hresult(),
+            format!(
+                "cannot load DLLs into {} process ({}) from {} dll-spider, {} the {} build of dll-spider",
+                process_arch, pid, current_arch, alternative, process_arch,
+            ),
+        ));
+    }
+
+    Ok(process_arch)
+}
+
+
+/// Checks a DLL before loading it into a target process, without modifying the process.
+/// 
+/// Checks that the DLL is a valid PE image and, unless `force` is set, that its architecture
+/// matches the one of the target process.
+fn check_dll(pid: u32, dll_path: &str, force: bool) -> Result<()> {
+    validate_pe(Path::new(dll_path))?;
+    if !force {
+        check_dll_architecture(pid, dll_path)?;
+    }
+
+    Ok(())
+}
+
+
+/// Performs the checks of `load_dlls` without loading the DLLs.
+/// 
+/// Unless `force` is set, checks that the target process is not critical to the system. Then
+/// checks that its architecture matches the one of dll-spider and that it can be opened with
+/// the access rights needed to load the DLLs, then checks the DLLs in order with `check_dll`,
+/// stopping at the first DLL that fails unless `keep_going` is set.
+pub fn check_load_dlls(pid: u32, dll_paths: &[&str], force: bool, keep_going: bool) -> Result<Vec<Result<()>>> {
+    if !force {
+        check_not_critical_process(pid)?;
+    }
+    // the default technique supports WOW64 processes, whose DLLs are always checked.
+    let process_arch: Arch = check_process_architecture(pid, true)?;
+    check_injection_access(pid)?;
+
+    let mut results: Vec<Result<()>> = Vec::with_capacity(dll_paths.len());
+    for dll_path in dll_paths {
+        let result: Result<()> = check_dll(pid, dll_path, force && !is_wow64_target(process_arch));
+
+        let failed: bool = result.is_err();
+        results.push(result);
+        if failed && !keep_going {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+
+/// Default time to wait for a remote `LoadLibraryW` thread, in milliseconds.
+pub const DEFAULT_LOAD_TIMEOUT_MS: u64 = 5000;
+
+
+/// Limit on the time a remote thread is waited on.
+#[derive(Clone, Copy, Debug)]
+pub struct ThreadTimeout {
+    /// Maximum time to wait for the thread to exit.
+    pub duration: Duration,
+    /// Whether to terminate the thread if it is still running after `duration`.
+    pub kill: bool,
+}
+
+impl Default for ThreadTimeout {
+    fn default() -> Self {
+        ThreadTimeout { duration: Duration::from_millis(DEFAULT_LOAD_TIMEOUT_MS), kill: false }
+    }
+}
+
+
+/// Flags of `CreateRemoteThread` that can be passed in `ThreadOptions`, by name.
+pub const THREAD_CREATION_FLAG_NAMES: &[(&str, THREAD_CREATION_FLAGS)] = &[
+    ("STACK_SIZE_PARAM_IS_A_RESERVATION", STACK_SIZE_PARAM_IS_A_RESERVATION),
+];
+
+
+/// Options of the remote threads created to load DLLs.
+/// 
+/// The default options wait indefinitely for the threads, which get the default stack of the
+/// executable of the target.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadOptions {
+    /// Limit on the time the thread is waited on, or `None` to wait indefinitely.
+    pub timeout: Option<ThreadTimeout>,
+    /// Size of the stack of the thread in bytes, committed unless `flags` holds
+    /// `STACK_SIZE_PARAM_IS_A_RESERVATION`, or 0 for the default size of the executable.
+    pub stack_size: usize,
+    /// Creation flags of the thread, from `THREAD_CREATION_FLAG_NAMES`.
+    pub flags: u32,
+    /// Function called with the PID of the target and the ID of the thread, which is created
+    /// suspended and resumed once the function returns, e.g. to let a debugger attach to the
+    /// target first. If `None`, the thread starts right away.
+    pub before_resume: Option<fn(u32, u32)>,
+    /// Whether the thread is left running once created, without waiting for it nor releasing
+    /// the memory it uses, for the injectors that support it.
+    pub detach: bool,
+}
+
+impl ThreadOptions {
+    /// Returns the sizes of the stack that is committed and reserved for the thread, as taken
+    /// by `NtCreateThreadEx`.
+    fn stack_sizes(&self) -> (usize, usize) {
+        if self.flags & STACK_SIZE_PARAM_IS_A_RESERVATION.0 != 0 {
+            (0, self.stack_size)
+        } else {
+            (self.stack_size, 0)
+        }
+    }
+}
+
+
+/// What is done with the memory holding the paths of the DLLs in a target process once they
+/// are loaded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MemoryCleanup {
+    /// Release the memory.
+    Free,
+    /// Zero out the memory, then release it.
+    #[default]
+    ZeroAndFree,
+    /// Keep the memory, e.g. for DLLs that read their path later on.
+    Keep,
+}
+
+
+/// Options of `load_dlls` and `reload_dlls`.
+#[derive(Clone, Copy, Debug)]
+pub struct InjectionOptions<'a> {
+    /// Whether to skip the critical process and architecture checks.
+    pub force: bool,
+    /// Whether to skip the checks on the mitigation policies of the target with
+    /// `check_mitigation_policies`.
+    pub ignore_mitigations: bool,
+    /// Whether to keep loading the remaining DLLs after one fails.
+    pub keep_going: bool,
+    /// Whether to check each DLL with `verify_injection` once it is loaded.
+    pub verify: bool,
+    /// Options of the remote threads that load the DLLs.
+    pub thread: ThreadOptions,
+    /// What is done with the memory holding the paths of the DLLs once they are loaded.
+    pub cleanup: MemoryCleanup,
+    /// Whether to skip the DLLs already in the module list of the target, instead of loading
+    /// them again and incrementing their reference count.
+    pub skip_loaded: bool,
+    /// Paths the target loads the DLLs from, one per DLL, when they differ from the paths of the
+    /// DLLs in this process (e.g. when the target runs in a container). They are written to the
+    /// target as is, without being canonicalized, since they may not exist locally.
+    pub remote_paths: Option<&'a [&'a str]>,
+}
+
+impl Default for InjectionOptions<'_> {
+    fn default() -> Self {
+        InjectionOptions {
+            force: false,
+            ignore_mitigations: false,
+            keep_going: false,
+            verify: true,
+            thread: ThreadOptions { timeout: Some(ThreadTimeout::default()), ..ThreadOptions::default() },
+            cleanup: MemoryCleanup::default(),
+            skip_loaded: true,
+            remote_paths: None,
+        }
+    }
+}
+
+
+/// Outcome of loading a DLL into a target process.
+#[derive(Debug)]
+pub struct DllLoad {
+    /// Base address of the DLL in the target process, or the reason why it was not loaded.
+    pub result: Result<usize>,
+    /// Time taken to check and load the DLL.
+    pub duration: Duration,
+    /// Whether the DLL is loaded by a thread left running, in which case its base address is
+    /// unknown and is reported as 0.
+    pub is_detached: bool,
+    /// Whether the DLL was already loaded by the target process and skipped with
+    /// `InjectionOptions::skip_loaded`.
+    pub is_already_loaded: bool,
+    /// Whether the injector ran, in which case the DLL may be loaded even if `result` is an
+    /// error, e.g. if the load could not be verified.
+    pub is_injected: bool,
+    /// Whether the base address of the DLL is unknown, because it is the exit code of the
+    /// remote thread truncated to 32 bits and was not verified.
+    pub is_base_address_unknown: bool,
+    /// Address of the path of the DLL in the target process, if it is kept with
+    /// `MemoryCleanup::Keep`.
+    pub remote_dll_path: Option<usize>,
+}
+
+
+/// Target process opened with the access rights needed to load DLLs into it.
+#[derive(Clone, Copy, Debug)]
+pub struct ProcessHandle {
+    /// PID of the process.
+    pub pid: u32,
+    /// Handle to the process, opened with `PROCESS_ALL_ACCESS` and owned by the caller.
+    pub handle: HANDLE,
+}
+
+
+/// DLL to load into a target process, with the options of the load.
+#[derive(Clone, Copy, Debug)]
+pub struct Payload<'a> {
+    /// Path of the DLL, checked with `check_dll` unless the load is forced.
+    pub dll_path: &'a str,
+    /// Path the target loads the DLL from, which is the canonical `dll_path` unless it is
+    /// overridden with `InjectionOptions::remote_paths`.
+    pub loaded_path: &'a str,
+    /// Address of the path of the DLL in the target process, as a null-terminated UTF-16 string,
+    /// or null if the injector does not use it.
+    pub remote_dll_path: *const c_void,
+    /// Options of the remote threads that load the DLL, whose timeout limits the time the DLL
+    /// is waited on to load.
+    pub thread: ThreadOptions,
+}
+
+
+/// DLL loaded into a target process.
+#[derive(Clone, Copy, Debug)]
+pub struct Injection {
+    /// Base address of the DLL in the target process.
+    pub base_address: usize,
+    /// Exit code of the remote thread that loaded the DLL, if the technique waits on one.
+    pub thread_exit_code: Option<u32>,
+    /// Whether the DLL is loaded by a thread left running with `ThreadOptions::detach`.
+    pub is_detached: bool,
+}
+
+
+/// Technique that loads a DLL into a target process.
+/// 
+/// The target is opened and the DLL is checked by the caller, so that every technique shares
+/// the same handle and the same checks.
+pub trait Injector {
+    /// Returns the name of the technique, as recorded in the audit log.
+    fn name(&self) -> &'static str;
+
+    /// Returns whether the technique reads the path of the DLL written to the target by the
+    /// caller, which is not written otherwise.
+    fn uses_remote_dll_path(&self) -> bool {
+        true
+    }
+
+    /// Returns whether the DLLs loaded by the technique are listed in the modules of the
+    /// target, which `verify_injection` looks them up in.
+    fn lists_loaded_modules(&self) -> bool {
+        true
+    }
+
+    /// Returns whether the technique loads the DLL in a new thread that can be left running
+    /// with `ThreadOptions::detach`.
+    fn supports_detached_threads(&self) -> bool {
+        false
+    }
+
+    /// Returns whether the target loads the DLL from its path, which can then differ from the
+    /// path of the DLL in this process with `InjectionOptions::remote_paths`.
+    fn loads_from_path(&self) -> bool {
+        true
+    }
+
+    /// Returns whether the technique can load DLLs into 32-bit (WOW64) processes from the x64
+    /// build of dll-spider.
+    fn supports_wow64_targets(&self) -> bool {
+        false
+    }
+
+    /// Returns whether the technique writes executable code to the target, which the arbitrary
+    /// code guard (ACG) policy of the target prevents.
+    fn writes_executable_code(&self) -> bool {
+        false
+    }
+
+    /// Loads a DLL into a target process.
+    fn inject(&self, target: &ProcessHandle, payload: &Payload) -> Result<Injection>;
+}
+
+
+/// Technique that calls `LoadLibraryW` in a new thread created with `CreateRemoteThread`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CreateRemoteThreadInjector;
+
+impl Injector for CreateRemoteThreadInjector {
+    fn name(&self) -> &'static str {
+        "CreateRemoteThread+LoadLibraryW"
+    }
+
+    fn supports_detached_threads(&self) -> bool {
+        true
+    }
+
+    fn supports_wow64_targets(&self) -> bool {
+        true
+    }
+
+    fn inject(&self, target: &ProcessHandle, payload: &Payload) -> Result<Injection> {
+        let process_arch: Arch = get_process_architecture(target.pid)?;
+        // https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-loadlibraryw.
+        let p_load_library_w: LPTHREAD_START_ROUTINE = get_remote_load_library_w(target)?;
+        debug!("LoadLibraryW address: {:?}", p_load_library_w);
+
+        // the argument of the thread is a 32-bit pointer in WOW64 processes.
+        if is_wow64_target(process_arch) && u32::try_from(payload.remote_dll_path as usize).is_err() {
+            return Err(Error::new(
+                ERROR_NOT_SUPPORTED.to_hresult(),
+                format!("the path of the DLL was written at {:?} in 32-bit process ({}), out of its reach", payload.remote_dll_path, target.pid),
+            ));
+        }
+
+        let h_thread: HANDLE = start_remote_thread(
+            target.handle,
+            target.pid,
+            p_load_library_w,
+            Some(payload.remote_dll_path),
+            payload.thread,
+        )?;
+        if payload.thread.detach {
+            return detach_remote_thread(h_thread, target.pid);
+        }
+
+        let dw_exit_code: u32 = wait_remote_thread(h_thread, target.pid, payload.thread.timeout)?;
+
+        get_loaded_dll(target, payload, dw_exit_code)
+    }
+}
+
+
+/// Signature of `NtCreateThreadEx`, which is resolved at runtime since it is exported by
+/// `ntdll.dll` but not documented.
+type NtCreateThreadExFn = unsafe extern "system" fn(
+    *mut HANDLE,      // ThreadHandle
+    u32,              // DesiredAccess
+    *const c_void,    // ObjectAttributes
+    HANDLE,           // ProcessHandle
+    *const c_void,    // StartRoutine
+    *const c_void,    // Argument
+    u32,              // CreateFlags
+    usize,            // ZeroBits
+    usize,            // StackSize
+    usize,            // MaximumStackSize
+    *const c_void,    // AttributeList
+) -> NTSTATUS;
+
+
+/// Flag of `NtCreateThreadEx` that creates the thread suspended.
+const THREAD_CREATE_FLAGS_CREATE_SUSPENDED: u32 = 0x1;
+
+
+/// First bytes of the system call stubs of `ntdll.dll` on x64 (`mov r10, rcx; mov eax, ...`).
+const SYSCALL_STUB_PREFIX: [u8; 4] = [0x4c, 0x8b, 0xd1, 0xb8];
+
+
+/// Returns the `NtCreateThreadEx` function of `ntdll.dll`.
+/// 
+/// Fails if the function is not exported, or if it does not start like a system call stub
+/// (e.g. because it is hooked), in which case its signature cannot be trusted. Only x64
+/// builds are supported.
+fn get_nt_create_thread_ex() -> Result<NtCreateThreadExFn> {
+    if !cfg!(target_arch = "x86_64") {
+        return Err(Error::new(ERROR_NOT_SUPPORTED.to_hresult(), "NtCreateThreadEx is only supported by x64 builds"));
+    }
+
+    let p_nt_create_thread_ex: FARPROC = get_proc_address(s!("ntdll.dll"), s!("NtCreateThreadEx"))
+        .map_err(|e| Error::new(ERROR_PROC_NOT_FOUND.to_hresult(), format!("NtCreateThreadEx is not exported by ntdll.dll: {}", e.message())))?;
+    let Some(nt_create_thread_ex) = p_nt_create_thread_ex else {
+        return Err(Error::new(ERROR_PROC_NOT_FOUND.to_hresult(), "NtCreateThreadEx is not exported by ntdll.dll"));
+    };
+    debug!("NtCreateThreadEx address: {:?}", nt_create_thread_ex);
+
+    // the function lies in the code of ntdll.dll, which is mapped in the current process.
+    let prefix: &[u8] = unsafe {
+        std::slice::from_raw_parts(nt_create_thread_ex as *const u8, SYSCALL_STUB_PREFIX.len())
+    };
+    if prefix != SYSCALL_STUB_PREFIX {
+        return Err(Error::new(
+            ERROR_NOT_SUPPORTED.to_hresult(),
+            format!("NtCreateThreadEx does not start like a system call stub ({:02x?}), it may be hooked", prefix),
+        ));
+    }
+
+    Ok(unsafe { transmute::<unsafe extern "system" fn() -> isize, NtCreateThreadExFn>(nt_create_thread_ex) })
+}
+
+
+/// Technique that calls `LoadLibraryW` in a new thread created with the native
+/// `NtCreateThreadEx` function, which some targets accept when they reject `CreateRemoteThread`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NtCreateThreadExInjector;
+
+impl Injector for NtCreateThreadExInjector {
+    fn name(&self) -> &'static str {
+        "NtCreateThreadEx+LoadLibraryW"
+    }
+
+    fn supports_detached_threads(&self) -> bool {
+        true
+    }
+
+    fn inject(&self, target: &ProcessHandle, payload: &Payload) -> Result<Injection> {
+        let nt_create_thread_ex: NtCreateThreadExFn = get_nt_create_thread_ex()?;
+
+        // https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-loadlibraryw.
+        let p_load_library_w: usize = get_remote_kernel32_proc_address(target, "LoadLibraryW")?;
+        debug!("LoadLibraryW address: {:#x}", p_load_library_w);
+
+        let (stack_commit, stack_reserve) = payload.thread.stack_sizes();
+        let mut h_thread: HANDLE = HANDLE::default();
+        let status: NTSTATUS = unsafe {
+            // NtCreateThreadEx is not documented, the call mirrors the one made by CreateRemoteThread:
+            // default security attributes and attribute list, and no creation flags unless the thread
+            // is created suspended. The stack size is split into the committed and the reserved size
+            // like CreateRemoteThread does.
+            nt_create_thread_ex(
+                &mut h_thread,
+                THREAD_ALL_ACCESS.0,
+                ptr::null(),
+                target.handle,
+                p_load_library_w as *const c_void,
+                payload.remote_dll_path,
+                if payload.thread.before_resume.is_some() { THREAD_CREATE_FLAGS_CREATE_SUSPENDED } else { 0 },
+                0,
+                stack_commit,
+                stack_reserve,
+                ptr::null(),
+            )
+        };
+        status.ok().map_err(|e| Error::new(
+            e.code(),
+            format!("NtCreateThreadEx failed in process ({}): {}", target.pid, e.message()),
+        ))?;
+        info!("{}", format!("remote thread started in process ({}): {:?}", target.pid, h_thread));
+
+        if let Some(before_resume) = payload.thread.before_resume {
+            // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getthreadid.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.GetThreadId.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/GetThreadId.
+            let tid: u32 = unsafe { GetThreadId(h_thread) };
+            resume_remote_thread(h_thread, target.pid, tid, before_resume)?;
+        }
+        if payload.thread.detach {
+            return detach_remote_thread(h_thread, target.pid);
+        }
+
+        let dw_exit_code: u32 = wait_remote_thread(h_thread, target.pid, payload.thread.timeout)?;
+
+        get_loaded_dll(target, payload, dw_exit_code)
+    }
+}
+
+
+/// Returns the DLL loaded into a target process by a remote `LoadLibraryW` thread, given the
+/// exit code of the thread.
+fn get_loaded_dll(target: &ProcessHandle, payload: &Payload, dw_exit_code: u32) -> Result<Injection> {
+    // the exit code of the remote thread is the truncated value returned by LoadLibraryW.
+    if dw_exit_code == 0 {
+        return Err(load_library_error(target, payload, "LoadLibraryW"));
+    }
+
+    // the full base address is read from the module list by `verify_injection`.
+    Ok(Injection { base_address: dw_exit_code as usize, thread_exit_code: Some(dw_exit_code), is_detached: false })
+}
+
+
+/// Returns the error of a function of the Windows loader that returned NULL in a target
+/// process, with the likely causes of the failure.
+/// 
+/// The error code of the remote thread is lost with it, so the causes cannot be narrowed down.
+fn load_library_error(target: &ProcessHandle, payload: &Payload, function: &str) -> Error {
+    Error::new(
+        HRESULT(-1),
+        format!(
+            "{} returned NULL for {:#} in process ({}): the path may not be visible to the target (e.g. a drive mapped \
+             in another session, or a directory its user cannot read), a DLL it depends on may be missing from the \
+             search path of the target, or its architecture may not match the one of the target",
+            function, payload.loaded_path, target.pid,
+        ),
+    )
+}
+
+
+/// Checks that a DLL loaded by an injector is present in the target process.
+/// 
+/// The DLL is looked up by the path the target loaded it from in the modules of the target, and its base
+/// address is taken from there. The DLLs that the technique does not add to the module list
+/// are checked by parsing the PE headers at their base address instead. The error includes
+/// the exit code of the remote thread, if any.
+fn verify_injection(target: &ProcessHandle, loaded_path: &str, injection: Injection, injector: &dyn Injector) -> Result<Injection> {
+    let exit_code: String = injection
+        .thread_exit_code
+        .map(|dw_exit_code| format!(", the remote thread exited with code {:#x}", dw_exit_code))
+        .unwrap_or_default();
+
+    if !injector.lists_loaded_modules() {
+        return match read_remote_headers(target.handle, injection.base_address) {
+            Ok(_) => Ok(injection),
+            Err(e) => Err(Error::new(
+                ERROR_MOD_NOT_FOUND.to_hresult(),
+                format!("no image of {:#} at {:#x} in process ({}): {}{}", loaded_path, injection.base_address, target.pid, e.message(), exit_code),
+            )),
+        };
+    }
+
+    match find_remote_module(target.handle, loaded_path) {
+        Ok(module) => Ok(Injection { base_address: module.base_address, ..injection }),
+        Err(e) if e.code() == ERROR_MOD_NOT_FOUND.to_hresult() => Err(Error::new(
+            ERROR_MOD_NOT_FOUND.to_hresult(),
+            format!("{:#} is not in the module list of process ({}) after loading it{}", loaded_path, target.pid, exit_code),
+        )),
+        Err(e) => Err(Error::new(
+            e.code(),
+            format!("failed to enumerate the modules of process ({}) to verify the load, pass --no-verify to skip it: {}", target.pid, e.message()),
+        )),
+    }
+}
+
+
+/// Returns the canonical path of a DLL, as listed in the module list of a process once loaded,
+/// or the path itself if it cannot be canonicalized.
+/// 
+/// Paths longer than `MAX_PATH` get the `\\?\` prefix with `to_long_path`, since
+/// `dunce::canonicalize` only keeps it when the path is not valid without it.
+fn canonicalize_dll_path(dll_path: &str) -> String {
+    let canonical_path: String = dunce::canonicalize(dll_path)
+        .ok()
+        .and_then(|path| path.to_str().map(str::to_owned))
+        .unwrap_or_else(|| dll_path.to_owned());
+
+    to_long_path(&canonical_path)
+}
+
+
+/// Returns the base address of a DLL if it is in the module list of a target process, matched
+/// by the path the target loads it from.
+fn find_loaded_dll(target: &ProcessHandle, loaded_path: &str) -> Result<Option<usize>> {
+    match find_remote_module(target.handle, loaded_path) {
+        Ok(module) => Ok(Some(module.base_address)),
+        Err(e) if e.code() == ERROR_MOD_NOT_FOUND.to_hresult() => Ok(None),
+        Err(e) => Err(Error::new(
+            e.code(),
+            format!("failed to enumerate the modules of process ({}) to check whether {:#} is already loaded: {}", target.pid, loaded_path, e.message()),
+        )),
+    }
+}
+
+
+/// Flags of `LoadLibraryExW` that can be passed to `LoadLibraryExInjector`, by name.
+pub const LOAD_LIBRARY_FLAG_NAMES: &[(&str, LOAD_LIBRARY_FLAGS)] = &[
+    ("DONT_RESOLVE_DLL_REFERENCES", DONT_RESOLVE_DLL_REFERENCES),
+    ("LOAD_IGNORE_CODE_AUTHZ_LEVEL", LOAD_IGNORE_CODE_AUTHZ_LEVEL),
+    ("LOAD_LIBRARY_AS_DATAFILE", LOAD_LIBRARY_AS_DATAFILE),
+    ("LOAD_LIBRARY_AS_DATAFILE_EXCLUSIVE", LOAD_LIBRARY_AS_DATAFILE_EXCLUSIVE),
+    ("LOAD_LIBRARY_AS_IMAGE_RESOURCE", LOAD_LIBRARY_AS_IMAGE_RESOURCE),
+    ("LOAD_LIBRARY_REQUIRE_SIGNED_TARGET", LOAD_LIBRARY_REQUIRE_SIGNED_TARGET),
+    ("LOAD_LIBRARY_SAFE_CURRENT_DIRS", LOAD_LIBRARY_SAFE_CURRENT_DIRS),
+    ("LOAD_LIBRARY_SEARCH_APPLICATION_DIR", LOAD_LIBRARY_SEARCH_APPLICATION_DIR),
+    ("LOAD_LIBRARY_SEARCH_DEFAULT_DIRS", LOAD_LIBRARY_SEARCH_DEFAULT_DIRS),
+    ("LOAD_LIBRARY_SEARCH_DLL_LOAD_DIR", LOAD_LIBRARY_SEARCH_DLL_LOAD_DIR),
+    ("LOAD_LIBRARY_SEARCH_SYSTEM32", LOAD_LIBRARY_SEARCH_SYSTEM32),
+    ("LOAD_LIBRARY_SEARCH_USER_DIRS", LOAD_LIBRARY_SEARCH_USER_DIRS),
+    ("LOAD_WITH_ALTERED_SEARCH_PATH", LOAD_WITH_ALTERED_SEARCH_PATH),
+];
+
+
+/// Technique that calls `LoadLibraryExW` with flags from a stub run by a new thread created
+/// with `CreateRemoteThread`, since a thread function takes a single argument.
+/// 
+/// Only x64 processes are supported.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LoadLibraryExInjector {
+    /// Flags passed to `LoadLibraryExW`, e.g. `LOAD_WITH_ALTERED_SEARCH_PATH`.
+    pub flags: u32,
+}
+
+impl Injector for LoadLibraryExInjector {
+    fn name(&self) -> &'static str {
+        "CreateRemoteThread+LoadLibraryExW"
+    }
+
+    fn writes_executable_code(&self) -> bool {
+        true
+    }
+
+    fn lists_loaded_modules(&self) -> bool {
+        // DLLs loaded as data files or resources are mapped without being added to the module list.
+        let data_flags: LOAD_LIBRARY_FLAGS = LOAD_LIBRARY_AS_DATAFILE | LOAD_LIBRARY_AS_DATAFILE_EXCLUSIVE | LOAD_LIBRARY_AS_IMAGE_RESOURCE;
+        self.flags & data_flags.0 == 0
+    }
+
+    fn inject(&self, target: &ProcessHandle, payload: &Payload) -> Result<Injection> {
+        let process_arch: Arch = get_process_architecture(target.pid)?;
+        if process_arch != Arch::X64 {
+            return Err(Error::new(
+                ERROR_NOT_SUPPORTED.to_hresult(),
+                format!("LoadLibraryExW flags are not supported for {} processes, only for x64 processes", process_arch),
+            ));
+        }
+
+        // https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-loadlibraryexw.
+        let p_load_library_ex_w: usize = get_remote_kernel32_proc_address(target, "LoadLibraryExW")?;
+        debug!("{}", format!("LoadLibraryExW address: {:#x}, flags: {:#x}", p_load_library_ex_w, self.flags));
+
+        let h_module: u64 = call_remote_function(
+            target,
+            p_load_library_ex_w as u64,
+            [payload.remote_dll_path as u64, 0, self.flags as u64],
+            payload.thread,
+        )?;
+        if h_module == 0 {
+            return Err(load_library_error(target, payload, "LoadLibraryExW"));
+        }
+
+        // the low bits of the handles of DLLs loaded as data files or resources tag the mapping.
+        Ok(Injection { base_address: h_module as usize & !3, thread_exit_code: None, is_detached: false })
+    }
+}
+
+
+/// Technique that calls `LoadLibraryW` then an initialization function exported by the DLL,
+/// from a stub run by a new thread created with `CreateRemoteThread`, so that the DLL can be
+/// passed an argument.
+/// 
+/// The initialization function is called with the address of the argument, as a
+/// null-terminated UTF-16 string, or with a null pointer if there is none. Only x64 processes
+/// are supported.
+#[derive(Clone, Debug, Default)]
+pub struct InitExportInjector {
+    /// Name of the initialization function exported by the DLL.
+    pub export: String,
+    /// Argument passed to the initialization function.
+    pub argument: Option<String>,
+}
+
+impl Injector for InitExportInjector {
+    fn name(&self) -> &'static str {
+        "CreateRemoteThread+LoadLibraryW+GetProcAddress"
+    }
+
+    fn writes_executable_code(&self) -> bool {
+        true
+    }
+
+    fn inject(&self, target: &ProcessHandle, payload: &Payload) -> Result<Injection> {
+        let process_arch: Arch = get_process_architecture(target.pid)?;
+        if process_arch != Arch::X64 {
+            return Err(Error::new(
+                ERROR_NOT_SUPPORTED.to_hresult(),
+                format!("initialization exports are not supported for {} processes, only for x64 processes", process_arch),
+            ));
+        }
+        let export_name_c: CString = CString::new(self.export.as_str())
+            .map_err(|_| Error::new(HRESULT(-1), format!("invalid export name {:?}", self.export)))?;
+
+        // the result slots come first, so that they are aligned, followed by the export name and the argument.
+        let mut data: Vec<u8> = vec![0; 16];
+        let export_name_offset: usize = data.len();
+        data.extend_from_slice(export_name_c.as_bytes_with_nul());
+        let argument_offset: Option<usize> = self.argument.as_ref().map(|argument| {
+            data.resize(data.len().next_multiple_of(2), 0);
+            let offset: usize = data.len();
+            data.extend(argument.encode_utf16().chain(std::iter::once(0)).flat_map(u16::to_le_bytes));
+            offset
+        });
+        let data_memory: RemoteMemory = write_remote_bytes(target.handle, &data, PAGE_READWRITE)?;
+        let data_address: u64 = data_memory.address() as u64;
+
+        let stub: Vec<u8> = build_init_export_stub(
+            payload.remote_dll_path as u64,
+            get_remote_kernel32_proc_address(target, "LoadLibraryW")? as u64,
+            get_remote_kernel32_proc_address(target, "GetProcAddress")? as u64,
+            data_address + export_name_offset as u64,
+            argument_offset.map_or(0, |offset| data_address + offset as u64),
+            [data_address, data_address + 8],
+        );
+        let result: Result<u32> = write_executable_remote_bytes(target.handle, &stub).and_then(|stub_memory| {
+            let start_address: LPTHREAD_START_ROUTINE = unsafe {
+                transmute::<*mut c_void, LPTHREAD_START_ROUTINE>(stub_memory.address())
+            };
+            let result: Result<u32> = run_remote_thread(target.handle, target.pid, start_address, None, payload.thread);
+
+            if result.as_ref().is_err_and(|e| e.code() == ERROR_TIMEOUT.to_hresult()) {
+                warn!("{}", format!("leaking {} bytes at {:?} in process ({}), still used by the remote thread", stub.len(), stub_memory.leak(), target.pid));
+            } else {
+                protect_remote_memory(target.handle, stub_memory.address(), stub.len(), PAGE_READWRITE)
+                    .and_then(|_| stub_memory.release())?;
+            }
+
+            result
+        });
+
+        let slots: Result<Vec<u8>> = match &result {
+            Err(e) if e.code() == ERROR_TIMEOUT.to_hresult() => {
+                warn!("{}", format!("leaking {} bytes at {:?} in process ({}), still used by the remote thread", data.len(), data_memory.leak(), target.pid));
+                return Err(result.unwrap_err());
+            },
+            _ => read_process_memory_by_handle(target.handle, data_address as usize, 16).map(|(slots, _)| slots),
+        };
+        data_memory.release()?;
+        let dw_exit_code: u32 = result?;
+        let slots: Vec<u8> = slots?;
+
+        // the stub stores the handle returned by LoadLibraryW, then the address returned by GetProcAddress.
+        let h_module: u64 = pe::read_u64(&slots, 0).unwrap_or_default();
+        let p_export: u64 = pe::read_u64(&slots, 8).unwrap_or_default();
+        if h_module == 0 {
+            return Err(load_library_error(target, payload, "LoadLibraryW"));
+        }
+        if p_export == 0 {
+            return Err(Error::new(ERROR_PROC_NOT_FOUND.to_hresult(), format!("{} does not export {}", payload.dll_path, self.export)));
+        }
+        info!("{}", format!("{} returned {:#x} in process ({})", self.export, dw_exit_code, target.pid));
+
+        Ok(Injection { base_address: h_module as usize, thread_exit_code: None, is_detached: false })
+    }
+}
+
+
+/// Magic number at the start of a `SharedConfig` block, `SPDR` in ASCII.
+pub const SHARED_CONFIG_MAGIC: u32 = u32::from_le_bytes(*b"SPDR");
+
+/// Version of the layout of a `SharedConfig` block.
+pub const SHARED_CONFIG_VERSION: u16 = 1;
+
+/// Size of the header of a `SharedConfig` block, in bytes.
+const SHARED_CONFIG_HEADER_SIZE: usize = 16;
+
+/// Offset of the counter of a `SharedConfig` block that the DLLs increment once they have read it.
+const SHARED_CONFIG_CONSUMERS_OFFSET: usize = 12;
+
+/// Security descriptor of a `SharedConfig` file mapping, in SDDL.
+/// 
+/// Everyone, including AppContainers, may map the block to read it and increment its counter,
+/// and its low integrity label lets low integrity processes map it for writing too.
+const SHARED_CONFIG_SDDL: &str = "D:(A;;0x7;;;WD)(A;;0x7;;;AC)(A;;GA;;;OW)S:(ML;;NW;;;LW)";
+
+
+/// Configuration block shared with the DLLs through a named file mapping, whose name is passed
+/// to their initialization export.
+/// 
+/// The block starts with a 16-byte little-endian header, followed by the content:
+/// 
+/// | Offset | Size | Field                                                        |
+/// |--------|------|--------------------------------------------------------------|
+/// | 0      | 4    | `SHARED_CONFIG_MAGIC`                                        |
+/// | 4      | 2    | `SHARED_CONFIG_VERSION`                                      |
+/// | 6      | 2    | reserved, 0                                                  |
+/// | 8      | 4    | size of the content, in bytes                                |
+/// | 12     | 4    | number of DLLs that have read the block, incremented by them |
+/// 
+/// The mapping is closed when dropped. DLLs that opened it keep it alive until they close it.
+pub struct SharedConfig {
+    /// Name of the file mapping, e.g. `Local\dll-spider-config-4242`.
+    pub name: String,
+    /// Handle to the file mapping.
+    h_mapping: HANDLE,
+    /// View of the file mapping in the current process.
+    view: MEMORY_MAPPED_VIEW_ADDRESS,
+}
+
+impl SharedConfig {
+    /// Creates a named file mapping and writes the header and the content of a configuration
+    /// block to it.
+    /// 
+    /// Fails if a mapping with the same name already exists, since it may belong to another
+    /// process. Its security descriptor allows processes of any integrity level to open it with
+    /// `FILE_MAP_READ | FILE_MAP_WRITE`.
+    pub fn create(name: &str, content: &[u8]) -> Result<SharedConfig> {
+        let content_size: u32 = u32::try_from(content.len())
+            .map_err(|_| Error::new(ERROR_INVALID_PARAMETER.to_hresult(), "the configuration is larger than 4 GB"))?;
+        let size: usize = SHARED_CONFIG_HEADER_SIZE + content.len();
+
+        // the last error is read before the security descriptor is freed, which may overwrite it.
+        let (h_mapping, already_exists): (HANDLE, bool) = with_security_attributes(SHARED_CONFIG_SDDL, |security_attributes| unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-createfilemappingw.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Memory/fn.CreateFileMappingW.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/CreateFileMappingW.
+            let h_mapping: HANDLE = CreateFileMappingW(
+                INVALID_HANDLE_VALUE,
+                Some(security_attributes),
+                PAGE_READWRITE,
+                (size as u64 >> 32) as u32,
+                size as u32,
+                &HSTRING::from(name),
+            )?;
+
+            Ok((h_mapping, GetLastError() == ERROR_ALREADY_EXISTS))
+        })?;
+        if already_exists {
+            close_handle(h_mapping)?;
+            return Err(Error::new(ERROR_ALREADY_EXISTS.to_hresult(), format!("file mapping {} already exists", name)));
+        }
+
+        let view: MEMORY_MAPPED_VIEW_ADDRESS = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-mapviewoffile.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Memory/fn.MapViewOfFile.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/MapViewOfFile.
+            MapViewOfFile(h_mapping, FILE_MAP_ALL_ACCESS, 0, 0, size)
+        };
+        if view.Value.is_null() {
+            let e: Error = Error::from_win32();
+            close_handle(h_mapping)?;
+            return Err(e);
+        }
+
+        let mut block: Vec<u8> = Vec::with_capacity(size);
+        block.extend_from_slice(&SHARED_CONFIG_MAGIC.to_le_bytes());
+        block.extend_from_slice(&SHARED_CONFIG_VERSION.to_le_bytes());
+        block.extend_from_slice(&0u16.to_le_bytes());
+        block.extend_from_slice(&content_size.to_le_bytes());
+        block.extend_from_slice(&0u32.to_le_bytes());
+        block.extend_from_slice(content);
+        unsafe {
+            ptr::copy_nonoverlapping(block.as_ptr(), view.Value as *mut u8, size);
+        }
+        debug!("{}", format!("configuration block of {} bytes shared as {}", size, name));
+
+        Ok(SharedConfig { name: name.to_owned(), h_mapping, view })
+    }
+
+    /// Returns the number of DLLs that have read the block.
+    pub fn consumers(&self) -> u32 {
+        let counter: &AtomicU32 = unsafe {
+            // the view is page-aligned, so the counter is aligned too.
+            AtomicU32::from_ptr((self.view.Value as *mut u8).add(SHARED_CONFIG_CONSUMERS_OFFSET) as *mut u32)
+        };
+
+        counter.load(Ordering::Acquire)
+    }
+
+    /// Waits for a number of DLLs to have read the block, up to a timeout.
+    /// 
+    /// Returns whether they all did.
+    pub fn wait_for_consumers(&self, count: u32, timeout: Duration) -> bool {
+        let start: Instant = Instant::now();
+        while self.consumers() < count {
+            if start.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        true
+    }
+}
+
+impl Drop for SharedConfig {
+    fn drop(&mut self) {
+        let result: Result<()> = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-unmapviewoffile.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Memory/fn.UnmapViewOfFile.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/UnmapViewOfFile.
+            UnmapViewOfFile(self.view)
+        }
+        .and_then(|_| close_handle(self.h_mapping));
+        if let Err(e) = result {
+            warn!("{}", format!("failed to close file mapping {}: {}", self.name, e.message()));
+        }
+    }
+}
+
+
+/// Converts a security descriptor from SDDL and passes it to a closure, as security attributes
+/// of non-inheritable handles.
+fn with_security_attributes<T>(sddl: &str, f: impl FnOnce(&SECURITY_ATTRIBUTES) -> Result<T>) -> Result<T> {
+    let mut security_descriptor = PSECURITY_DESCRIPTOR::default();
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/sddl/nf-sddl-convertstringsecuritydescriptortosecuritydescriptorw.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Security/Authorization/fn.ConvertStringSecurityDescriptorToSecurityDescriptorW.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/ConvertStringSecurityDescriptorToSecurityDescriptorW.
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            &HSTRING::from(sddl),
+            SDDL_REVISION_1,
+            &mut security_descriptor,
+            None,
+        )
+    }?;
+
+    let security_attributes = SECURITY_ATTRIBUTES {
+        nLength: size_of::<SECURITY_ATTRIBUTES>() as u32,
+        lpSecurityDescriptor: security_descriptor.0,
+        bInheritHandle: false.into(),
+    };
+    let result: Result<T> = f(&security_attributes);
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/winbase/nf-winbase-localfree.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Foundation/fn.LocalFree.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/LocalFree.
+        LocalFree(Some(HLOCAL(security_descriptor.0)));
+    }
+
+    result
+}
+
+
+/// Security descriptor of a `ReadyEvent`, in SDDL.
+/// 
+/// Everyone, including AppContainers, may wait on the event and set it, and its low integrity
+/// label lets low integrity processes set it too.
+const READY_EVENT_SDDL: &str = "D:(A;;0x100002;;;WD)(A;;0x100002;;;AC)(A;;GA;;;OW)S:(ML;;NW;;;LW)";
+
+
+/// Named manual-reset event that the loaded DLLs set once they are ready, e.g. once their
+/// hooks are installed.
+#[derive(Debug)]
+pub struct ReadyEvent {
+    /// Name of the event, e.g. `Local\overlay-ready`.
+    pub name: String,
+    /// Handle to the event.
+    h_event: HANDLE,
+}
+
+impl ReadyEvent {
+    /// Creates a named event, or opens it if it exists, in the non-signaled state.
+    /// 
+    /// Its security descriptor allows processes of any integrity level to set it.
+    pub fn create(name: &str) -> Result<ReadyEvent> {
+        let h_event: HANDLE = with_security_attributes(READY_EVENT_SDDL, |security_attributes| unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/synchapi/nf-synchapi-createeventw.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.CreateEventW.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/CreateEventW.
+            CreateEventW(Some(security_attributes), true, false, &HSTRING::from(name))
+        })?;
+
+        let ready_event = ReadyEvent { name: name.to_owned(), h_event };
+        // an existing event may have been left signaled.
+        ready_event.reset()?;
+
+        Ok(ready_event)
+    }
+
+    /// Sets the event back to the non-signaled state, e.g. before loading the DLLs into the
+    /// next target.
+    pub fn reset(&self) -> Result<()> {
+        unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/synchapi/nf-synchapi-resetevent.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.ResetEvent.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/ResetEvent.
+            ResetEvent(self.h_event)
+        }
+    }
+
+    /// Waits for the event to be set, up to a timeout.
+    /// 
+    /// Returns whether it was set.
+    pub fn wait(&self, timeout: Duration) -> Result<bool> {
+        let wait_event: WAIT_EVENT = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/synchapi/nf-synchapi-waitforsingleobject.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.WaitForSingleObject.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/WaitForSingleObject.
+            WaitForSingleObject(self.h_event, timeout.as_millis().min(INFINITE as u128 - 1) as u32)
+        };
+
+        match wait_event {
+            WAIT_OBJECT_0 => Ok(true),
+            WAIT_TIMEOUT => Ok(false),
+            _ => Err(Error::from_win32()),
+        }
+    }
+}
+
+impl Drop for ReadyEvent {
+    fn drop(&mut self) {
+        if let Err(e) = close_handle(self.h_event) {
+            warn!("{}", format!("failed to close event {}: {}", self.name, e.message()));
+        }
+    }
+}
+
+
+/// Security descriptor of a `PipeServer`, in SDDL.
+/// 
+/// Everyone, including AppContainers, may read from and write to the pipe, and its low
+/// integrity label lets low integrity processes write to it too.
+const PIPE_SERVER_SDDL: &str = "D:(A;;GRGW;;;WD)(A;;GRGW;;;AC)(A;;GA;;;OW)S:(ML;;NW;;;LW)";
+
+/// Size of the input and output buffers of a `PipeServer`, in bytes.
+const PIPE_BUFFER_SIZE: u32 = 4096;
+
+/// Maximum number of random names tried by `PipeServer::create`.
+const MAX_PIPE_NAME_ATTEMPTS: u32 = 8;
+
+
+/// Named pipe that a loaded DLL connects to, created with a random name.
+/// 
+/// The pipe accepts a single local client, and is closed when dropped unless a client
+/// connected to it.
+#[derive(Debug)]
+pub struct PipeServer {
+    /// Name of the pipe, e.g. `\\.\pipe\dll-spider-4242-5f0e9a1c3b7d2e48`.
+    pub name: String,
+    /// Handle to the pipe.
+    h_pipe: HANDLE,
+}
+
+impl PipeServer {
+    /// Creates a duplex byte-mode pipe with a random name, unique to the current process.
+    /// 
+    /// A new name is tried if a pipe with the same name already exists.
+    pub fn create() -> Result<PipeServer> {
+        let mut attempts: u32 = 0;
+        loop {
+            let name: String = format!(r"\\.\pipe\dll-spider-{}-{:016x}", std::process::id(), RandomState::new().build_hasher().finish());
+            let h_pipe: HANDLE = with_security_attributes(PIPE_SERVER_SDDL, |security_attributes| Ok(unsafe {
+                // https://learn.microsoft.com/en-us/windows/win32/api/namedpipeapi/nf-namedpipeapi-createnamedpipew.
+                // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Pipes/fn.CreateNamedPipeW.html.
+                // https://microsoft.github.io/windows-rs/features/#/latest/search/CreateNamedPipeW.
+                CreateNamedPipeW(
+                    &HSTRING::from(&name),
+                    PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE,
+                    PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                    1,
+                    PIPE_BUFFER_SIZE,
+                    PIPE_BUFFER_SIZE,
+                    0,
+                    Some(security_attributes),
+                )
+            }))?;
+
+            if h_pipe != INVALID_HANDLE_VALUE {
+                debug!("{}", format!("created pipe {}", name));
+                return Ok(PipeServer { name, h_pipe });
+            }
+
+            let e: Error = Error::from_win32();
+            attempts += 1;
+            if e.code() != ERROR_ACCESS_DENIED.to_hresult() || attempts == MAX_PIPE_NAME_ATTEMPTS {
+                return Err(e);
+            }
+            // FILE_FLAG_FIRST_PIPE_INSTANCE fails with ERROR_ACCESS_DENIED if the name is taken.
+            debug!("{}", format!("pipe {} already exists, trying another name", name));
+        }
+    }
+
+    /// Waits for a client to connect to the pipe, up to a timeout, and returns the pipe as a
+    /// file to read from and write to.
+    /// 
+    /// Fails with `ERROR_TIMEOUT` if no client connected in time.
+    pub fn accept(self, timeout: Duration) -> Result<File> {
+        // handles are not Send, so the raw value is passed to the thread waiting for the client.
+        let h_pipe: usize = self.h_pipe.0 as usize;
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let result: Result<()> = unsafe {
+                // https://learn.microsoft.com/en-us/windows/win32/api/namedpipeapi/nf-namedpipeapi-connectnamedpipe.
+                // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Pipes/fn.ConnectNamedPipe.html.
+                // https://microsoft.github.io/windows-rs/features/#/latest/search/ConnectNamedPipe.
+                ConnectNamedPipe(HANDLE(h_pipe as *mut c_void), None)
+            };
+            // the client may have connected between the creation of the pipe and the call.
+            let _ = sender.send(result.map_err(|e| e.code()).or_else(|code| if code == ERROR_PIPE_CONNECTED.to_hresult() { Ok(()) } else { Err(code) }));
+        });
+
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(())) => {},
+            Ok(Err(code)) => return Err(Error::from(code)),
+            Err(_) => {
+                // connecting to the pipe releases the waiting thread before the pipe is closed.
+                let _ = OpenOptions::new().read(true).write(true).open(&self.name);
+                let _ = receiver.recv();
+                return Err(Error::new(
+                    ERROR_TIMEOUT.to_hresult(),
+                    format!("no client connected to pipe {} within {} ms", self.name, timeout.as_millis()),
+                ));
+            },
+        }
+        debug!("{}", format!("client connected to pipe {}", self.name));
+
+        let pipe = ManuallyDrop::new(self);
+        Ok(unsafe { File::from_raw_handle(pipe.h_pipe.0) })
+    }
+}
+
+impl Drop for PipeServer {
+    fn drop(&mut self) {
+        if let Err(e) = close_handle(self.h_pipe) {
+            warn!("{}", format!("failed to close pipe {}: {}", self.name, e.message()));
+        }
+    }
+}
+
+
+/// Builds the x64 stub that loads a DLL and calls its initialization export.
+/// 
+/// The stub calls `LoadLibraryW` with the path of the DLL, then `GetProcAddress` with the name
+/// of the export, and finally the export with the argument. The values returned by
+/// `LoadLibraryW` and `GetProcAddress` are stored in the two result slots, and the stub stops
+/// at the first null one. The value returned by the export is returned by the stub.
+fn build_init_export_stub(
+    dll_path_address: u64,
+    load_library_w_address: u64,
+    get_proc_address_address: u64,
+    export_name_address: u64,
+    argument_address: u64,
+    result_slots: [u64; 2],
+) -> Vec<u8> {
+    let mut stub: Vec<u8> = Vec::new();
+    let mut jumps_to_end: Vec<usize> = Vec::new();
+
+    // sub rsp, 0x28 (shadow space, and alignment of the stack on 16 bytes).
+    stub.extend_from_slice(&[0x48, 0x83, 0xec, 0x28]);
+
+    // mov rcx, <path>; mov rax, <LoadLibraryW>; call rax; mov [<slot 1>], rax.
+    stub.extend_from_slice(&[0x48, 0xb9]);
+    stub.extend_from_slice(&dll_path_address.to_le_bytes());
+    stub.extend_from_slice(&[0x48, 0xb8]);
+    stub.extend_from_slice(&load_library_w_address.to_le_bytes());
+    stub.extend_from_slice(&[0xff, 0xd0, 0x48, 0xa3]);
+    stub.extend_from_slice(&result_slots[0].to_le_bytes());
+    // test rax, rax; jz <end>, whose displacement is patched below.
+    stub.extend_from_slice(&[0x48, 0x85, 0xc0, 0x74, 0x00]);
+    jumps_to_end.push(stub.len());
+
+    // mov rcx, rax; mov rdx, <export name>; mov rax, <GetProcAddress>; call rax; mov [<slot 2>], rax.
+    stub.extend_from_slice(&[0x48, 0x89, 0xc1, 0x48, 0xba]);
+    stub.extend_from_slice(&export_name_address.to_le_bytes());
+    stub.extend_from_slice(&[0x48, 0xb8]);
+    stub.extend_from_slice(&get_proc_address_address.to_le_bytes());
+    stub.extend_from_slice(&[0xff, 0xd0, 0x48, 0xa3]);
+    stub.extend_from_slice(&result_slots[1].to_le_bytes());
+    // test rax, rax; jz <end>.
+    stub.extend_from_slice(&[0x48, 0x85, 0xc0, 0x74, 0x00]);
+    jumps_to_end.push(stub.len());
+
+    // mov rcx, <argument>; call rax.
+    stub.extend_from_slice(&[0x48, 0xb9]);
+    stub.extend_from_slice(&argument_address.to_le_bytes());
+    stub.extend_from_slice(&[0xff, 0xd0]);
+
+    // end: add rsp, 0x28; ret.
+    let end: usize = stub.len();
+    for displacement_end in jumps_to_end {
+        stub[displacement_end - 1] = (end - displacement_end) as u8;
+    }
+    stub.extend_from_slice(&[0x48, 0x83, 0xc4, 0x28, 0xc3]);
+
+    stub
+}
+
+
+/// Allocates memory in a process, writes code to it, then makes it executable and read-only.
+/// 
+/// Returns the memory holding the code, which must be made writable again before it is
+/// released with `RemoteMemory::release`.
+fn write_executable_remote_bytes(h_process: HANDLE, code: &[u8]) -> Result<RemoteMemory> {
+    let code_memory: RemoteMemory = write_remote_bytes(h_process, code, PAGE_READWRITE)?;
+
+    protect_remote_memory(h_process, code_memory.address(), code.len(), PAGE_EXECUTE_READ).and_then(|_| unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-flushinstructioncache.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/Debug/fn.FlushInstructionCache.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/FlushInstructionCache.
+        FlushInstructionCache(h_process, Some(code_memory.address()), code.len())
+    })?;
+
+    Ok(code_memory)
+}
+
+
+/// Changes the protection of memory allocated in a process.
+fn protect_remote_memory(h_process: HANDLE, p_address: *const c_void, dw_size: usize, protection: PAGE_PROTECTION_FLAGS) -> Result<()> {
+    let mut old_protection: PAGE_PROTECTION_FLAGS = Default::default();
+
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-virtualprotectex.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Memory/fn.VirtualProtectEx.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/VirtualProtectEx.
+        VirtualProtectEx(
+            h_process,
+            p_address,
+            dw_size,
+            protection,
+            &mut old_protection,
+        )
+    }
+}
+
+
+/// Signature of `NtCreateSection`, which is resolved at runtime from `ntdll.dll`.
+type NtCreateSectionFn = unsafe extern "system" fn(
+    *mut HANDLE,      // SectionHandle
+    u32,              // DesiredAccess
+    *const c_void,    // ObjectAttributes
+    *const i64,       // MaximumSize
+    u32,              // SectionPageProtection
+    u32,              // AllocationAttributes
+    HANDLE,           // FileHandle
+) -> NTSTATUS;
+
+/// Signature of `NtMapViewOfSection`, which is resolved at runtime from `ntdll.dll`.
+type NtMapViewOfSectionFn = unsafe extern "system" fn(
+    HANDLE,           // SectionHandle
+    HANDLE,           // ProcessHandle
+    *mut *mut c_void, // BaseAddress
+    usize,            // ZeroBits
+    usize,            // CommitSize
+    *mut i64,         // SectionOffset
+    *mut usize,       // ViewSize
+    u32,              // InheritDisposition
+    u32,              // AllocationType
+    u32,              // Win32Protect
+) -> NTSTATUS;
+
+/// Signature of `NtUnmapViewOfSection`, which is resolved at runtime from `ntdll.dll`.
+type NtUnmapViewOfSectionFn = unsafe extern "system" fn(
+    HANDLE,           // ProcessHandle
+    *const c_void,    // BaseAddress
+) -> NTSTATUS;
+
+
+/// Access rights of a section object that allow mapping it for reading and writing.
+const SECTION_ALL_ACCESS: u32 = 0x000f_001f;
+
+/// Allocation attribute of a section backed by the pagefile whose pages are all committed.
+const SEC_COMMIT: u32 = 0x0800_0000;
+
+/// `SECTION_INHERIT` value of views that are not mapped into child processes.
+const VIEW_UNMAP: u32 = 2;
+
+
+/// Native functions of `ntdll.dll` that create and map sections.
+#[derive(Clone, Copy)]
+struct SectionApi {
+    /// `NtCreateSection`.
+    create_section: NtCreateSectionFn,
+    /// `NtMapViewOfSection`.
+    map_view_of_section: NtMapViewOfSectionFn,
+    /// `NtUnmapViewOfSection`.
+    unmap_view_of_section: NtUnmapViewOfSectionFn,
+}
+
+impl SectionApi {
+    /// Resolves the functions from `ntdll.dll`.
+    fn resolve() -> Result<Self> {
+        let resolve = |proc_name: PCSTR| -> Result<FARPROC> {
+            get_proc_address(s!("ntdll.dll"), proc_name).map_err(|e| Error::new(
+                ERROR_PROC_NOT_FOUND.to_hresult(),
+                format!("{} is not exported by ntdll.dll: {}", unsafe { proc_name.display() }, e.message()),
+            ))
+        };
+
+        unsafe {
+            Ok(SectionApi {
+                create_section: transmute::<FARPROC, Option<NtCreateSectionFn>>(resolve(s!("NtCreateSection"))?)
+                    .ok_or_else(|| Error::new(ERROR_PROC_NOT_FOUND.to_hresult(), "NtCreateSection is not exported by ntdll.dll"))?,
+                map_view_of_section: transmute::<FARPROC, Option<NtMapViewOfSectionFn>>(resolve(s!("NtMapViewOfSection"))?)
+                    .ok_or_else(|| Error::new(ERROR_PROC_NOT_FOUND.to_hresult(), "NtMapViewOfSection is not exported by ntdll.dll"))?,
+                unmap_view_of_section: transmute::<FARPROC, Option<NtUnmapViewOfSectionFn>>(resolve(s!("NtUnmapViewOfSection"))?)
+                    .ok_or_else(|| Error::new(ERROR_PROC_NOT_FOUND.to_hresult(), "NtUnmapViewOfSection is not exported by ntdll.dll"))?,
+            })
+        }
+    }
+
+    /// Creates a section backed by the pagefile, readable and writable, of the given size.
+    fn create(&self, size: usize) -> Result<HANDLE> {
+        let mut h_section: HANDLE = HANDLE::default();
+        let maximum_size: i64 = size as i64;
+        unsafe {
+            // NtCreateSection is documented as ZwCreateSection in the WDK:
+            // https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwcreatesection.
+            (self.create_section)(
+                &mut h_section,
+                SECTION_ALL_ACCESS,
+                ptr::null(),
+                &maximum_size,
+                PAGE_READWRITE.0,
+                SEC_COMMIT,
+                HANDLE::default(),
+            )
+        }
+        .ok()
+        .map_err(|e| Error::new(e.code(), format!("NtCreateSection failed: {}", e.message())))?;
+        debug!("section handle: {:?}", h_section);
+
+        Ok(h_section)
+    }
+
+    /// Maps a whole section into a process with the given protection.
+    fn map(&self, h_section: HANDLE, h_process: HANDLE, protection: PAGE_PROTECTION_FLAGS) -> Result<*mut c_void> {
+        let mut p_view: *mut c_void = ptr::null_mut();
+        let mut view_size: usize = 0;
+        unsafe {
+            // NtMapViewOfSection is documented as ZwMapViewOfSection in the WDK:
+            // https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwmapviewofsection.
+            (self.map_view_of_section)(
+                h_section,
+                h_process,
+                &mut p_view,
+                0,
+                0,
+                ptr::null_mut(),
+                &mut view_size,
+                VIEW_UNMAP,
+                0,
+                protection.0,
+            )
+        }
+        .ok()
+        .map_err(|e| Error::new(e.code(), format!("NtMapViewOfSection failed: {}", e.message())))?;
+        debug!("{}", format!("view of {} bytes mapped at {:?}", view_size, p_view));
+
+        Ok(p_view)
+    }
+
+    /// Unmaps a view of a section from a process.
+    fn unmap(&self, h_process: HANDLE, p_view: *mut c_void) -> Result<()> {
+        unsafe {
+            // NtUnmapViewOfSection is documented as ZwUnmapViewOfSection in the WDK:
+            // https://learn.microsoft.com/en-us/windows-hardware/drivers/ddi/wdm/nf-wdm-zwunmapviewofsection.
+            (self.unmap_view_of_section)(h_process, p_view)
+        }
+        .ok()
+        .map_err(|e| Error::new(e.code(), format!("NtUnmapViewOfSection failed: {}", e.message())))
+    }
+}
+
+
+/// Technique that shares the path of the DLL with the target through a section mapped in both
+/// processes instead of writing it with `WriteProcessMemory`, then calls `LoadLibraryW` in a new
+/// thread created with `CreateRemoteThread`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SectionInjector;
+
+impl Injector for SectionInjector {
+    fn name(&self) -> &'static str {
+        "NtMapViewOfSection+LoadLibraryW"
+    }
+
+    fn uses_remote_dll_path(&self) -> bool {
+        false
+    }
+
+    fn inject(&self, target: &ProcessHandle, payload: &Payload) -> Result<Injection> {
+        let api: SectionApi = SectionApi::resolve()?;
+
+        let mut path_w: Vec<u16> = payload.loaded_path.encode_utf16().collect();
+        path_w.push(0);
+
+        let h_section: HANDLE = api.create(path_w.len() * 2)?; // 2 bytes per character.
+        let result: Result<Injection> = load_dll_through_section(&api, h_section, target, payload, &path_w);
+        close_handle(h_section)?;
+
+        result
+    }
+}
+
+
+/// Writes the path of a DLL to a section through a local view, maps the section into a target
+/// process and loads the DLL from the remote view.
+/// 
+/// The local view is always unmapped. The remote view is unmapped too, unless the remote
+/// thread is left running after the timeout, since it may still read the path.
+fn load_dll_through_section(api: &SectionApi, h_section: HANDLE, target: &ProcessHandle, payload: &Payload, path_w: &[u16]) -> Result<Injection> {
+    let h_current_process: HANDLE = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getcurrentprocess.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.GetCurrentProcess.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetCurrentProcess.
+        GetCurrentProcess()
+    };
+    let p_load_library_w: LPTHREAD_START_ROUTINE = get_remote_load_library_w(target)?;
+
+    let p_local_view: *mut c_void = api.map(h_section, h_current_process, PAGE_READWRITE)?;
+    unsafe { ptr::copy_nonoverlapping(path_w.as_ptr(), p_local_view as *mut u16, path_w.len()) };
+
+    // the section keeps the path once the local view is gone, as long as the remote view exists.
+    let remote_view_result: Result<*mut c_void> = api.map(h_section, target.handle, PAGE_READONLY);
+    let local_unmap_result: Result<()> = api.unmap(h_current_process, p_local_view);
+    let p_remote_view: *mut c_void = remote_view_result?;
+    if let Err(e) = local_unmap_result {
+        api.unmap(target.handle, p_remote_view)?;
+        return Err(e);
+    }
+
+    let result: Result<u32> = run_remote_thread(
+        target.handle,
+        target.pid,
+        p_load_library_w,
+        Some(p_remote_view as *const c_void),
+        payload.thread,
+    );
+
+    if result.as_ref().is_err_and(|e| e.code() == ERROR_TIMEOUT.to_hresult()) {
+        warn!("{}", format!("leaving the view at {:?} mapped in process ({}), still used by the remote thread", p_remote_view, target.pid));
+    } else {
+        api.unmap(target.handle, p_remote_view)?;
+    }
+
+    get_loaded_dll(target, payload, result?)
+}
+
+
+/// Technique that hijacks an existing thread of the target process: the thread is suspended
+/// and redirected to a stub that calls `LoadLibraryW` and jumps back to where the thread was.
+/// 
+/// The DLL is loaded the next time the thread runs, so a thread blocked in a wait loads it
+/// only when the wait ends. Only x64 processes are supported.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ThreadHijackInjector;
+
+impl Injector for ThreadHijackInjector {
+    fn name(&self) -> &'static str {
+        "SetThreadContext+LoadLibraryW"
+    }
+
+    fn writes_executable_code(&self) -> bool {
+        true
+    }
+
+    fn inject(&self, target: &ProcessHandle, payload: &Payload) -> Result<Injection> {
+        let process_arch: Arch = get_process_architecture(target.pid)?;
+        if process_arch != Arch::X64 {
+            return Err(Error::new(
+                ERROR_NOT_SUPPORTED.to_hresult(),
+                format!("thread hijacking is not supported for {} processes, only for x64 processes", process_arch),
+            ));
+        }
+
+        let h_module: usize = hijack_thread(target, payload)?;
+        if h_module == 0 {
+            return Err(load_library_error(target, payload, "LoadLibraryW"));
+        }
+
+        Ok(Injection { base_address: h_module, thread_exit_code: None, is_detached: false })
+    }
+}
+
+
+/// Value of the result slot of the hijack stub until `LoadLibraryW` returns.
+const HIJACK_PENDING: u64 = u64::MAX;
+
+/// Maximum number of times a hijacked thread is checked for having left the hijack stub.
+const MAX_STUB_EXIT_CHECKS: u32 = 100;
+
+
+/// Thread context aligned on 16 bytes, as required by `GetThreadContext` on x64.
+#[cfg(target_arch = "x86_64")]
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Default)]
+struct AlignedContext(CONTEXT);
+
+
+/// Builds the x64 stub run by a hijacked thread.
+/// 
+/// The stub saves the flags and the volatile registers, aligns the stack, calls
+/// `LoadLibraryW` with the path of the DLL, stores the returned handle in the result slot
+/// that ends the stub, restores the registers and jumps back to `return_address`.
+fn build_hijack_stub(dll_path_address: u64, load_library_w_address: u64, return_address: u64) -> Vec<u8> {
+    /// Returns the `movdqu` instruction that stores (`0x7f`) or loads (`0x6f`) `xmm<i>` at `[rsp + 16 * i]`.
+    fn movdqu_rsp(opcode: u8, i: u8) -> [u8; 6] {
+        [0xf3, 0x0f, opcode, 0x44 | (i << 3), 0x24, 16 * i]
+    }
+
+    let mut stub: Vec<u8> = Vec::new();
+
+    // pushfq; cld; push rax; push rcx; push rdx; push r8; push r9; push r10; push r11.
+    stub.extend_from_slice(&[0x9c, 0xfc, 0x50, 0x51, 0x52, 0x41, 0x50, 0x41, 0x51, 0x41, 0x52, 0x41, 0x53]);
+    // push rbp; mov rbp, rsp; and rsp, -16; sub rsp, 0x60.
+    stub.extend_from_slice(&[0x55, 0x48, 0x89, 0xe5, 0x48, 0x83, 0xe4, 0xf0, 0x48, 0x83, 0xec, 0x60]);
+    // the volatile xmm0-xmm5 registers are saved too, since LoadLibraryW may use them.
+    for i in 0..6 {
+        stub.extend_from_slice(&movdqu_rsp(0x7f, i));
+    }
+
+    // sub rsp, 0x20 (shadow space); mov rcx, <path>; mov rax, <LoadLibraryW>; call rax.
+    stub.extend_from_slice(&[0x48, 0x83, 0xec, 0x20, 0x48, 0xb9]);
+    stub.extend_from_slice(&dll_path_address.to_le_bytes());
+    stub.extend_from_slice(&[0x48, 0xb8]);
+    stub.extend_from_slice(&load_library_w_address.to_le_bytes());
+    stub.extend_from_slice(&[0xff, 0xd0]);
+    // mov [rip + <result slot>], rax, whose displacement is patched below.
+    stub.extend_from_slice(&[0x48, 0x89, 0x05, 0x00, 0x00, 0x00, 0x00]);
+    let displacement_end: usize = stub.len();
+    // add rsp, 0x20.
+    stub.extend_from_slice(&[0x48, 0x83, 0xc4, 0x20]);
+
+    for i in 0..6 {
+        stub.extend_from_slice(&movdqu_rsp(0x6f, i));
+    }
+    // mov rsp, rbp; pop rbp; pop r11; pop r10; pop r9; pop r8; pop rdx; pop rcx; pop rax; popfq.
+    stub.extend_from_slice(&[0x48, 0x89, 0xec, 0x5d, 0x41, 0x5b, 0x41, 0x5a, 0x41, 0x59, 0x41, 0x58, 0x5a, 0x59, 0x58, 0x9d]);
+    // jmp [rip], followed by the return address.
+    stub.extend_from_slice(&[0xff, 0x25, 0x00, 0x00, 0x00, 0x00]);
+    stub.extend_from_slice(&return_address.to_le_bytes());
+
+    // the result slot is aligned, so that it is written and read atomically.
+    while !stub.len().is_multiple_of(size_of::<u64>()) {
+        stub.push(0xcc); // int3.
+    }
+    let result_offset: usize = stub.len();
+    stub.extend_from_slice(&HIJACK_PENDING.to_le_bytes());
+    let displacement: u32 = (result_offset - displacement_end) as u32;
+    stub[displacement_end - 4..displacement_end].copy_from_slice(&displacement.to_le_bytes());
+
+    stub
+}
+
+
+/// Opens the first thread of a process that can be suspended and redirected.
+/// 
+/// Returns the ID of the thread and its handle.
+fn open_hijackable_thread(pid: u32) -> Result<(u32, HANDLE)> {
+    get_thread_entries(pid)?
+        .iter()
+        .find_map(|thread_entry| unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-openthread.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.OpenThread.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/OpenThread.
+            OpenThread(THREAD_SUSPEND_RESUME | THREAD_GET_CONTEXT | THREAD_SET_CONTEXT, false, thread_entry.th32ThreadID)
+                .ok()
+                .map(|h_thread| (thread_entry.th32ThreadID, h_thread))
+        })
+        .ok_or_else(|| Error::new(ERROR_NOT_FOUND.to_hresult(), format!("no thread of process ({}) can be hijacked", pid)))
+}
+
+
+/// Suspends a thread, failing if it cannot be suspended.
+fn suspend_thread(h_thread: HANDLE) -> Result<()> {
+    // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-suspendthread.
+    // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.SuspendThread.html.
+    // https://microsoft.github.io/windows-rs/features/#/latest/search/SuspendThread.
+    if unsafe { SuspendThread(h_thread) } == u32::MAX {
+        return Err(Error::from_win32());
+    }
+
+    Ok(())
+}
+
+
+/// Resumes a thread, failing if it cannot be resumed.
+fn resume_thread(h_thread: HANDLE) -> Result<()> {
+    // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-resumethread.
+    // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.ResumeThread.html.
+    // https://microsoft.github.io/windows-rs/features/#/latest/search/ResumeThread.
+    if unsafe { ResumeThread(h_thread) } == u32::MAX {
+        return Err(Error::from_win32());
+    }
+
+    Ok(())
+}
+
+
+/// Loads a DLL into a target process by hijacking one of its threads, and returns the value
+/// returned by `LoadLibraryW`.
+/// 
+/// The original context of the thread is restored if it cannot be redirected to the stub.
+/// If the stub has not run after the timeout of the payload, an `ERROR_TIMEOUT` error is
+/// returned and the stub is left in the target, since the thread may still run it.
+#[cfg(target_arch = "x86_64")]
+fn hijack_thread(target: &ProcessHandle, payload: &Payload) -> Result<usize> {
+    // https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-loadlibraryw.
+    let p_load_library_w: usize = get_remote_kernel32_proc_address(target, "LoadLibraryW")?;
+    debug!("LoadLibraryW address: {:#x}", p_load_library_w);
+
+    let (tid, h_thread) = open_hijackable_thread(target.pid)?;
+    info!("{}", format!("hijacking thread {} of process ({})", tid, target.pid));
+
+    let result: Result<usize> = suspend_thread(h_thread).and_then(|_| {
+        let (p_stub, stub_size) = redirect_thread(target, payload, h_thread, p_load_library_w as u64)?;
+        wait_for_hijack_stub(target, payload, h_thread, tid, p_stub, stub_size)
+    });
+    close_handle(h_thread)?;
+
+    result
+}
+
+
+/// Loads a DLL into a target process by hijacking one of its threads.
+/// 
+/// Thread hijacking is only implemented by x64 builds.
+#[cfg(not(target_arch = "x86_64"))]
+fn hijack_thread(_target: &ProcessHandle, _payload: &Payload) -> Result<usize> {
+    Err(Error::new(ERROR_NOT_SUPPORTED.to_hresult(), "thread hijacking is only supported by x64 builds"))
+}
+
+
+/// Writes the hijack stub into a target process and redirects a suspended thread to it, then
+/// resumes the thread.
+/// 
+/// If any step fails, the stub is released and the original context of the thread is
+/// restored before the thread is resumed. Returns the address and the size of the stub.
+#[cfg(target_arch = "x86_64")]
+fn redirect_thread(target: &ProcessHandle, payload: &Payload, h_thread: HANDLE, load_library_w_address: u64) -> Result<(*mut c_void, usize)> {
+    let mut context = AlignedContext::default();
+    context.0.ContextFlags = CONTEXT_FULL_AMD64;
+    let context_result: Result<()> = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getthreadcontext.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/Debug/fn.GetThreadContext.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetThreadContext.
+        GetThreadContext(h_thread, &mut context.0)
+    };
+    if let Err(e) = context_result {
+        resume_thread(h_thread)?;
+        return Err(e);
+    }
+    debug!("{}", format!("original RIP: {:#x}, RSP: {:#x}", context.0.Rip, context.0.Rsp));
+
+    let stub: Vec<u8> = build_hijack_stub(payload.remote_dll_path as u64, load_library_w_address, context.0.Rip);
+    let stub_memory: RemoteMemory = match write_remote_bytes(target.handle, &stub, PAGE_EXECUTE_READWRITE) {
+        Ok(stub_memory) => stub_memory,
+        Err(e) => {
+            resume_thread(h_thread)?;
+            return Err(e);
+        },
+    };
+    let p_stub: *mut c_void = stub_memory.address();
+    debug!("address of the hijack stub: {:?}", p_stub);
+
+    let mut hijacked_context: AlignedContext = context;
+    hijacked_context.0.Rip = p_stub as u64;
+    let redirect_result: Result<()> = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-flushinstructioncache.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/Debug/fn.FlushInstructionCache.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/FlushInstructionCache.
+        FlushInstructionCache(target.handle, Some(p_stub), stub.len())
+            // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-setthreadcontext.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/Debug/fn.SetThreadContext.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/SetThreadContext.
+            .and_then(|_| SetThreadContext(h_thread, &hijacked_context.0))
+    }
+    .and_then(|_| resume_thread(h_thread));
+
+    if let Err(e) = redirect_result {
+        warn!("{}", format!("failed to redirect the thread, restoring its context: {}", e.message()));
+        // the thread is still suspended, since resuming it is the last step.
+        if let Err(e) = unsafe { SetThreadContext(h_thread, &context.0) } {
+            // the thread may still be redirected to the stub.
+            stub_memory.leak();
+            return Err(e);
+        }
+        stub_memory.release()?;
+        resume_thread(h_thread)?;
+        return Err(e);
+    }
+
+    // the stub is released by `wait_for_hijack_stub` once the thread has left it.
+    Ok((stub_memory.leak(), stub.len()))
+}
+
+
+/// Waits for a hijacked thread to run the hijack stub, and returns the value returned by
+/// `LoadLibraryW`.
+/// 
+/// The stub is released once the thread has left it.
+#[cfg(target_arch = "x86_64")]
+fn wait_for_hijack_stub(target: &ProcessHandle, payload: &Payload, h_thread: HANDLE, tid: u32, p_stub: *mut c_void, stub_size: usize) -> Result<usize> {
+    let result_address: usize = p_stub as usize + stub_size - size_of::<u64>();
+    let start: Instant = Instant::now();
+
+    let h_module: u64 = loop {
+        let (bytes, readable_bytes) = read_process_memory_by_handle(target.handle, result_address, size_of::<u64>())?;
+        let value: u64 = u64::from_le_bytes(bytes.try_into().unwrap_or_default());
+        if readable_bytes == size_of::<u64>() && value != HIJACK_PENDING {
+            break value;
+        }
+
+        if let Some(timeout) = payload.thread.timeout.filter(|timeout| start.elapsed() >= timeout.duration) {
+            warn!("{}", format!("leaking {} bytes at {:?} in process ({}), the hijack stub may still run", stub_size, p_stub, target.pid));
+            return Err(Error::new(
+                ERROR_TIMEOUT.to_hresult(),
+                format!("thread {} of process ({}) has not run the hijack stub after {} ms, it will load the DLL when it runs", tid, target.pid, timeout.duration.as_millis()),
+            ));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    // the thread restores its registers after storing the result, so it may still be in the stub.
+    let stub_range = (p_stub as u64)..(p_stub as u64 + stub_size as u64);
+    for _ in 0..MAX_STUB_EXIT_CHECKS {
+        let mut context = AlignedContext::default();
+        context.0.ContextFlags = CONTEXT_CONTROL_AMD64;
+
+        suspend_thread(h_thread)?;
+        let context_result: Result<()> = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getthreadcontext.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/Debug/fn.GetThreadContext.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/GetThreadContext.
+            GetThreadContext(h_thread, &mut context.0)
+        };
+        resume_thread(h_thread)?;
+        context_result?;
+
+        if !stub_range.contains(&context.0.Rip) {
+            release_remote_memory(target.handle, p_stub, stub_size)?;
+            return Ok(h_module as usize);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    warn!("{}", format!("leaking {} bytes at {:?} in process ({}), thread {} is still in the hijack stub", stub_size, p_stub, target.pid, tid));
+
+    Ok(h_module as usize)
+}
+
+
+/// Technique that makes the target process load a DLL by installing a `WH_GETMESSAGE` hook,
+/// whose procedure is exported by the DLL, on the thread that owns its main window.
+/// 
+/// The hook is removed once the DLL is loaded, which makes Windows unload the DLL from the
+/// target later on, unless the DLL takes a reference on itself.
+#[derive(Clone, Debug, Default)]
+pub struct SetWindowsHookInjector {
+    /// Name of the hook procedure exported by the DLL.
+    pub export: String,
+}
+
+impl Injector for SetWindowsHookInjector {
+    fn name(&self) -> &'static str {
+        "SetWindowsHookEx"
+    }
+
+    fn uses_remote_dll_path(&self) -> bool {
+        false
+    }
+
+    fn loads_from_path(&self) -> bool {
+        false
+    }
+
+    fn inject(&self, target: &ProcessHandle, payload: &Payload) -> Result<Injection> {
+        // the DLL is loaded by this process too, and SetWindowsHookEx fails obscurely across architectures.
+        let dll_arch: Arch = get_dll_architecture(Path::new(payload.dll_path))?;
+        let current_arch: Arch = Arch::current();
+        let target_arch: Arch = get_process_architecture(target.pid)?;
+        if dll_arch != current_arch || dll_arch != target_arch {
+            return Err(Error::new(
+                ERROR_EXE_MACHINE_TYPE_MISMATCH.to_hresult(),
+                format!("cannot hook {} process with {} DLL from {} process", target_arch, dll_arch, current_arch),
+            ));
+        }
+
+        let tid: u32 = get_window_thread_id(target.pid)?;
+        debug!("{}", format!("hooking thread {} of process ({})", tid, target.pid));
+
+        let h_module: HMODULE = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-loadlibraryexw.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/LibraryLoader/fn.LoadLibraryExW.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/LoadLibraryExW.
+            LoadLibraryExW(&HSTRING::from(payload.dll_path), None, DONT_RESOLVE_DLL_REFERENCES)
+        }?;
+
+        let result: Result<Injection> = get_hook_procedure(h_module, &self.export)
+            .and_then(|hook_procedure| hook_thread(target, payload, h_module, hook_procedure, tid));
+
+        unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-freelibrary.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Foundation/fn.FreeLibrary.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/FreeLibrary.
+            FreeLibrary(h_module)
+        }?;
+
+        result
+    }
+}
+
+
+/// Returns the ID of the thread that owns the main window of a process, i.e. its first
+/// visible top-level window.
+fn get_window_thread_id(pid: u32) -> Result<u32> {
+    get_windows(false)?
+        .into_iter()
+        .find(|window| window.pid == pid)
+        .map(|window| window.tid)
+        .ok_or_else(|| Error::new(ERROR_NOT_FOUND.to_hresult(), format!("process ({}) has no visible window", pid)))
+}
+
+
+/// Returns a hook procedure exported by a DLL loaded by the current process.
+fn get_hook_procedure(h_module: HMODULE, export_name: &str) -> Result<HOOKPROC> {
+    let export_name_c: CString = CString::new(export_name)
+        .map_err(|_| Error::new(HRESULT(-1), format!("invalid export name {:?}", export_name)))?;
+
+    let p_hook_procedure: FARPROC = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-getprocaddress.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/LibraryLoader/fn.GetProcAddress.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetProcAddress.
+        GetProcAddress(h_module, PCSTR(export_name_c.as_ptr() as *const u8))
+    };
+    if p_hook_procedure.is_none() {
+        return Err(Error::new(ERROR_PROC_NOT_FOUND.to_hresult(), format!("the DLL does not export {}", export_name)));
+    }
+
+    Ok(unsafe { transmute::<FARPROC, HOOKPROC>(p_hook_procedure) })
+}
+
+
+/// Installs a `WH_GETMESSAGE` hook on a thread of a target process, posts a message to the
+/// thread so that the hook runs, and waits for the DLL to appear in the target.
+/// 
+/// The hook is removed before returning, so nothing is left running in the target after the
+/// timeout of the payload: the error is `ERROR_OPERATION_ABORTED`, like for a terminated
+/// remote thread. A failure to remove the hook is only logged.
+fn hook_thread(target: &ProcessHandle, payload: &Payload, h_module: HMODULE, hook_procedure: HOOKPROC, tid: u32) -> Result<Injection> {
+    let h_hook: HHOOK = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setwindowshookexw.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/UI/WindowsAndMessaging/fn.SetWindowsHookExW.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/SetWindowsHookExW.
+        SetWindowsHookExW(WH_GETMESSAGE, hook_procedure, Some(HINSTANCE(h_module.0)), tid)
+    }?;
+    info!("{}", format!("hook installed on thread {} of process ({})", tid, target.pid));
+
+    let start: Instant = Instant::now();
+    let result: Result<Injection> = loop {
+        // WM_NULL is ignored by the thread, but makes GetMessage return and run the hook.
+        let post_result: Result<()> = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-postthreadmessagew.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/UI/WindowsAndMessaging/fn.PostThreadMessageW.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/PostThreadMessageW.
+            PostThreadMessageW(tid, WM_NULL, WPARAM(0), LPARAM(0))
+        };
+        if let Err(e) = post_result {
+            break Err(e);
+        }
+
+        if let Ok(module) = find_remote_module(target.handle, payload.dll_path) {
+            break Ok(Injection { base_address: module.base_address, thread_exit_code: None, is_detached: false });
+        }
+        if let Some(timeout) = payload.thread.timeout.filter(|timeout| start.elapsed() >= timeout.duration) {
+            break Err(Error::new(
+                ERROR_OPERATION_ABORTED.to_hresult(),
+                format!("hook on thread {} of process ({}) did not run after {} ms, removing it", tid, target.pid, timeout.duration.as_millis()),
+            ));
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    let unhook_result: Result<()> = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-unhookwindowshookex.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/UI/WindowsAndMessaging/fn.UnhookWindowsHookEx.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/UnhookWindowsHookEx.
+        UnhookWindowsHookEx(h_hook)
+    };
+    // the outcome of the injection matters more than the hook, which is removed when dll-spider exits anyway.
+    match unhook_result {
+        Ok(()) => debug!("hook removed"),
+        Err(e) => warn!("{}", format!("failed to remove the hook on thread {} of process ({}): {}", tid, target.pid, e.message())),
+    }
+
+    result
+}
+
+
+/// Technique that maps the DLL into the target process without the Windows loader: the
+/// sections are copied to a remote allocation, relocated, their imports are resolved from the
+/// export tables of the modules loaded by the target, and `DllMain` is called by a remote stub.
+/// 
+/// The DLL does not appear in the module list of the target, and cannot be unloaded. Only x64
+/// DLLs without delay-load imports nor static TLS data are supported, and .NET DLLs are not.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ManualMapInjector;
+
+impl Injector for ManualMapInjector {
+    fn name(&self) -> &'static str {
+        "ManualMap"
+    }
+
+    fn writes_executable_code(&self) -> bool {
+        true
+    }
+
+    fn uses_remote_dll_path(&self) -> bool {
+        false
+    }
+
+    fn loads_from_path(&self) -> bool {
+        false
+    }
+
+    fn lists_loaded_modules(&self) -> bool {
+        false
+    }
+
+    fn inject(&self, target: &ProcessHandle, payload: &Payload) -> Result<Injection> {
+        let process_arch: Arch = get_process_architecture(target.pid)?;
+        if process_arch != Arch::X64 {
+            return Err(Error::new(
+                ERROR_NOT_SUPPORTED.to_hresult(),
+                format!("manual mapping is not supported for {} processes, only for x64 processes", process_arch),
+            ));
+        }
+
+        let file: Vec<u8> = fs::read(payload.dll_path)?;
+        let headers: pe::PeHeaders = pe::parse_headers(&file)?;
+        check_manual_mapping_support(&headers, payload.dll_path)?;
+        let mut image: Vec<u8> = pe::map_image(&file, &headers)?;
+
+        let image_memory: RemoteMemory = allocate_remote_image(target.handle, &headers)?;
+        let p_image: *mut c_void = image_memory.address();
+        debug!("{}", format!("image mapped at {:?} in process ({}), preferred base {:#x}", p_image, target.pid, headers.image_base));
+
+        // nothing ran in the target until the image is initialized, so the image is released on failure.
+        let tls_callbacks: Vec<u32> = prepare_remote_image(target, payload, &headers, &mut image, p_image as u64)?;
+        image_memory.leak();
+
+        initialize_remote_image(target, payload, &headers, &tls_callbacks, p_image as u64)?;
+        info!("{}", format!("{} mapped at {:?} in process ({})", payload.dll_path, p_image, target.pid));
+
+        Ok(Injection { base_address: p_image as usize, thread_exit_code: None, is_detached: false })
+    }
+}
+
+
+/// Maximum number of forwarders followed to resolve an import.
+const MAX_FORWARDER_DEPTH: u32 = 8;
+
+/// Maximum length of the name of the target of a forwarded export, in bytes.
+const MAX_FORWARDER_LENGTH: usize = 256;
+
+/// `DllMain` reason of a DLL loaded into a process.
+const DLL_PROCESS_ATTACH: u64 = 1;
+
+/// Size of a `RUNTIME_FUNCTION` structure, in bytes.
+const RUNTIME_FUNCTION_SIZE: u32 = 12;
+
+
+/// Fails if a DLL uses a feature that manual mapping does not support.
+fn check_manual_mapping_support(headers: &pe::PeHeaders, dll_path: &str) -> Result<()> {
+    let unsupported = |feature: &str| Error::new(
+        ERROR_NOT_SUPPORTED.to_hresult(),
+        format!("cannot manually map {}: {}", dll_path, feature),
+    );
+
+    if headers.machine != IMAGE_FILE_MACHINE_AMD64.0 || !headers.is_64_bit {
+        return Err(unsupported("only x64 DLLs are supported"));
+    }
+    if headers.data_directory(pe::IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR).is_some() {
+        return Err(unsupported(".NET DLLs are not supported"));
+    }
+    if headers.data_directory(pe::IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT).is_some() {
+        return Err(unsupported("delay-load imports are not supported"));
+    }
+
+    Ok(())
+}
+
+
+/// Allocates the memory of an image in a process, at its preferred base address if it is free.
+fn allocate_remote_image(h_process: HANDLE, headers: &pe::PeHeaders) -> Result<RemoteMemory> {
+    let allocate = |address: Option<*const c_void>| unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-virtualallocex.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Memory/fn.VirtualAllocEx.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/VirtualAllocEx.
+        VirtualAllocEx(
+            h_process,
+            address,
+            headers.size_of_image as usize,
+            MEM_COMMIT | MEM_RESERVE,
+            PAGE_READWRITE,
+        )
+    };
+
+    let mut p_image: *mut c_void = allocate(Some(headers.image_base as *const c_void));
+    if p_image.is_null() {
+        if headers.data_directory(pe::IMAGE_DIRECTORY_ENTRY_BASERELOC).is_none() {
+            return Err(Error::new(
+                ERROR_NOT_SUPPORTED.to_hresult(),
+                format!("the preferred base address {:#x} is in use and the DLL has no base relocations", headers.image_base),
+            ));
+        }
+        p_image = allocate(None);
+    }
+    if p_image.is_null() {
+        return Err(Error::from_win32());
+    }
+
+    Ok(RemoteMemory { h_process, p_address: p_image, dw_size: headers.size_of_image as usize })
+}
+
+
+/// Relocates a mapped image for its remote base address, resolves its imports, writes it to
+/// the process and applies the protection of its sections.
+/// 
+/// Returns the TLS callbacks of the image, to run before its entry point.
+fn prepare_remote_image(target: &ProcessHandle, payload: &Payload, headers: &pe::PeHeaders, image: &mut [u8], base_address: u64) -> Result<Vec<u32>> {
+    pe::apply_relocations(image, headers, base_address.wrapping_sub(headers.image_base))?;
+    pe::write_u64(image, headers.image_base_offset(), base_address);
+
+    // static TLS data is set up by the loader only, so code reading it would crash the target.
+    let tls_callbacks: Vec<u32> = match pe::parse_tls_directory(image, headers, base_address)? {
+        Some(tls_directory) if tls_directory.static_data_size > 0 => {
+            return Err(Error::new(
+                ERROR_NOT_SUPPORTED.to_hresult(),
+                format!("cannot manually map {}: static TLS data is not supported", payload.dll_path),
+            ));
+        },
+        Some(tls_directory) => tls_directory.callbacks,
+        None => Vec::new(),
+    };
+
+    let mut resolver = RemoteImportResolver::new(target, payload);
+    for import in pe::parse_imports(image, headers)? {
+        let address: usize = resolver.resolve(&import.module_name, &import.symbol)?;
+        debug!("{}", format!("resolved {}!{} to {:#x}", import.module_name, import.symbol, address));
+        pe::write_u64(image, import.thunk_rva as usize, address as u64);
+    }
+
+    write_remote_memory(target.handle, base_address as *mut c_void, image)?;
+    protect_remote_image(target.handle, headers, base_address)?;
+
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-flushinstructioncache.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/Debug/fn.FlushInstructionCache.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/FlushInstructionCache.
+        FlushInstructionCache(target.handle, Some(base_address as *const c_void), headers.size_of_image as usize)
+    }?;
+
+    Ok(tls_callbacks)
+}
+
+
+/// Writes bytes to memory already allocated in a process.
+fn write_remote_memory(h_process: HANDLE, p_address: *mut c_void, bytes: &[u8]) -> Result<()> {
+    let mut lp_number_of_bytes_written: usize = 0;
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-writeprocessmemory.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/Debug/fn.WriteProcessMemory.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/WriteProcessMemory.
+        WriteProcessMemory(
+            h_process,
+            p_address,
+            bytes.as_ptr() as *const c_void,
+            bytes.len(),
+            Some(&mut lp_number_of_bytes_written),
+        )
+    }?;
+
+    if lp_number_of_bytes_written != bytes.len() {
+        return Err(Error::new(HRESULT(-1), "failed to write the bytes in memory"));
+    }
+
+    Ok(())
+}
+
+
+/// Applies the protection of the headers and of each section of an image mapped in a process.
+fn protect_remote_image(h_process: HANDLE, headers: &pe::PeHeaders, base_address: u64) -> Result<()> {
+    const PAGE_SIZE: u32 = 0x1000;
+
+    let mut regions: Vec<(u32, u32, PAGE_PROTECTION_FLAGS)> = vec![(0, headers.size_of_headers, PAGE_READONLY)];
+    for section in &headers.sections {
+        let is_executable: bool = section.characteristics & pe::IMAGE_SCN_MEM_EXECUTE != 0;
+        let is_readable: bool = section.characteristics & pe::IMAGE_SCN_MEM_READ != 0;
+        let is_writable: bool = section.characteristics & pe::IMAGE_SCN_MEM_WRITE != 0;
+        let protection: PAGE_PROTECTION_FLAGS = match (is_executable, is_readable, is_writable) {
+            (true, _, true) => PAGE_EXECUTE_READWRITE,
+            (true, true, false) => PAGE_EXECUTE_READ,
+            (true, false, false) => PAGE_EXECUTE,
+            (false, _, true) => PAGE_READWRITE,
+            (false, true, false) => PAGE_READONLY,
+            (false, false, false) => PAGE_NOACCESS,
+        };
+        let size: u32 = if section.virtual_size != 0 { section.virtual_size } else { section.size_of_raw_data };
+        regions.push((section.virtual_address, size, protection));
+    }
+
+    for (rva, size, protection) in regions {
+        if size == 0 {
+            continue;
+        }
+
+        protect_remote_memory(
+            h_process,
+            (base_address + rva as u64) as *const c_void,
+            pe::align_up(size, PAGE_SIZE) as usize,
+            protection,
+        )?;
+    }
+
+    Ok(())
+}
+
+
+/// Runs the initialization of an image mapped in a process: registers its exception handlers,
+/// runs its TLS callbacks, then calls its entry point with `DLL_PROCESS_ATTACH`.
+fn initialize_remote_image(target: &ProcessHandle, payload: &Payload, headers: &pe::PeHeaders, tls_callbacks: &[u32], base_address: u64) -> Result<()> {
+    // without its function table, exceptions raised by the image cannot be unwound and kill the target.
+    if let Some((rva, size)) = headers.data_directory(pe::IMAGE_DIRECTORY_ENTRY_EXCEPTION) {
+        let rtl_add_function_table: FARPROC = get_proc_address(s!("ntdll.dll"), s!("RtlAddFunctionTable"))?;
+        let registered: u64 = call_remote_function(
+            target,
+            rtl_add_function_table.map_or(0, |f| f as usize as u64),
+            [base_address + rva as u64, (size / RUNTIME_FUNCTION_SIZE) as u64, base_address],
+            payload.thread,
+        )?;
+        // RtlAddFunctionTable returns a BOOLEAN, the upper bits of the returned value are undefined.
+        if registered as u8 == 0 {
+            return Err(Error::new(HRESULT(-1), format!("RtlAddFunctionTable failed in process ({})", target.pid)));
+        }
+    }
+
+    for &callback in tls_callbacks {
+        debug!("{}", format!("running TLS callback at {:#x}", base_address + callback as u64));
+        call_remote_function(target, base_address + callback as u64, [base_address, DLL_PROCESS_ATTACH, 0], payload.thread)?;
+    }
+
+    if headers.address_of_entry_point != 0 {
+        let entry_point: u64 = base_address + headers.address_of_entry_point as u64;
+        debug!("{}", format!("calling DllMain at {:#x}", entry_point));
+
+        // DllMain returns a BOOL, the upper bits of the returned value are undefined.
+        if call_remote_function(target, entry_point, [base_address, DLL_PROCESS_ATTACH, 0], payload.thread)? as u32 == 0 {
+            return Err(Error::new(HRESULT(-1), format!("DllMain returned FALSE in process ({})", target.pid)));
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Builds the x64 stub that calls a function with three arguments, stores the returned value
+/// in the result slot that ends the stub, and returns it.
+fn build_call_stub(function_address: u64, arguments: [u64; 3]) -> Vec<u8> {
+    let mut stub: Vec<u8> = Vec::new();
+
+    // sub rsp, 0x28 (shadow space, and alignment of the stack on 16 bytes).
+    stub.extend_from_slice(&[0x48, 0x83, 0xec, 0x28]);
+    // mov rcx, <argument 1>; mov rdx, <argument 2>; mov r8, <argument 3>.
+    for (prefix, argument) in [[0x48, 0xb9], [0x48, 0xba], [0x49, 0xb8]].iter().zip(arguments) {
+        stub.extend_from_slice(prefix);
+        stub.extend_from_slice(&argument.to_le_bytes());
+    }
+    // mov rax, <function>; call rax.
+    stub.extend_from_slice(&[0x48, 0xb8]);
+    stub.extend_from_slice(&function_address.to_le_bytes());
+    stub.extend_from_slice(&[0xff, 0xd0]);
+    // mov [rip + <result slot>], rax; add rsp, 0x28; ret.
+    stub.extend_from_slice(&[0x48, 0x89, 0x05]);
+    stub.extend_from_slice(&5u32.to_le_bytes()); // the slot follows the last two instructions.
+    stub.extend_from_slice(&[0x48, 0x83, 0xc4, 0x28, 0xc3]);
+    stub.extend_from_slice(&0u64.to_le_bytes());
+
+    stub
+}
+
+
+/// Calls a function with three arguments in a new thread of a process, through a stub
+/// written to the process.
+/// 
+/// Returns the value returned by the function, read from the result slot of the stub. The
+/// stub is not released if the thread is left running.
+fn call_remote_function(target: &ProcessHandle, function_address: u64, arguments: [u64; 3], thread: ThreadOptions) -> Result<u64> {
+    let stub: Vec<u8> = build_call_stub(function_address, arguments);
+    let stub_memory: RemoteMemory = write_remote_bytes(target.handle, &stub, PAGE_EXECUTE_READWRITE)?;
+    let p_stub: *mut c_void = stub_memory.address();
+
+    let start_address: LPTHREAD_START_ROUTINE = unsafe {
+        transmute::<*mut c_void, LPTHREAD_START_ROUTINE>(p_stub)
+    };
+    let result: Result<u64> = run_remote_thread(target.handle, target.pid, start_address, None, thread)
+        .and_then(|_| read_process_memory_by_handle(target.handle, p_stub as usize + stub.len() - 8, 8))
+        .and_then(|(slot, _)| pe::read_u64(&slot, 0).ok_or_else(|| Error::new(HRESULT(-1), "failed to read the result slot")));
+
+    if result.as_ref().is_err_and(|e| e.code() == ERROR_TIMEOUT.to_hresult()) {
+        warn!("{}", format!("leaking {} bytes at {:?} in process ({}), still used by the remote thread", stub.len(), stub_memory.leak(), target.pid));
+    } else {
+        stub_memory.release()?;
+    }
+
+    result
+}
+
+
+/// Resolves the imports of a manually mapped image from the export tables of the modules
+/// loaded by the target process, loading the missing modules with `LoadLibraryW`.
+struct RemoteImportResolver<'a> {
+    /// Target process.
+    target: &'a ProcessHandle,
+    /// Options of the load, whose timeout applies to the loads of the missing modules.
+    payload: &'a Payload<'a>,
+    /// Modules already resolved and their exports, by uppercase name.
+    modules: HashMap<String, (ModuleInfo, Vec<pe::Export>)>,
+}
+
+impl<'a> RemoteImportResolver<'a> {
+    /// Creates a resolver for a target process.
+    fn new(target: &'a ProcessHandle, payload: &'a Payload<'a>) -> Self {
+        RemoteImportResolver { target, payload, modules: HashMap::new() }
+    }
+
+    /// Returns the address of a function exported by a module in the target process,
+    /// following the forwarded exports.
+    fn resolve(&mut self, module_name: &str, symbol: &pe::ImportSymbol) -> Result<usize> {
+        let mut module_name: String = module_name.to_owned();
+        let mut symbol: pe::ImportSymbol = symbol.clone();
+
+        for _ in 0..MAX_FORWARDER_DEPTH {
+            let (module, exports) = self.module(&module_name)?;
+            let module: ModuleInfo = module.clone();
+            let export: pe::Export = exports
+                .iter()
+                .find(|export| match &symbol {
+                    pe::ImportSymbol::Name(name) => export.name == *name,
+                    pe::ImportSymbol::Ordinal(ordinal) => export.ordinal == *ordinal as u32,
+                })
+                .cloned()
+                .ok_or_else(|| Error::new(
+                    ERROR_PROC_NOT_FOUND.to_hresult(),
+                    format!("{} is not exported by name by module {}", symbol, module.name),
+                ))?;
+            if !export.is_forwarded {
+                return Ok(module.base_address + export.rva as usize);
+            }
+
+            // the export points to the name of its target, e.g. `NTDLL.RtlAllocateHeap` or `MODULE.#12`.
+            let (forwarder, _) = read_process_memory_by_handle(self.target.handle, module.base_address + export.rva as usize, MAX_FORWARDER_LENGTH)?;
+            let forwarder: String = String::from_utf8_lossy(&forwarder[..forwarder.iter().position(|&c| c == 0).unwrap_or(forwarder.len())]).into_owned();
+            let Some((forwarder_module, forwarder_symbol)) = forwarder.rsplit_once('.') else {
+                return Err(Error::new(HRESULT(-1), format!("malformed forwarder {:?} of {}!{}", forwarder, module.name, symbol)));
+            };
+            debug!("{}", format!("{}!{} is forwarded to {}", module.name, symbol, forwarder));
+
+            module_name = format!("{}.dll", forwarder_module);
+            symbol = match forwarder_symbol.strip_prefix('#') {
+                Some(ordinal) => pe::ImportSymbol::Ordinal(ordinal.parse().map_err(|_| {
+                    Error::new(HRESULT(-1), format!("malformed forwarder {:?}", forwarder))
+                })?),
+                None => pe::ImportSymbol::Name(forwarder_symbol.to_owned()),
+            };
+        }
+
+        Err(Error::new(
+            ERROR_NOT_SUPPORTED.to_hresult(),
+            format!("{}!{} is forwarded more than {} times", module_name, symbol, MAX_FORWARDER_DEPTH),
+        ))
+    }
+
+    /// Returns a module loaded by the target process and its exports, loading the module if
+    /// it is missing.
+    fn module(&mut self, module_name: &str) -> Result<&(ModuleInfo, Vec<pe::Export>)> {
+        let key: String = module_name.to_uppercase();
+        if !self.modules.contains_key(&key) {
+            let module: ModuleInfo = match find_remote_module(self.target.handle, module_name) {
+                Ok(module) => module,
+                Err(_) => self.load_module(module_name)?,
+            };
+
+            let (_, headers) = read_remote_headers(self.target.handle, module.base_address)?;
+            let exports: Vec<pe::Export> = pe::parse_exports(&headers, |rva, size| {
+                read_process_memory_by_handle(self.target.handle, module.base_address + rva as usize, size).map(|(data, _)| data)
+            })?;
+
+            self.modules.insert(key.clone(), (module, exports));
+        }
+
+        Ok(&self.modules[&key])
+    }
+
+    /// Loads a module missing from the target process with `LoadLibraryW`, from the directory
+    /// of the DLL if it is there, and from the search path of the target otherwise.
+    fn load_module(&self, module_name: &str) -> Result<ModuleInfo> {
+        let local_path = Path::new(self.payload.dll_path).parent().map(|directory| directory.join(module_name));
+        let path: String = match local_path {
+            Some(path) if path.is_file() => to_long_path(&path.to_string_lossy()),
+            _ => module_name.to_owned(),
+        };
+        info!("{}", format!("loading dependency {} into process ({})", path, self.target.pid));
+
+        let path_memory: RemoteMemory = write_remote_wide_string(self.target.handle, &path)?;
+        let p_load_library_w: LPTHREAD_START_ROUTINE = get_remote_load_library_w(self.target)?;
+        let result: Result<u32> = run_remote_thread(self.target.handle, self.target.pid, p_load_library_w, Some(path_memory.address()), self.payload.thread);
+        if result.as_ref().is_err_and(|e| e.code() == ERROR_TIMEOUT.to_hresult()) {
+            path_memory.leak();
+        } else {
+            path_memory.release()?;
+        }
+
+        let dw_exit_code: u32 = result?;
+        if dw_exit_code == 0 {
+            return Err(Error::new(HRESULT(-1), format!("failed to load dependency {} into process ({})", path, self.target.pid)));
+        }
+
+        // API set names (e.g. `api-ms-win-crt-runtime-l1-1-0.dll`) are not the names of the modules
+        // they resolve to, which are found by the truncated handle returned by LoadLibraryW instead.
+        get_process_modules_by_handle(self.target.handle)?
+            .into_iter()
+            .find(|module| module_matches(module, module_name) || module.base_address as u32 == dw_exit_code)
+            .ok_or_else(|| Error::new(HRESULT(-1), format!("module {:#} is not loaded in the target process", module_name)))
+    }
+}
+
+
+/// Name of the export that implements the loader of reflective DLLs.
+const REFLECTIVE_LOADER_EXPORT: &str = "ReflectiveLoader";
+
+
+/// Technique that writes the DLL file as is to the target process and starts a thread at its
+/// `ReflectiveLoader` export, which maps the DLL itself and returns its base address.
+/// 
+/// The DLL does not appear in the module list of the target, unless its loader registers it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReflectiveInjector;
+
+impl Injector for ReflectiveInjector {
+    fn name(&self) -> &'static str {
+        "ReflectiveLoader"
+    }
+
+    fn writes_executable_code(&self) -> bool {
+        true
+    }
+
+    fn uses_remote_dll_path(&self) -> bool {
+        false
+    }
+
+    fn loads_from_path(&self) -> bool {
+        false
+    }
+
+    fn lists_loaded_modules(&self) -> bool {
+        false
+    }
+
+    fn inject(&self, target: &ProcessHandle, payload: &Payload) -> Result<Injection> {
+        let file: Vec<u8> = fs::read(payload.dll_path)?;
+        let loader_offset: usize = get_reflective_loader_offset(&file, payload.dll_path)?;
+        debug!("{}", format!("{} found at file offset {:#x}", REFLECTIVE_LOADER_EXPORT, loader_offset));
+
+        let file_memory: RemoteMemory = write_remote_bytes(target.handle, &file, PAGE_EXECUTE_READWRITE)?;
+        let p_file: *mut c_void = file_memory.address();
+        let loader_address: u64 = p_file as u64 + loader_offset as u64;
+        debug!("{}", format!("DLL file written at {:?} in process ({})", p_file, target.pid));
+
+        // the loader takes a single parameter, which is null, and is called through a stub on x64 so
+        // that its 64-bit result is not truncated to the exit code of the thread.
+        let result: Result<u64> = match get_process_architecture(target.pid)? {
+            Arch::X64 => call_remote_function(target, loader_address, [0, 0, 0], payload.thread),
+            _ => run_remote_thread(
+                target.handle,
+                target.pid,
+                unsafe { transmute::<usize, LPTHREAD_START_ROUTINE>(loader_address as usize) },
+                None,
+                payload.thread,
+            ).map(u64::from),
+        };
+
+        let base_address: u64 = match result {
+            Err(e) if e.code() == ERROR_TIMEOUT.to_hresult() => {
+                warn!("{}", format!("leaking {} bytes at {:?} in process ({}), still used by the remote thread", file.len(), file_memory.leak(), target.pid));
+                return Err(e);
+            },
+            result => result,
+        }?;
+
+        // the loader maps the DLL to a new allocation, unless it runs it in place.
+        if (p_file as u64..p_file as u64 + file.len() as u64).contains(&base_address) {
+            file_memory.leak();
+        } else {
+            file_memory.release()?;
+        }
+        if base_address == 0 {
+            return Err(Error::new(HRESULT(-1), format!("{} failed in process ({})", REFLECTIVE_LOADER_EXPORT, target.pid)));
+        }
+
+        Ok(Injection { base_address: base_address as usize, thread_exit_code: None, is_detached: false })
+    }
+}
+
+
+/// Returns the offset of the `ReflectiveLoader` export in the file of a DLL.
+/// 
+/// As in the original reflective loader, any export whose name contains `ReflectiveLoader`
+/// matches, so that decorated names (e.g. `_ReflectiveLoader@4`) are found too.
+fn get_reflective_loader_offset(file: &[u8], dll_path: &str) -> Result<usize> {
+    let headers: pe::PeHeaders = pe::parse_headers(file)?;
+    let exports: Vec<pe::Export> = pe::parse_exports(&headers, |rva, size| {
+        let offset: usize = pe::rva_to_offset(&headers, rva)
+            .ok_or_else(|| Error::new(HRESULT(-1), format!("RVA {:#x} is not backed by the file", rva)))?;
+        // names near the end of a section are read up to the end of the file.
+        Ok(file.get(offset..).map(|data| data[..size.min(data.len())].to_vec()).unwrap_or_default())
+    })?;
+
+    let export: &pe::Export = exports
+        .iter()
+        .find(|export| export.name.contains(REFLECTIVE_LOADER_EXPORT) && !export.is_forwarded)
+        .ok_or_else(|| Error::new(
+            ERROR_PROC_NOT_FOUND.to_hresult(),
+            format!("{} does not export {}", dll_path, REFLECTIVE_LOADER_EXPORT),
+        ))?;
+
+    pe::rva_to_offset(&headers, export.rva)
+        .ok_or_else(|| Error::new(HRESULT(-1), format!("{} of {} is not backed by the file", REFLECTIVE_LOADER_EXPORT, dll_path)))
+}
+
+
+/// Loads a DLL into a target process with `CreateRemoteThreadInjector` and returns its base
+/// address.
+/// 
+/// The exit code of the remote thread only holds the low 32 bits of the handle returned by
+/// `LoadLibraryW`, so the load is verified and the base address is read from the module list
+/// of the target once the DLL is loaded. Unless `force` is set, the DLL is loaded only if its
+/// architecture matches the one of the target process.
+pub fn load_dll(pid: u32, dll_path: &str, force: bool) -> Result<usize> {
+    let options = InjectionOptions { force, ..InjectionOptions::default() };
+
+    load_dlls(pid, &[dll_path], &options, &CreateRemoteThreadInjector)?.remove(0).result
+}
+
+
+/// Loads DLLs in order into a target process with an injector and returns the base address
+/// of each of them.
+/// 
+/// The process is opened once, and the paths of all DLLs are written to a single remote
+/// allocation if the injector uses them. Unless `options.force` is set, the DLLs are not
+/// loaded into critical system processes, and a DLL is loaded only if its architecture matches
+/// the one of the target process. Loading stops at the first DLL that fails, unless
+/// `options.keep_going` is set: the returned vector holds the outcome of each DLL that was
+/// attempted, in order.
+/// 
+/// The architecture of the target must match the one of dll-spider, and unless
+/// `options.ignore_mitigations` is set, its mitigation policies are checked with
+/// `check_mitigation_policies`. The DLLs are checked with
+/// `check_dll` before the target is opened. Unless `options.keep_going` is set, nothing is
+/// loaded if one of them is not a valid DLL or does not match the architecture of the target.
+/// 
+/// Unless `options.verify` is unset, each DLL is checked with `verify_injection` once loaded.
+/// Without it, the base address returned by the techniques that only get the exit code of the
+/// remote thread is truncated to 32 bits in 64-bit targets, so `DllLoad::is_base_address_unknown`
+/// is set.
+/// 
+/// Unless `options.skip_loaded` is unset, the DLLs already in the module list of the target are
+/// not loaded again: they are reported as loaded at their current base address, with
+/// `DllLoad::is_already_loaded` set.
+/// 
+/// If `options.remote_paths` is set, the local DLLs are still checked, but the target loads
+/// them from the remote paths, which the verification and the check above look up instead.
+/// 
+/// The remote allocation is cleaned up once the DLLs are loaded as set by `options.cleanup`.
+/// If a remote thread is left running after the timeout of `options.thread`, loading stops
+/// and the allocation is not released, since the thread may still read from it. The allocation
+/// is not released either if the threads are detached with `options.thread.detach`, which
+/// requires an injector that supports it.
+pub fn load_dlls(pid: u32, dll_paths: &[&str], options: &InjectionOptions, injector: &dyn Injector) -> Result<Vec<DllLoad>> {
+    let InjectionOptions { force, ignore_mitigations, keep_going, verify, thread, cleanup, skip_loaded, remote_paths } = *options;
+    if thread.detach && !injector.supports_detached_threads() {
+        return Err(Error::new(
+            ERROR_NOT_SUPPORTED.to_hresult(),
+            format!("{} cannot leave the thread loading a DLL running without waiting for it", injector.name()),
+        ));
+    }
+    if let Some(remote_paths) = remote_paths {
+        if !injector.loads_from_path() {
+            return Err(Error::new(
+                ERROR_NOT_SUPPORTED.to_hresult(),
+                format!("{} does not load the DLLs from a path, so they cannot be loaded from a remote path", injector.name()),
+            ));
+        }
+        if remote_paths.len() != dll_paths.len() {
+            return Err(Error::new(
+                ERROR_INVALID_PARAMETER.to_hresult(),
+                format!("{} remote paths given for {} DLLs", remote_paths.len(), dll_paths.len()),
+            ));
+        }
+    }
+    if !force {
+        check_not_critical_process(pid)?;
+    }
+    let process_arch: Arch = check_process_architecture(pid, injector.supports_wow64_targets())?;
+    let is_64_bit_target: bool = matches!(process_arch, Arch::X64 | Arch::Arm64);
+    if !ignore_mitigations {
+        check_mitigation_policies(pid, injector)?;
+    }
+
+    // the DLLs are checked before opening the target, which is not touched unless a DLL can be loaded.
+    // their architecture is always checked for WOW64 processes, which can only load 32-bit DLLs.
+    let check_architecture: bool = !force || is_wow64_target(process_arch);
+    let checks: Vec<Result<()>> = dll_paths.iter().map(|dll_path| check_dll(pid, dll_path, !check_architecture)).collect();
+    if !keep_going || checks.iter().all(Result::is_err) {
+        if let Some(Err(e)) = checks.iter().find(|check| check.is_err()) {
+            return Err(e.clone());
+        }
+    }
+
+    // the remote paths are written as is, since they may not exist locally.
+    let loaded_paths: Vec<String> = match remote_paths {
+        Some(remote_paths) => remote_paths.iter().map(|remote_path| remote_path.to_string()).collect(),
+        None => dll_paths.iter().map(|dll_path| canonicalize_dll_path(dll_path)).collect(),
+    };
+
+    with_process(pid, PROCESS_ALL_ACCESS, |h_process| {
+        let target = ProcessHandle { pid, handle: h_process };
+        let remote_paths: Option<(RemoteMemory, Vec<usize>)> = injector
+            .uses_remote_dll_path()
+            .then(|| write_remote_wide_strings(h_process, &loaded_paths.iter().map(String::as_str).collect::<Vec<&str>>()))
+            .transpose()?;
+
+        let mut results: Vec<DllLoad> = Vec::with_capacity(dll_paths.len());
+        let mut is_thread_running: bool = false;
+        for (i, dll_path) in dll_paths.iter().enumerate() {
+            let start: Instant = Instant::now();
+            let loaded_path: &str = &loaded_paths[i];
+            let payload = Payload {
+                dll_path,
+                loaded_path,
+                remote_dll_path: remote_paths
+                    .as_ref()
+                    .map_or(ptr::null(), |(path_memory, offsets)| (path_memory.address() as usize + offsets[i]) as *const c_void),
+                thread,
+            };
+            let loaded_base_address: Result<Option<usize>> = if skip_loaded { find_loaded_dll(&target, loaded_path) } else { Ok(None) };
+            let is_already_loaded: bool = matches!(loaded_base_address, Ok(Some(_)));
+            let mut is_injected: bool = false;
+            let injection: Result<Injection> = match loaded_base_address {
+                Ok(Some(base_address)) => {
+                    info!("{}", format!("{} is already loaded at {:#x} in process ({}), skipping it", loaded_path, base_address, pid));
+                    Ok(Injection { base_address, thread_exit_code: None, is_detached: false })
+                },
+                Ok(None) => checks[i]
+                    .clone()
+                    .and_then(|_| {
+                        is_injected = true;
+                        injector.inject(&target, &payload)
+                    })
+                    .and_then(|injection| {
+                        if verify && !injection.is_detached { verify_injection(&target, loaded_path, injection, injector) } else { Ok(injection) }
+                    }),
+                Err(e) => Err(e),
+            };
+            let is_detached: bool = injection.as_ref().is_ok_and(|injection| injection.is_detached);
+            // the verified base address is taken from the module list instead.
+            let is_base_address_unknown: bool = !verify
+                && is_64_bit_target
+                && injection.as_ref().is_ok_and(|injection| injection.thread_exit_code.is_some());
+            let result: Result<usize> = injection.map(|injection| injection.base_address);
+
+            let failed: bool = result.is_err();
+            is_thread_running = result.as_ref().is_err_and(|e| e.code() == ERROR_TIMEOUT.to_hresult());
+            let remote_dll_path: Option<usize> = remote_paths
+                .as_ref()
+                .filter(|_| cleanup == MemoryCleanup::Keep && !is_already_loaded)
+                .map(|_| payload.remote_dll_path as usize);
+            results.push(DllLoad {
+                result,
+                duration: start.elapsed(),
+                is_detached,
+                is_already_loaded,
+                is_injected,
+                is_base_address_unknown,
+                remote_dll_path,
+            });
+            // the loader lock is likely held by the running thread, so the next loads would block.
+            if is_thread_running || (failed && !keep_going) {
+                break;
+            }
+        }
+
+        match remote_paths {
+            Some((path_memory, _)) if is_thread_running => {
+                warn!("{}", format!("leaking {} bytes at {:?} in process ({}), still used by the remote thread", path_memory.size(), path_memory.leak(), pid));
+            },
+            Some((path_memory, _)) if results.iter().any(|dll_load| dll_load.is_detached) => {
+                info!("{}", format!("leaking {} bytes at {:?} in process ({}), used by the detached threads", path_memory.size(), path_memory.leak(), pid));
+            },
+            Some((path_memory, _)) => match cleanup {
+                MemoryCleanup::Free => path_memory.free()?,
+                MemoryCleanup::ZeroAndFree => path_memory.release()?,
+                MemoryCleanup::Keep => info!("{}", format!("keeping {} bytes at {:?} in process ({})", path_memory.size(), path_memory.leak(), pid)),
+            },
+            None => {},
+        }
+
+        Ok(results)
+    })
+}
+
+
+/// Allocates memory in a process and writes null-terminated UTF-16 strings to it, one after
+/// the other.
+/// 
+/// Returns the allocated memory and the offset of each string from its address.
+fn write_remote_wide_strings(h_process: HANDLE, values: &[&str]) -> Result<(RemoteMemory, Vec<usize>)> {
+    let (bytes, offsets) = encode_wide_strings(values);
+
+    let memory: RemoteMemory = write_remote_bytes(h_process, &bytes, PAGE_READWRITE)?;
+
+    Ok((memory, offsets))
+}
+
+
+/// Encodes strings as null-terminated UTF-16 strings, one after the other, in little-endian
+/// bytes.
+/// 
+/// Each string takes `(len + 1) * 2` bytes, where `len` is its length in UTF-16 code units, so
+/// characters outside the BMP take two code units. Returns the bytes and the offset of each
+/// string in them.
+fn encode_wide_strings(values: &[&str]) -> (Vec<u8>, Vec<usize>) {
+    let mut values_w: Vec<u16> = Vec::new();
+    let mut offsets: Vec<usize> = Vec::with_capacity(values.len());
+    for value in values {
+        offsets.push(values_w.len() * 2); // 2 bytes per UTF-16 code unit.
+        values_w.extend(value.encode_utf16());
+        // the terminator is written explicitly, rather than relying on the allocation being zeroed.
+        values_w.push(0);
+    }
+    let bytes: Vec<u8> = values_w.iter().flat_map(|c| c.to_le_bytes()).collect();
+
+    (bytes, offsets)
+}
+
+
+/// Allocates memory with the given protection in a process and writes bytes to it.
+/// 
+/// Returns the allocated memory, which is released when dropped.
+fn write_remote_bytes(h_process: HANDLE, bytes: &[u8], protection: PAGE_PROTECTION_FLAGS) -> Result<RemoteMemory> {
+    let dw_size_to_write: usize = bytes.len();
+
+    let p_address: *mut c_void = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-virtualallocex.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Memory/fn.VirtualAllocEx.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/VirtualAllocEx.
+        VirtualAllocEx(
+            h_process,
+            None,
+            dw_size_to_write,
+            MEM_COMMIT | MEM_RESERVE,
+            protection,
+        )
+    };
+    if p_address.is_null() {
+        return Err(Error::from_win32());
+    }
+    debug!("address of externally allocated memory: {:?}", p_address.clone());
+    let memory = RemoteMemory { h_process, p_address, dw_size: dw_size_to_write };
+
+    let mut lp_number_of_bytes_written: usize = 0;
+    let result: Result<()> = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-writeprocessmemory.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/Debug/fn.WriteProcessMemory.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/WriteProcessMemory.
+        WriteProcessMemory(
+            h_process,
+            p_address,
+            bytes.as_ptr() as *const c_void,
+            dw_size_to_write,
+            Some(&mut lp_number_of_bytes_written),
+        )
+    };
+
+    if result.is_err() || lp_number_of_bytes_written != dw_size_to_write {
+        // dropping the memory frees it even if it cannot be zeroed out, e.g. without write access.
+        drop(memory);
+        return Err(result.err().unwrap_or_else(|| Error::new(HRESULT(-1), "failed to write the bytes in memory")));
+    }
+
+    Ok(memory)
+}
+
+
+/// Allocates memory in a process and writes a null-terminated UTF-16 string to it.
+/// 
+/// Returns the memory holding the string.
+fn write_remote_wide_string(h_process: HANDLE, value: &str) -> Result<RemoteMemory> {
+    let (memory, _) = write_remote_wide_strings(h_process, &[value])?;
+
+    Ok(memory)
+}
+
+
+/// Memory allocated in a process, zeroed out and released with `release_remote_memory` when
+/// dropped.
+/// 
+/// Returning early from a failure path is enough to release the memory. Once the memory may
+/// still be used by the process (e.g. by a thread left running), it is handed over with `leak`.
+struct RemoteMemory {
+    /// Handle to the process that the memory is allocated in.
+    h_process: HANDLE,
+    /// Address of the memory in the process.
+    p_address: *mut c_void,
+    /// Size of the memory, in bytes.
+    dw_size: usize,
+}
+
+impl RemoteMemory {
+    /// Returns the address of the memory in the process.
+    fn address(&self) -> *mut c_void {
+        self.p_address
+    }
+
+    /// Returns the size of the memory, in bytes.
+    fn size(&self) -> usize {
+        self.dw_size
+    }
+
+    /// Zeroes out and releases the memory, unlike dropping it, failing if it cannot.
+    fn release(self) -> Result<()> {
+        let memory = ManuallyDrop::new(self);
+
+        release_remote_memory(memory.h_process, memory.p_address, memory.dw_size)
+    }
+
+    /// Releases the memory without zeroing it out, failing if it cannot.
+    fn free(self) -> Result<()> {
+        let memory = ManuallyDrop::new(self);
+
+        free_remote_memory(memory.h_process, memory.p_address)
+    }
+
+    /// Leaves the memory allocated in the process, and returns its address.
+    fn leak(self) -> *mut c_void {
+        ManuallyDrop::new(self).p_address
+    }
+}
+
+impl Drop for RemoteMemory {
+    fn drop(&mut self) {
+        // executable memory cannot be zeroed out, so it is released as is.
+        let result: Result<()> = release_remote_memory(self.h_process, self.p_address, self.dw_size)
+            .or_else(|_| free_remote_memory(self.h_process, self.p_address));
+        if let Err(e) = result {
+            warn!("{}", format!("failed to release {} bytes at {:?}: {}", self.dw_size, self.p_address, e.message()));
+        }
+    }
+}
+
+
+/// Zeroes out and releases memory allocated by `write_remote_bytes`, with `free_remote_memory`.
+fn release_remote_memory(h_process: HANDLE, p_address: *mut c_void, dw_size: usize) -> Result<()> {
+    debug!("releasing the allocated memory");
+
+    let mut lp_number_of_bytes_written: usize = 0;
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-writeprocessmemory.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Diagnostics/Debug/fn.WriteProcessMemory.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/WriteProcessMemory.
+        WriteProcessMemory(
+            h_process,
+            p_address,
+            vec![0; dw_size].as_ptr() as *const c_void,
+            dw_size,
+            Some(&mut lp_number_of_bytes_written),
+        )
+    }?;
+
+    if lp_number_of_bytes_written != dw_size {
+        return Err(Error::new(HRESULT(-1), "failed to zero out the allocated memory"));
+    }
+
+    free_remote_memory(h_process, p_address)
+}
+
+
+/// Releases memory allocated by `write_remote_bytes`, without zeroing it out first.
+fn free_remote_memory(h_process: HANDLE, p_address: *mut c_void) -> Result<()> {
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-virtualfreeex.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Memory/fn.VirtualFreeEx.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/VirtualFreeEx.
+        VirtualFreeEx(
+            h_process,
+            p_address,
+            0,
+            MEM_RELEASE,
+        )
+    }?;
+
+    Ok(())
+}
+
+
+/// Runs a function in a new thread of a process, created with the given options, and waits
+/// for it to return with `wait_remote_thread`.
+fn run_remote_thread(
+    h_process: HANDLE,
+    pid: u32,
+    start_address: LPTHREAD_START_ROUTINE,
+    parameter: Option<*const c_void>,
+    thread: ThreadOptions,
+) -> Result<u32> {
+    let h_thread: HANDLE = start_remote_thread(h_process, pid, start_address, parameter, thread)?;
+
+    wait_remote_thread(h_thread, pid, thread.timeout)
+}
+
+
+/// Starts a function in a new thread of a process, created with the given options, and
+/// returns the handle to the thread.
+/// 
+/// If the thread is created suspended, it is resumed with `resume_remote_thread` first.
+fn start_remote_thread(
+    h_process: HANDLE,
+    pid: u32,
+    start_address: LPTHREAD_START_ROUTINE,
+    parameter: Option<*const c_void>,
+    thread: ThreadOptions,
+) -> Result<HANDLE> {
+    let dw_creation_flags: u32 = match thread.before_resume {
+        Some(_) => thread.flags | CREATE_SUSPENDED.0,
+        None => thread.flags,
+    };
+    let mut tid: u32 = 0;
+    let h_thread: HANDLE = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-createremotethread.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.CreateRemoteThread.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/CreateRemoteThread.
+        CreateRemoteThread(
+            h_process,
+            None,
+            thread.stack_size,
+            start_address,
+            parameter,
+            dw_creation_flags,
+            Some(&mut tid),
+        )
+    }?;
+    info!("{}", format!("remote thread started in process ({}): {:?}", pid, h_thread));
+
+    if let Some(before_resume) = thread.before_resume {
+        resume_remote_thread(h_thread, pid, tid, before_resume)?;
+    }
+
+    Ok(h_thread)
+}
+
+
+/// Closes the handle to a thread of a process that loads a DLL, leaving the thread running.
+/// 
+/// The DLL is not waited on, so the returned injection has no base address.
+fn detach_remote_thread(h_thread: HANDLE, pid: u32) -> Result<Injection> {
+    close_handle(h_thread)?;
+    info!("{}", format!("remote thread in process ({}) left running", pid));
+
+    Ok(Injection { base_address: 0, thread_exit_code: None, is_detached: true })
+}
+
+
+/// Resumes a thread of a process created suspended, once `before_resume` returns.
+/// 
+/// If the thread cannot be resumed, it is terminated and its handle is closed, so that the
+/// memory it would use can be released instead of being held by a thread that never runs.
+fn resume_remote_thread(h_thread: HANDLE, pid: u32, tid: u32, before_resume: fn(u32, u32)) -> Result<()> {
+    info!("{}", format!("remote thread {} of process ({}) created suspended", tid, pid));
+    before_resume(pid, tid);
+
+    // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-resumethread.
+    // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.ResumeThread.html.
+    // https://microsoft.github.io/windows-rs/features/#/latest/search/ResumeThread.
+    if unsafe { ResumeThread(h_thread) } != u32::MAX {
+        info!("{}", format!("remote thread {} of process ({}) resumed", tid, pid));
+        return Ok(());
+    }
+
+    let error: Error = Error::from_win32();
+    warn!("{}", format!("terminating remote thread {} of process ({}), which cannot be resumed", tid, pid));
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-terminatethread.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.TerminateThread.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/TerminateThread.
+        if TerminateThread(h_thread, 1).is_ok() {
+            // TerminateThread is asynchronous, the thread is gone once its handle is signaled.
+            WaitForSingleObject(h_thread, INFINITE);
+        }
+    }
+    close_handle(h_thread)?;
+
+    Err(Error::new(error.code(), format!("failed to resume remote thread {} of process ({}): {}", tid, pid, error.message())))
+}
+
+
+/// Waits for a thread of a process to exit and closes its handle.
+/// 
+/// Returns the exit code of the thread, which is the value returned by its function. If the
+/// thread is still running after `timeout`, it is terminated if requested, and an error is
+/// returned: its code is `ERROR_OPERATION_ABORTED` if the thread was terminated, and
+/// `ERROR_TIMEOUT` if it was left running. A failed wait is reported as `ERROR_TIMEOUT` too,
+/// since the thread may still be running and using the memory of its caller.
+fn wait_remote_thread(h_thread: HANDLE, pid: u32, timeout: Option<ThreadTimeout>) -> Result<u32> {
+    let dw_milliseconds: u32 = match timeout {
+        // INFINITE is u32::MAX, so longer timeouts are clamped just below it.
+        Some(timeout) => timeout.duration.as_millis().min((INFINITE - 1) as u128) as u32,
+        None => INFINITE,
+    };
+    let wait_result: WAIT_EVENT = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/synchapi/nf-synchapi-waitforsingleobject.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.WaitForSingleObject.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/WaitForSingleObject.
+        WaitForSingleObject(h_thread, dw_milliseconds)
+    };
+
+    if wait_result == WAIT_TIMEOUT {
+        let error: Error = if timeout.is_some_and(|timeout| timeout.kill) {
+            warn!("{}", format!("terminating remote thread in process ({}) after {} ms", pid, dw_milliseconds));
+            let terminate_result: Result<()> = unsafe {
+                // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-terminatethread.
+                // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.TerminateThread.html.
+                // https://microsoft.github.io/windows-rs/features/#/latest/search/TerminateThread.
+                TerminateThread(h_thread, 1).map(|_| {
+                    // TerminateThread is asynchronous, the thread is gone once its handle is signaled.
+                    WaitForSingleObject(h_thread, INFINITE);
+                })
+            };
+
+            match terminate_result {
+                Ok(()) => Error::new(ERROR_OPERATION_ABORTED.to_hresult(), format!("remote thread in process ({}) terminated after {} ms", pid, dw_milliseconds)),
+                Err(e) => Error::new(ERROR_TIMEOUT.to_hresult(), format!("failed to terminate remote thread in process ({}): {}", pid, e.message())),
+            }
+        } else {
+            Error::new(
+                ERROR_TIMEOUT.to_hresult(),
+                format!("remote thread in process ({}) still running after {} ms, leaving it alone", pid, dw_milliseconds),
+            )
+        };
+
+        close_handle(h_thread)?;
+        return Err(error);
+    }
+    if wait_result == WAIT_FAILED {
+        let error: Error = Error::from_win32();
+        close_handle(h_thread)?;
+        return Err(Error::new(
+            ERROR_TIMEOUT.to_hresult(),
+            format!("failed to wait for remote thread in process ({}), leaving it alone: {}", pid, error.message()),
+        ));
+    }
+
+    let mut dw_exit_code: u32 = 0;
+    let wait_result: Result<()> = unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getexitcodethread.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.GetExitCodeThread.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/GetExitCodeThread.
+        GetExitCodeThread(h_thread, &mut dw_exit_code)
+    };
+
+    close_handle(h_thread)?;
+    wait_result?;
+
+    Ok(dw_exit_code)
+}
+
+
+/// Loads a DLL into a target process with an injector, given its handle.
+/// 
+/// The DLL is waited on to load and verified with `verify_injection` before releasing the memory
+/// that holds the DLL path.
+fn load_dll_by_handle(h_process: HANDLE, pid: u32, dll_path: &str, injector: &dyn Injector) -> Result<()> {
+    let loaded_path: String = canonicalize_dll_path(dll_path);
+    let path_memory: RemoteMemory = write_remote_wide_string(h_process, &loaded_path)?;
+
+    let target = ProcessHandle { pid, handle: h_process };
+    let payload = Payload { dll_path, loaded_path: &loaded_path, remote_dll_path: path_memory.address(), thread: ThreadOptions::default() };
+    let result: Result<Injection> = injector
+        .inject(&target, &payload)
+        .and_then(|injection| verify_injection(&target, &loaded_path, injection, injector));
+
+    path_memory.release()?;
+    result?;
+
+    Ok(())
+}
+
+
+/// Quotes a command-line argument so that it is parsed back as a single argument.
+fn quote_argument(argument: &str) -> String {
+    if !argument.is_empty() && !argument.contains([' ', '\t', '\n', '"']) {
+        return argument.to_owned();
+    }
+
+    // backslashes are literal, unless they precede a double quote.
+    let mut quoted: String = String::from('"');
+    let mut backslashes: usize = 0;
+    for c in argument.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                backslashes = 0;
+            },
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                backslashes = 0;
+            },
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+
+    quoted
+}
+
+
+/// Starts a process in a suspended state.
+fn create_suspended_process(executable_path: &str, args: &[String]) -> Result<PROCESS_INFORMATION> {
+    let mut command_line: Vec<u16> = std::iter::once(executable_path)
+        .chain(args.iter().map(String::as_str))
+        .map(quote_argument)
+        .collect::<Vec<String>>()
+        .join(" ")
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+
+    let startup_info = STARTUPINFOW {
+        cb: size_of::<STARTUPINFOW>().try_into()?,
+        ..Default::default()
+    };
+    let mut process_information: PROCESS_INFORMATION = Default::default();
+    unsafe {
+        // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-createprocessw.
+        // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.CreateProcessW.html.
+        // https://microsoft.github.io/windows-rs/features/#/latest/search/CreateProcessW.
+        CreateProcessW(
+            &HSTRING::from(executable_path),
+            Some(PWSTR(command_line.as_mut_ptr())),
+            None,
+            None,
+            false,
+            CREATE_SUSPENDED,
+            None,
+            None,
+            &startup_info,
+            &mut process_information,
+        )
+    }?;
+    info!("{}", format!("process ({}) started in a suspended state", process_information.dwProcessId));
+
+    Ok(process_information)
+}
+
+
+/// Resumes the main thread of a process started by `create_suspended_process`.
+fn resume_main_thread(process_information: &PROCESS_INFORMATION) -> Result<()> {
+    // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-resumethread.
+    // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.ResumeThread.html.
+    // https://microsoft.github.io/windows-rs/features/#/latest/search/ResumeThread.
+    if unsafe { ResumeThread(process_information.hThread) } == u32::MAX {
+        return Err(Error::from_win32());
+    }
+    info!("{}", format!("process ({}) resumed", process_information.dwProcessId));
+
+    Ok(())
+}
+
+
+/// Terminates a process started by `create_suspended_process` if the DLL failed to load into
+/// it, and closes the handles to the process and to its main thread.
+/// 
+/// Returns the PID of the process if the DLL was loaded.
+fn finish_spawn(process_information: &PROCESS_INFORMATION, result: Result<()>) -> Result<u32> {
+    if result.is_err() {
+        warn!("{}", format!("terminating process ({})", process_information.dwProcessId));
+
+        unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-terminateprocess.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.TerminateProcess.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/TerminateProcess.
+            TerminateProcess(process_information.hProcess, 1)
+        }?;
+    }
+
+    close_handle(process_information.hThread)?;
+    close_handle(process_information.hProcess)?;
+
+    result.map(|_| process_information.dwProcessId)
+}
+
+
+/// Starts a process in a suspended state and loads a DLL into it with
+/// `CreateRemoteThreadInjector` before it starts running.
+/// 
+/// The main thread of the process is resumed only after `LoadLibraryW` returns. If the DLL
+/// cannot be loaded, the process is terminated. Returns the PID of the new process.
+pub fn spawn_and_load(executable_path: &str, args: &[String], dll_path: &str, force: bool) -> Result<u32> {
+    let process_information: PROCESS_INFORMATION = create_suspended_process(executable_path, args)?;
+    let pid: u32 = process_information.dwProcessId;
+
+    let result: Result<()> = check_process_architecture(pid, true)
+        .and_then(|process_arch| if force && !is_wow64_target(process_arch) { Ok(()) } else { check_dll_architecture(pid, dll_path) })
+        .and_then(|_| load_dll_by_handle(process_information.hProcess, pid, dll_path, &CreateRemoteThreadInjector))
+        .and_then(|_| resume_main_thread(&process_information));
+
+    finish_spawn(&process_information, result)
+}
+
+
+/// Starts a process in a suspended state and loads a DLL into it with an "early bird" APC:
+/// a call to `LoadLibraryW` is queued to the main thread of the process before resuming it,
+/// and runs once the loader has initialized the process, before its entry point.
+/// 
+/// The DLL is waited on to show up in the module list of the process for up to `timeout`. If
+/// it does not, the process is terminated. Returns the PID of the new process.
+pub fn spawn_and_load_early_bird(executable_path: &str, args: &[String], dll_path: &str, force: bool, timeout: Duration) -> Result<u32> {
+    let process_information: PROCESS_INFORMATION = create_suspended_process(executable_path, args)?;
+    let pid: u32 = process_information.dwProcessId;
+    let h_process: HANDLE = process_information.hProcess;
+    let target = ProcessHandle { pid, handle: h_process };
+
+    let result: Result<()> = check_process_architecture(pid, false)
+        .and_then(|_| if force { Ok(()) } else { check_dll_architecture(pid, dll_path) })
+        // https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-loadlibraryw.
+        .and_then(|_| get_remote_kernel32_proc_address(&target, "LoadLibraryW"))
+        .and_then(|load_library_w| {
+            debug!("LoadLibraryW address: {:#x}", load_library_w);
+            let p_load_library_w: PAPCFUNC = unsafe { transmute::<usize, PAPCFUNC>(load_library_w) };
+            write_remote_wide_string(h_process, &canonicalize_dll_path(dll_path)).map(|path_memory| (p_load_library_w, path_memory))
+        })
+        .and_then(|(p_load_library_w, path_memory)| {
+            let is_queued: bool = unsafe {
+                // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-queueuserapc.
+                // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.QueueUserAPC.html.
+                // https://microsoft.github.io/windows-rs/features/#/latest/search/QueueUserAPC.
+                QueueUserAPC(p_load_library_w, process_information.hThread, path_memory.address() as usize)
+            } != 0;
+            if !is_queued {
+                return Err(Error::from_win32());
+            }
+            debug!("{}", format!("APC queued to the main thread of process ({})", pid));
+
+            // the APC runs as soon as the main thread is resumed, so the path is left to the
+            // process, which is terminated if the DLL fails to load.
+            let dw_size: usize = path_memory.size();
+            let p_address: *mut c_void = path_memory.leak();
+            resume_main_thread(&process_information)?;
+            wait_for_remote_module(h_process, pid, dll_path, timeout)?;
+            info!("{}", format!("{} loaded into process ({})", dll_path, pid));
+
+            // LoadLibraryW has returned, so the path is no longer used.
+            release_remote_memory(h_process, p_address, dw_size)
+        });
+
+    finish_spawn(&process_information, result)
+}
+
+
+/// Waits for a module to show up in the module list of a process.
+/// 
+/// Fails if the process exits, or if the module is still missing after `timeout`.
+fn wait_for_remote_module(h_process: HANDLE, pid: u32, module_name: &str, timeout: Duration) -> Result<ModuleInfo> {
+    let start: Instant = Instant::now();
+
+    loop {
+        // the module list cannot be read until the loader has initialized the process.
+        if let Ok(module) = find_remote_module(h_process, module_name) {
+            return Ok(module);
+        }
+
+        if start.elapsed() >= timeout {
+            return Err(Error::new(
+                ERROR_TIMEOUT.to_hresult(),
+                format!("{} was not loaded into process ({}) after {} ms", module_name, pid, timeout.as_millis()),
+            ));
+        }
+
+        // the process handle is signaled when the process exits.
+        let wait_result: WAIT_EVENT = unsafe {
+            // https://learn.microsoft.com/en-us/windows/win32/api/synchapi/nf-synchapi-waitforsingleobject.
+            // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/System/Threading/fn.WaitForSingleObject.html.
+            // https://microsoft.github.io/windows-rs/features/#/latest/search/WaitForSingleObject.
+            WaitForSingleObject(h_process, 10)
+        };
+        match wait_result {
+            WAIT_TIMEOUT => {},
+            WAIT_OBJECT_0 => return Err(Error::new(HRESULT(-1), format!("process ({}) exited before {} was loaded", pid, module_name))),
+            _ => return Err(Error::from_win32()),
+        }
+    }
+}
+
+/// Unloads a DLL from a target process.
+/// 
+/// The module can be identified either by its base name or by its full path.
+pub fn unload_dll(pid: u32, module_name: &str) -> Result<()> {
+    let h_process: HANDLE = open_process(pid, PROCESS_ALL_ACCESS)?;
+
+    let result: Result<()> = find_remote_module(h_process, module_name).and_then(|module| {
+        debug!("remote module base address: {:#x}", module.base_address);
+
+        // https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-freelibrary.
+        let free_library: usize = get_remote_kernel32_proc_address(&ProcessHandle { pid, handle: h_process }, "FreeLibrary")?;
+        debug!("FreeLibrary address: {:#x}", free_library);
+        let p_free_library: LPTHREAD_START_ROUTINE = unsafe { transmute::<usize, LPTHREAD_START_ROUTINE>(free_library) };
+
+        let dw_exit_code: u32 = run_remote_thread(
+            h_process,
+            pid,
+            p_free_library,
+            Some(module.base_address as *const c_void),
+            ThreadOptions::default(),
+        )?;
+
+        // the exit code of the remote thread is the value returned by FreeLibrary.
+        if dw_exit_code == 0 {
+            return Err(Error::new(HRESULT(-1), format!("FreeLibrary failed in process ({})", pid)));
+        }
+        info!("{}", format!("module {} unloaded from process ({})", module_name, pid));
+
+        Ok(())
+    });
+
+    close_handle(h_process)?;
+
+    result
+}
+
+
+/// Calls a function exported by a module loaded by a target process.
+/// 
+/// The export is looked up in the export table of the module as loaded in the process. If
+/// `string_arg` is set, it is written to the process as a UTF-16 string whose address is
+/// passed to the function, otherwise the function is called with a null argument. Returns
+/// the exit code of the remote thread, i.e. the low 32 bits of the value returned by the function.
+pub fn call_export(pid: u32, module_name: &str, export_name: &str, string_arg: Option<&str>) -> Result<u32> {
+    with_process(pid, PROCESS_ALL_ACCESS, |h_process| {
+        let module: ModuleInfo = find_remote_module(h_process, module_name)?;
+        debug!("remote module base address: {:#x}", module.base_address);
+
+        let (_, headers) = read_remote_headers(h_process, module.base_address)?;
+        let exports: Vec<pe::Export> = pe::parse_exports(&headers, |rva, size| {
+            read_process_memory_by_handle(h_process, module.base_address + rva as usize, size).map(|(data, _)| data)
+        })?;
+
+        let export: &pe::Export = match exports.iter().find(|export| export.name == export_name) {
+            Some(export) => export,
+            None => {
+                let names: Vec<&str> = exports.iter().map(|export| export.name.as_str()).collect();
+                debug!("{}", format!("exports of {}: {}", module.name, names.join(", ")));
+
+                return Err(Error::new(HRESULT(-1), format!("export {} not found in module {}", export_name, module.name)));
+            },
+        };
+        if export.is_forwarded {
+            return Err(Error::new(HRESULT(-1), format!("export {} of module {} is forwarded to another module", export_name, module.name)));
+        }
+
+        let export_address: usize = module.base_address + export.rva as usize;
+        debug!("export address: {:#x}", export_address);
+
+        let start_address: LPTHREAD_START_ROUTINE = unsafe {
+            transmute::<usize, LPTHREAD_START_ROUTINE>(export_address)
+        };
+
+        let remote_arg: Option<RemoteMemory> = string_arg
+            .map(|value| write_remote_wide_string(h_process, value))
+            .transpose()?;
+
+        let result: Result<u32> = run_remote_thread(
+            h_process,
+            pid,
+            start_address,
+            remote_arg.as_ref().map(|arg_memory| arg_memory.address() as *const c_void),
+            ThreadOptions::default(),
+        );
+
+        if let Some(arg_memory) = remote_arg {
+            arg_memory.release()?;
+        }
+
+        result
+    })
+}
+
+
+
+/// Returns whether a module is loaded by a target process.
+/// 
+/// The module can be identified either by its base name or by its full path.
+pub fn is_module_loaded(pid: u32, module_name: &str) -> Result<bool> {
+    with_process(pid, PROCESS_QUERY_INFORMATION | PROCESS_VM_READ, |h_process| {
+        Ok(find_remote_module(h_process, module_name).is_ok())
+    })
+}
+
+
+/// Maximum number of `FreeLibrary` calls issued by `reload_dll` before giving up.
+const MAX_FREE_LIBRARY_CALLS: u32 = 16;
+
+/// Replaces a DLL loaded by a target process with a new build of the same DLL.
+/// 
+/// The DLL currently loaded is matched by base name, so the new build can live at a different
+/// path (e.g. when the old file is still locked on disk). The old module is unloaded until it
+/// disappears from the module list of the target, which takes more than one `FreeLibrary` call
+/// if its reference count is greater than one. If the module is not loaded, the new build
+/// is simply loaded into the target.
+/// 
+/// Returns the outcome of loading the new build into the target process, loaded with an
+/// injector and the options of `load_dlls`.
+pub fn reload_dll(pid: u32, dll_path: &str, options: &InjectionOptions, injector: &dyn Injector) -> Result<DllLoad> {
+    let Some(module_name) = Path::new(dll_path).file_name().and_then(|name| name.to_str()) else {
+        return Err(Error::new(HRESULT(-1), format!("invalid DLL path {:#}", dll_path)));
+    };
+
+    let mut free_library_calls: u32 = 0;
+    while is_module_loaded(pid, module_name)? {
+        if free_library_calls == MAX_FREE_LIBRARY_CALLS {
+            return Err(Error::new(
+                HRESULT(-1),
+                format!("module {:#} is still loaded after {} FreeLibrary calls", module_name, free_library_calls),
+            ));
+        }
+        if free_library_calls == 1 {
+            warn!("{}", format!("module {} is still loaded, its reference count is greater than one", module_name));
+        }
+
+        unload_dll(pid, module_name)?;
+        free_library_calls += 1;
+
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    if free_library_calls == 0 {
+        info!("{}", format!("module {} is not loaded in process ({}), loading it", module_name, pid));
+    }
+
+    Ok(load_dlls(pid, &[dll_path], options, injector)?.remove(0))
+}
+
+
+/// Reloads DLLs in order in a target process with `reload_dll`.
+/// 
+/// Follows the same conventions as `load_dlls`: reloading stops at the first DLL that fails,
+/// unless `options.keep_going` is set.
+pub fn reload_dlls(pid: u32, dll_paths: &[&str], options: &InjectionOptions, injector: &dyn Injector) -> Result<Vec<DllLoad>> {
+    let mut results: Vec<DllLoad> = Vec::with_capacity(dll_paths.len());
+    for (i, dll_path) in dll_paths.iter().enumerate() {
+        let start: Instant = Instant::now();
+        let options = InjectionOptions { remote_paths: options.remote_paths.map(|remote_paths| &remote_paths[i..=i]), ..*options };
+        let dll_load: DllLoad = match validate_pe(Path::new(dll_path)).and_then(|_| reload_dll(pid, dll_path, &options, injector)) {
+            Ok(dll_load) => DllLoad { duration: start.elapsed(), ..dll_load },
+            Err(e) => DllLoad {
+                result: Err(e),
+                duration: start.elapsed(),
+                is_detached: false,
+                is_already_loaded: false,
+                is_injected: false,
+                is_base_address_unknown: false,
+                remote_dll_path: None,
+            },
+        };
+
+        let failed: bool = dll_load.result.is_err();
+        results.push(dll_load);
+        if failed && !options.keep_going {
+            break;
+        }
+    }
+
+    Ok(results)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::process::{Child, Command, Stdio};
+
+    /// Spawns a process that waits on its stdin until it is killed, e.g. `cmd.exe /k`.
+    fn spawn_target(executable: &str, args: &[&str]) -> Child {
+        Command::new(executable)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("failed to spawn the target process")
+    }
+
+    /// Kills a process spawned by `spawn_target` and waits for it to exit.
+    fn kill_target(mut target: Child) {
+        let _ = target.kill();
+        let _ = target.wait();
+    }
+
+    /// Waits for the loader of a process spawned by `spawn_target` to list a module, among the
+    /// modules listed with a `LIST_MODULES_*` flag.
+    fn wait_for_module(h_process: HANDLE, filter: ENUM_PROCESS_MODULES_EX_FLAGS, module_name: &str) -> ModuleInfo {
+        for _ in 0..100 {
+            let module: Option<ModuleInfo> = get_process_module_handles(h_process, filter)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|h_module| get_module_info(h_process, h_module).ok())
+                .find(|module| module_matches(module, module_name));
+            if let Some(module) = module {
+                return module;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        panic!("{} was not loaded by the target process", module_name);
+    }
+
+    #[test]
+    fn resolves_kernel32_functions_from_the_target() {
+        let target: Child = spawn_target("C:\\Windows\\System32\\cmd.exe", &["/k"]);
+        let h_process: HANDLE = open_process(target.id(), PROCESS_ALL_ACCESS).unwrap();
+        let kernel32: ModuleInfo = wait_for_module(h_process, LIST_MODULES_ALL, "kernel32.dll");
+
+        let address: Result<usize> = get_remote_kernel32_proc_address(&ProcessHandle { pid: target.id(), handle: h_process }, "LoadLibraryW");
+        close_handle(h_process).unwrap();
+        kill_target(target);
+
+        let local_address: usize = get_kernel32_proc_address(s!("LoadLibraryW")).unwrap().unwrap() as usize;
+        let local_base_address: usize = get_module_handle(s!("kernel32.dll")).unwrap().0 as usize;
+        assert_eq!(address.unwrap(), kernel32.base_address + (local_address - local_base_address));
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn resolves_kernel32_functions_from_wow64_targets() {
+        let target: Child = spawn_target("C:\\Windows\\SysWOW64\\cmd.exe", &["/k"]);
+        let h_process: HANDLE = open_process(target.id(), PROCESS_ALL_ACCESS).unwrap();
+        let kernel32: ModuleInfo = wait_for_module(h_process, LIST_MODULES_32BIT, "kernel32.dll");
+
+        let address: Result<usize> = get_remote_kernel32_proc_address(&ProcessHandle { pid: target.id(), handle: h_process }, "LoadLibraryW");
+        close_handle(h_process).unwrap();
+        kill_target(target);
+
+        let address: usize = address.unwrap();
+        assert!(kernel32.contains(address), "{:#x} is outside of the 32-bit kernel32.dll at {:#x}", address, kernel32.base_address);
+        assert!(address <= u32::MAX as usize);
+    }
+
+    #[test]
+    fn tells_missing_dlls_apart_from_enumeration_failures() {
+        let target: Child = spawn_target("C:\\Windows\\System32\\cmd.exe", &["/k"]);
+        let h_process: HANDLE = open_process(target.id(), PROCESS_ALL_ACCESS).unwrap();
+        wait_for_module(h_process, LIST_MODULES_ALL, "kernel32.dll");
+        // the modules cannot be enumerated without PROCESS_VM_READ.
+        let h_limited_process: HANDLE = open_process(target.id(), PROCESS_QUERY_LIMITED_INFORMATION).unwrap();
+
+        let missing: Result<Option<usize>> = find_loaded_dll(&ProcessHandle { pid: target.id(), handle: h_process }, "C:\\hooks\\missing.dll");
+        let unlisted: Result<Option<usize>> = find_loaded_dll(&ProcessHandle { pid: target.id(), handle: h_limited_process }, "C:\\hooks\\missing.dll");
+        close_handle(h_limited_process).unwrap();
+        close_handle(h_process).unwrap();
+        kill_target(target);
+
+        assert_eq!(missing.unwrap(), None);
+        assert!(unlisted.is_err());
+    }
+
+    /// Technique that fails once the path of the DLL is written to the target, recording the
+    /// memory region that holds it.
+    #[derive(Default)]
+    struct FailingInjector {
+        path_region: std::cell::RefCell<Option<MemoryRegion>>,
+    }
+
+    impl Injector for FailingInjector {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        fn inject(&self, target: &ProcessHandle, payload: &Payload) -> Result<Injection> {
+            *self.path_region.borrow_mut() = query_memory_region(target.handle, payload.remote_dll_path as usize);
+
+            Err(Error::new(HRESULT(-1), "mid-way failure"))
+        }
+    }
+
+    #[test]
+    fn releases_remote_memory_on_failure() {
+        let target: Child = spawn_target("C:\\Windows\\System32\\cmd.exe", &["/k"]);
+        let h_process: HANDLE = open_process(target.id(), PROCESS_ALL_ACCESS).unwrap();
+        wait_for_module(h_process, LIST_MODULES_ALL, "kernel32.dll");
+
+        let injector = FailingInjector::default();
+        let loads: Result<Vec<DllLoad>> = load_dlls(target.id(), &["C:\\Windows\\System32\\version.dll"], &InjectionOptions::default(), &injector);
+        let path_region: Option<MemoryRegion> = injector.path_region.take();
+        let region: Option<MemoryRegion> = path_region.as_ref().and_then(|path_region| query_memory_region(h_process, path_region.base_address));
+        close_handle(h_process).unwrap();
+        kill_target(target);
+
+        assert!(loads.unwrap()[0].result.is_err());
+        assert_eq!(path_region.expect("the path of the DLL was not written").state, MEM_COMMIT);
+        assert_eq!(region.unwrap().state, MEM_FREE);
+    }
+
+    /// Returns the UTF-16 code units of the string at an offset of bytes encoded by
+    /// `encode_wide_strings`, up to its terminator.
+    fn decode_wide_string(bytes: &[u8], offset: usize) -> Vec<u16> {
+        bytes[offset..]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|&c| c != 0)
+            .collect()
+    }
+
+    #[test]
+    fn classifies_system_pids_as_critical() {
+        for pid in SYSTEM_PIDS {
+            assert!(is_critical_system_process(pid, ""));
+            assert!(is_critical_system_process(pid, "notepad.exe"));
+        }
+    }
+
+    #[test]
+    fn classifies_critical_names_case_insensitively() {
+        for name in CRITICAL_PROCESS_NAMES {
+            assert!(is_critical_system_process(1234, name), "{}", name);
+            assert!(is_critical_system_process(1234, &name.to_uppercase()), "{}", name);
+        }
+        assert!(is_critical_system_process(1234, "LSASS.exe"));
+    }
+
+    #[test]
+    fn does_not_classify_look_alike_names_as_critical() {
+        assert!(!is_critical_system_process(1234, "csrss.exe.bak"));
+        assert!(!is_critical_system_process(1234, "csrss"));
+        assert!(!is_critical_system_process(1234, "my-winlogon.exe"));
+    }
+
+    #[test]
+    fn does_not_classify_ordinary_processes_as_critical() {
+        assert!(!is_critical_system_process(1234, "notepad.exe"));
+        assert!(!is_critical_system_process(1234, ""));
+        assert!(check_not_critical_process(std::process::id()).is_ok());
+        assert!(check_not_critical_process(4).is_err());
+    }
+
+    #[test]
+    fn encodes_ascii_paths() {
+        let path: &str = "C:\\hooks\\hook.dll";
+        let (bytes, offsets) = encode_wide_strings(&[path]);
+
+        assert_eq!(bytes.len(), (path.len() + 1) * 2);
+        assert_eq!(offsets, [0]);
+        assert_eq!(bytes[bytes.len() - 2..], [0, 0]);
+        assert_eq!(decode_wide_string(&bytes, 0), path.encode_utf16().collect::<Vec<u16>>());
+    }
+
+    #[test]
+    fn encodes_non_ascii_paths() {
+        // CJK characters take one code unit, the emoji takes a surrogate pair.
+        for path in ["C:\\フック\\注入.dll", "C:\\hooks\\\u{1f577}.dll"] {
+            let len: usize = path.encode_utf16().count();
+            let (bytes, offsets) = encode_wide_strings(&[path]);
+
+            assert_eq!(bytes.len(), (len + 1) * 2);
+            assert_eq!(bytes.len() % 2, 0);
+            assert_eq!(offsets, [0]);
+            assert_eq!(bytes[bytes.len() - 2..], [0, 0]);
+            assert_eq!(String::from_utf16(&decode_wide_string(&bytes, 0)).unwrap(), path);
+        }
+        assert_eq!("\u{1f577}".encode_utf16().count(), 2);
+    }
+
+    #[test]
+    fn encodes_several_paths() {
+        let paths: [&str; 3] = ["a.dll", "C:\\注入\\b.dll", "C:\\\u{1f577}\\c.dll"];
+        let (bytes, offsets) = encode_wide_strings(&paths);
+
+        let lens: Vec<usize> = paths.iter().map(|path| path.encode_utf16().count()).collect();
+        assert_eq!(bytes.len(), lens.iter().map(|len| (len + 1) * 2).sum::<usize>());
+        assert_eq!(offsets, [0, (lens[0] + 1) * 2, (lens[0] + 1) * 2 + (lens[1] + 1) * 2]);
+        for (i, path) in paths.iter().enumerate() {
+            assert_eq!(offsets[i] % 2, 0);
+            // each string is followed by its terminator.
+            let end: usize = offsets[i] + lens[i] * 2;
+            assert_eq!(bytes[end..end + 2], [0, 0]);
+            assert_eq!(String::from_utf16(&decode_wide_string(&bytes, offsets[i])).unwrap(), *path);
+        }
+    }
+
+    #[test]
+    fn encodes_no_paths() {
+        assert_eq!(encode_wide_strings(&[]), (Vec::new(), Vec::new()));
+    }
+
+    #[test]
+    fn matches_globs_with_a_star() {
+        assert!(glob_matches("*.exe", "notepad.exe", false));
+        assert!(glob_matches("*.exe", ".exe", false));
+        assert!(!glob_matches("*.exe", "notepad.dll", false));
+        assert!(!glob_matches("*.exe", "notepad.exe.bak", false));
+        assert!(glob_matches("*pad*", "notepad.exe", false));
+    }
+
+    #[test]
+    fn matches_globs_with_a_question_mark() {
+        assert!(glob_matches("chr?me.exe", "chrome.exe", false));
+        assert!(glob_matches("chr?me.exe", "chrxme.exe", false));
+        assert!(!glob_matches("chr?me.exe", "chrme.exe", false));
+        assert!(!glob_matches("chr?me.exe", "chroome.exe", false));
+    }
+
+    #[test]
+    fn matches_literal_globs() {
+        assert!(glob_matches("notepad.exe", "notepad.exe", false));
+        assert!(!glob_matches("notepad.exe", "notepad", false));
+        assert!(!glob_matches("notepad", "notepad.exe", false));
+    }
+
+    #[test]
+    fn matches_globs_ignoring_case() {
+        assert!(glob_matches("*.EXE", "notepad.exe", false));
+        assert!(glob_matches("chr?me.exe", "Chrome.EXE", false));
+    }
+
+    #[test]
+    fn matches_empty_globs_with_empty_names_only() {
+        assert!(glob_matches("", "", false));
+        assert!(!glob_matches("", "notepad.exe", false));
+        assert!(glob_matches("*", "", false));
+    }
+
+    #[test]
+    fn matches_globs_with_a_trailing_star() {
+        assert!(glob_matches("note*", "notepad.exe", false));
+        assert!(glob_matches("note*", "note", false));
+        assert!(glob_matches("note**", "note", false));
+        assert!(!glob_matches("note*", "not", false));
+    }
+
+    #[test]
+    fn matches_names_with_or_without_extension() {
+        assert!(name_matches("notepad.exe", "notepad.exe", false));
+        assert!(name_matches("notepad", "notepad.exe", false));
+        assert!(!name_matches("notepad.exe", "notepad", false));
+        assert!(!name_matches("notepad", "notepad.com", false));
+    }
+
+    #[test]
+    fn matches_names_ignoring_case() {
+        assert!(name_matches("NOTEPAD", "Notepad.exe", false));
+        assert!(name_matches("notepad.EXE", "NotePad.exe", false));
+    }
+
+    #[test]
+    fn does_not_match_partial_names() {
+        assert!(!name_matches("note", "notepad.exe", false));
+        assert!(!name_matches("pad.exe", "notepad.exe", false));
+        assert!(!name_matches("notepad.ex", "notepad.exe", false));
+        assert!(!name_matches("", "notepad.exe", false));
+    }
+
+    #[test]
+    fn matches_names_by_pattern_mode() {
+        assert!(matches!(NamePattern::new("notepad", false), NamePattern::Name(_, false)));
+        assert!(matches!(NamePattern::new("note*", false), NamePattern::Glob(_, false)));
+
+        assert!(NamePattern::Name("Notepad".to_owned(), false).matches("notepad.exe"));
+        assert!(NamePattern::Exact("notepad.exe".to_owned()).matches("notepad.exe"));
+        assert!(!NamePattern::Exact("Notepad.exe".to_owned()).matches("notepad.exe"));
+        assert!(!NamePattern::Exact("notepad".to_owned()).matches("notepad.exe"));
+
+        // wildcards are matched by glob patterns only.
+        assert!(NamePattern::new("NOTE*", false).matches("notepad.exe"));
+        assert!(!NamePattern::Name("note*".to_owned(), false).matches("notepad.exe"));
+        assert!(!NamePattern::Exact("note*".to_owned()).matches("notepad.exe"));
+    }
+
+    #[test]
+    fn matches_names_case_sensitively() {
+        assert!(name_matches("notepad", "notepad.exe", true));
+        assert!(!name_matches("Notepad", "notepad.exe", true));
+        assert!(!name_matches("notepad", "notepad.EXE", true));
+        assert!(glob_matches("note*", "notepad.exe", true));
+        assert!(!glob_matches("NOTE*", "notepad.exe", true));
+
+        // unlike exact names, case-sensitive names keep wildcards and the optional extension.
+        assert!(NamePattern::new("note*", true).matches("notepad.exe"));
+        assert!(NamePattern::new("notepad", true).matches("notepad.exe"));
+        assert!(!NamePattern::new("Notepad", true).matches("notepad.exe"));
+    }
+}
@@ -0,0 +1,57 @@
+//! Integration tests that load DLLs into processes spawned for the purpose.
+
+#![cfg(windows)]
+
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Child, Command, Output, Stdio};
+
+use serde_json::Value;
+
+
+/// Returns the path of a file in the system directory.
+fn system_path(name: &str) -> PathBuf {
+    PathBuf::from(env::var_os("SystemRoot").unwrap_or_else(|| "C:\\Windows".into())).join("System32").join(name)
+}
+
+
+/// Spawns a command prompt that waits on its stdin until it is killed.
+fn spawn_target() -> Child {
+    Command::new(system_path("cmd.exe"))
+        .arg("/k")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("failed to spawn the target process")
+}
+
+
+#[test]
+fn loads_dll_from_non_ascii_path() {
+    // CJK characters and a character outside the BMP, which takes a surrogate pair in UTF-16.
+    let directory: PathBuf = env::temp_dir().join(format!("dll-spider-test-{}", std::process::id())).join("注入 \u{1f577}");
+    fs::create_dir_all(&directory).unwrap();
+    let dll_path: PathBuf = directory.join("version.dll");
+    fs::copy(system_path("version.dll"), &dll_path).unwrap();
+
+    let mut target: Child = spawn_target();
+    let output: Output = Command::new(env!("CARGO_BIN_EXE_dll-spider"))
+        .arg("load")
+        .arg(&dll_path)
+        .args(["-p", &target.id().to_string(), "--yes", "--output", "json", "--retry", "10"])
+        .output()
+        .expect("failed to run dll-spider");
+
+    // the target holds the DLL until it exits.
+    target.kill().unwrap();
+    target.wait().unwrap();
+    let _ = fs::remove_dir_all(directory.parent().unwrap());
+
+    assert!(output.status.success(), "dll-spider failed: {}", String::from_utf8_lossy(&output.stderr));
+    let report: Value = serde_json::from_slice(&output.stdout).expect("the summary should be JSON");
+    assert_eq!(report["status"], "loaded");
+    assert!(report["module_path"].as_str().is_some_and(|module_path| module_path.contains("注入 \u{1f577}")));
+    assert!(report["remote_base"].is_string());
+}